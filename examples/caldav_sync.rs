@@ -0,0 +1,54 @@
+//! Syncs a file store with a CalDAV calendar: pushes blocks moved locally since
+//! the previous run, then pulls the server's events for the given days. What
+//! was pulled is kept next to the store, in `<STORE>.caldav.json`.
+//!
+//! cargo run --example caldav_sync --features caldav -- \
+//!     http://localhost:5232/user/calendar/ calendar.json 2024-05-01 2024-05-02
+
+use cal_ender::prelude::*;
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let [calendar_url, store_path, start, end] = args.as_slice() else {
+        return Err("usage: caldav_sync <CALENDAR_URL> <STORE> <START_DAY> <END_DAY>".into());
+    };
+
+    let client = match (
+        std::env::var("CALDAV_USER"),
+        std::env::var("CALDAV_PASSWORD"),
+    ) {
+        (Ok(username), Ok(password)) => {
+            CalDavClient::new(calendar_url).with_basic_auth(&username, &password)
+        }
+        _ => CalDavClient::new(calendar_url),
+    };
+    let (start, end) = (Day(start.clone()), Day(end.clone()));
+    let mut store = FileStore::open(store_path)?;
+    let sync_state_path = format!("{store_path}.caldav.json");
+    let mut sync_state: SyncState = match std::fs::read_to_string(&sync_state_path) {
+        Ok(json) => serde_json::from_str(&json)?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => SyncState::new(),
+        Err(e) => return Err(e.into()),
+    };
+
+    // push before pulling, a pull would overwrite the local moves
+    for result in client.push_moves(&store, &mut sync_state) {
+        match result {
+            Ok(remote_event) => println!("pushed {}", remote_event.event.block.label),
+            Err(e) => println!("skipped: {e}"),
+        }
+    }
+
+    for remote_event in client.pull(&mut store, &mut sync_state, &start, &end)? {
+        let block = remote_event.event.block;
+        println!(
+            "{} {} {}",
+            remote_event.event.date,
+            TimeFormat::default().format_range(block.start_minute, block.end_minute),
+            block.label
+        );
+    }
+    std::fs::write(&sync_state_path, serde_json::to_string(&sync_state)?)?;
+
+    Ok(())
+}
@@ -0,0 +1,92 @@
+use crate::prelude::*;
+
+/// Whether an attendee is coming, after iCalendar's PARTSTAT.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RsvpStatus {
+    #[default]
+    NeedsAction,
+    Accepted,
+    Declined,
+    Tentative,
+}
+
+impl RsvpStatus {
+    pub fn from_partstat(partstat: &str) -> Self {
+        match partstat.to_uppercase().as_str() {
+            "ACCEPTED" => RsvpStatus::Accepted,
+            "DECLINED" => RsvpStatus::Declined,
+            "TENTATIVE" => RsvpStatus::Tentative,
+            _ => RsvpStatus::NeedsAction,
+        }
+    }
+
+    pub fn to_partstat(&self) -> &'static str {
+        match self {
+            RsvpStatus::NeedsAction => "NEEDS-ACTION",
+            RsvpStatus::Accepted => "ACCEPTED",
+            RsvpStatus::Declined => "DECLINED",
+            RsvpStatus::Tentative => "TENTATIVE",
+        }
+    }
+}
+
+impl Display for RsvpStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rsvp_status = match self {
+            RsvpStatus::NeedsAction => "no reply",
+            RsvpStatus::Accepted => "accepted",
+            RsvpStatus::Declined => "declined",
+            RsvpStatus::Tentative => "maybe",
+        };
+        write!(f, "{rsvp_status}")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Attendee {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub email: String,
+    #[serde(default)]
+    pub rsvp: RsvpStatus,
+}
+
+impl Display for Attendee {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.name {
+            Some(name) => write!(f, "{name} <{}>", self.email),
+            None => write!(f, "{}", self.email),
+        }
+    }
+}
+
+/// Details of an event beyond its time and label. Every field is optional and
+/// left out of JSON when unset.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BlockMetadata {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attendees: Vec<Attendee>,
+    /// A CSS color filling the block in place of its type's color.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+}
+
+impl BlockMetadata {
+    pub fn is_empty(&self) -> bool {
+        *self == BlockMetadata::default()
+    }
+
+    /// The URL, if it's safe to open as a link.
+    pub fn get_link(&self) -> Option<&str> {
+        self.url
+            .as_deref()
+            .filter(|url| url.starts_with("https://") || url.starts_with("http://"))
+    }
+}
@@ -1,51 +1,529 @@
 use crate::prelude::*;
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+/// Calendar of blocks that don't name one.
+pub const DEFAULT_CALENDAR_ID: &str = "default";
+
+const MINUTES_IN_DAY: u32 = 1440;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum CalendarBlockType {
     Busy,
     Available,
-    Wrapper,
+    Tentative,
+    OutOfOffice,
+    Focus,
+    Travel,
+}
+
+/// How much a block keeps its time from being booked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Busyness {
+    Free,
+    /// Not a conflict, but not offered as a free slot either.
+    Tentative,
+    Busy,
+}
+
+/// Everything that differs between block types.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BlockTypeInfo {
+    pub name: &'static str,
+    /// Class styling the type's blocks in `app.css`.
+    pub css_class: &'static str,
+    pub busyness: Busyness,
+    /// Whether overlapping another such block is reported as a conflict. Focus
+    /// time is busy, but gives way to meetings rather than clashing with them.
+    pub can_conflict: bool,
+    /// Of blocks covering the same time, the higher priority nests inside and draws on top.
+    pub stacking_priority: u8,
+}
+
+impl CalendarBlockType {
+    pub const ALL: [CalendarBlockType; 6] = [
+        CalendarBlockType::Busy,
+        CalendarBlockType::Available,
+        CalendarBlockType::Tentative,
+        CalendarBlockType::OutOfOffice,
+        CalendarBlockType::Focus,
+        CalendarBlockType::Travel,
+    ];
+
+    /// The table is fixed on purpose: every front end, the conflict checks and
+    /// the ICS export agree on what a type means. A type that should count
+    /// differently needs a variant of its own.
+    pub fn info(&self) -> BlockTypeInfo {
+        match self {
+            CalendarBlockType::Busy => BlockTypeInfo {
+                name: "Busy",
+                css_class: "busy",
+                busyness: Busyness::Busy,
+                can_conflict: true,
+                stacking_priority: 5,
+            },
+            CalendarBlockType::Available => BlockTypeInfo {
+                name: "Available",
+                css_class: "available",
+                busyness: Busyness::Free,
+                can_conflict: false,
+                stacking_priority: 0,
+            },
+            CalendarBlockType::Tentative => BlockTypeInfo {
+                name: "Tentative",
+                css_class: "tentative",
+                busyness: Busyness::Tentative,
+                can_conflict: false,
+                stacking_priority: 4,
+            },
+            CalendarBlockType::OutOfOffice => BlockTypeInfo {
+                name: "Out of Office",
+                css_class: "out-of-office",
+                busyness: Busyness::Busy,
+                can_conflict: true,
+                stacking_priority: 1,
+            },
+            CalendarBlockType::Focus => BlockTypeInfo {
+                name: "Focus",
+                css_class: "focus",
+                busyness: Busyness::Busy,
+                can_conflict: false,
+                stacking_priority: 2,
+            },
+            CalendarBlockType::Travel => BlockTypeInfo {
+                name: "Travel",
+                css_class: "travel",
+                busyness: Busyness::Busy,
+                can_conflict: true,
+                stacking_priority: 3,
+            },
+        }
+    }
+
+    pub fn busyness(&self) -> Busyness {
+        self.info().busyness
+    }
 }
 
 impl Display for CalendarBlockType {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let block_type = match self {
-            CalendarBlockType::Wrapper => "Wrapper",
-            CalendarBlockType::Busy => "Busy",
-            CalendarBlockType::Available => "Available",
-        };
-        write!(f, "{block_type}")
+        write!(f, "{}", self.info().name)
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct CalendarBlock {
+    #[serde(default = "Uuid::new_v4")]
     pub id: Uuid,
     pub start_minute: u32,
     pub end_minute: u32, // exclusive
     pub block_type: CalendarBlockType,
+    #[serde(skip)]
     pub subtree_depth: usize,
     pub label: String,
+    #[serde(default = "get_default_calendar_id")]
+    pub calendar_id: String,
+    #[serde(default, skip_serializing_if = "BlockMetadata::is_empty")]
+    pub metadata: BlockMetadata,
+}
+
+/// Why a block or day from outside, such as a request body or an input file, can't be used.
+#[derive(Debug, Error, Clone, PartialEq)]
+pub enum ValidationError {
+    #[error("block \"{0}\" ends before it starts")]
+    EndsBeforeStart(String),
+    #[error("block \"{0}\" ends after the end of the day")]
+    EndsAfterDay(String),
+    #[error("invalid day \"{0}\", expected YYYY-MM-DD")]
+    InvalidDay(String),
+}
+
+fn get_default_calendar_id() -> String {
+    DEFAULT_CALENDAR_ID.to_string()
+}
+
+/// How one block's interval relates to another's, after Allen's interval algebra.
+/// Blocks are half-open, so a block ending at 10:00 meets, but does not overlap,
+/// a block starting at 10:00.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CalendarBlockRelation {
+    /// Ends before the other starts, with a gap between them.
+    Before,
+    /// Ends exactly when the other starts.
+    Meets,
+    /// Starts first and ends inside the other.
+    Overlaps,
+    /// Starts with the other and ends first.
+    Starts,
+    /// Lies strictly inside the other.
+    During,
+    /// Starts inside the other and ends with it.
+    Finishes,
+    Equals,
+    /// Inverse of `Finishes`.
+    FinishedBy,
+    /// Inverse of `During`.
+    Contains,
+    /// Inverse of `Starts`.
+    StartedBy,
+    /// Inverse of `Overlaps`.
+    OverlappedBy,
+    /// Inverse of `Meets`.
+    MetBy,
+    /// Inverse of `Before`.
+    After,
 }
 
-#[derive(Debug)]
-pub enum CalendarBlockOverlap {
-    Swallows,
-    GetsSwallowed,
+impl CalendarBlockRelation {
+    pub fn inverse(&self) -> Self {
+        match self {
+            CalendarBlockRelation::Before => CalendarBlockRelation::After,
+            CalendarBlockRelation::Meets => CalendarBlockRelation::MetBy,
+            CalendarBlockRelation::Overlaps => CalendarBlockRelation::OverlappedBy,
+            CalendarBlockRelation::Starts => CalendarBlockRelation::StartedBy,
+            CalendarBlockRelation::During => CalendarBlockRelation::Contains,
+            CalendarBlockRelation::Finishes => CalendarBlockRelation::FinishedBy,
+            CalendarBlockRelation::Equals => CalendarBlockRelation::Equals,
+            CalendarBlockRelation::FinishedBy => CalendarBlockRelation::Finishes,
+            CalendarBlockRelation::Contains => CalendarBlockRelation::During,
+            CalendarBlockRelation::StartedBy => CalendarBlockRelation::Starts,
+            CalendarBlockRelation::OverlappedBy => CalendarBlockRelation::Overlaps,
+            CalendarBlockRelation::MetBy => CalendarBlockRelation::Meets,
+            CalendarBlockRelation::After => CalendarBlockRelation::Before,
+        }
+    }
+
+    /// The blocks share no minute.
+    pub fn is_disjoint(&self) -> bool {
+        matches!(
+            self,
+            CalendarBlockRelation::Before
+                | CalendarBlockRelation::Meets
+                | CalendarBlockRelation::MetBy
+                | CalendarBlockRelation::After
+        )
+    }
+
+    /// Every minute of the block is also covered by the other.
+    pub fn is_contained(&self) -> bool {
+        matches!(
+            self,
+            CalendarBlockRelation::Starts
+                | CalendarBlockRelation::During
+                | CalendarBlockRelation::Finishes
+                | CalendarBlockRelation::Equals
+        )
+    }
+
+    /// Every minute of the other block is also covered by the block.
+    pub fn is_containing(&self) -> bool {
+        self.inverse().is_contained()
+    }
+
+    /// The blocks share time, but neither contains the other.
+    pub fn is_partial_overlap(&self) -> bool {
+        matches!(
+            self,
+            CalendarBlockRelation::Overlaps | CalendarBlockRelation::OverlappedBy
+        )
+    }
+}
+
+/// Canonical block ordering: earlier start first, then the longer block, so a
+/// container sorts before the blocks it holds. Blocks covering the same time
+/// go by stacking priority, then id to break remaining ties.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CalendarBlockOrder {
+    pub start_minute: u32,
+    pub end_minute: u32,
+    pub stacking_priority: u8,
+    pub id: Uuid,
+}
+
+impl Ord for CalendarBlockOrder {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.start_minute
+            .cmp(&other.start_minute)
+            .then_with(|| other.end_minute.cmp(&self.end_minute))
+            .then_with(|| self.stacking_priority.cmp(&other.stacking_priority))
+            .then_with(|| self.id.cmp(&other.id))
+    }
+}
+
+impl PartialOrd for CalendarBlockOrder {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
 }
 
 impl CalendarBlock {
-    pub fn does_overlap(&self, block: CalendarBlock) -> Option<CalendarBlockOverlap> {
-        if self.start_minute >= block.end_minute || self.end_minute < block.start_minute {
-            return None;
+    pub fn sort_key(&self) -> CalendarBlockOrder {
+        CalendarBlockOrder {
+            start_minute: self.start_minute,
+            end_minute: self.end_minute,
+            stacking_priority: self.block_type.info().stacking_priority,
+            id: self.id,
+        }
+    }
+
+    /// Checks that the block lies within its day, `start_minute <= end_minute <= 1440`.
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        match (
+            self.start_minute <= self.end_minute,
+            self.end_minute <= MINUTES_IN_DAY,
+        ) {
+            (false, _) => Err(ValidationError::EndsBeforeStart(self.label.clone())),
+            (true, false) => Err(ValidationError::EndsAfterDay(self.label.clone())),
+            (true, true) => Ok(()),
+        }
+    }
+
+    /// Covers no minute at all.
+    pub fn is_empty(&self) -> bool {
+        self.end_minute <= self.start_minute
+    }
+
+    /// A zero-length block is a point in time: it meets a block starting
+    /// there, is met by a block ending there and lies during a block around it.
+    pub fn relation(&self, block: &CalendarBlock) -> CalendarBlockRelation {
+        match (self.is_empty(), block.is_empty()) {
+            (true, true) => {
+                return match self.start_minute.cmp(&block.start_minute) {
+                    Ordering::Less => CalendarBlockRelation::Before,
+                    Ordering::Equal => CalendarBlockRelation::Equals,
+                    Ordering::Greater => CalendarBlockRelation::After,
+                }
+            }
+            (true, false) => return get_point_relation(self.start_minute, block),
+            (false, true) => return get_point_relation(block.start_minute, self).inverse(),
+            (false, false) => {}
+        }
+
+        if self.end_minute < block.start_minute {
+            return CalendarBlockRelation::Before;
+        }
+        if self.start_minute > block.end_minute {
+            return CalendarBlockRelation::After;
+        }
+        if self.end_minute == block.start_minute && self.start_minute < block.start_minute {
+            return CalendarBlockRelation::Meets;
+        }
+        if self.start_minute == block.end_minute && block.start_minute < self.start_minute {
+            return CalendarBlockRelation::MetBy;
         }
 
-        if self.start_minute > block.start_minute
-            || (self.start_minute == block.start_minute && self.end_minute <= block.end_minute)
-        {
-            return Some(CalendarBlockOverlap::GetsSwallowed);
+        match (
+            self.start_minute.cmp(&block.start_minute),
+            self.end_minute.cmp(&block.end_minute),
+        ) {
+            (Ordering::Less, Ordering::Less) => CalendarBlockRelation::Overlaps,
+            (Ordering::Less, Ordering::Equal) => CalendarBlockRelation::FinishedBy,
+            (Ordering::Less, Ordering::Greater) => CalendarBlockRelation::Contains,
+            (Ordering::Equal, Ordering::Less) => CalendarBlockRelation::Starts,
+            (Ordering::Equal, Ordering::Equal) => CalendarBlockRelation::Equals,
+            (Ordering::Equal, Ordering::Greater) => CalendarBlockRelation::StartedBy,
+            (Ordering::Greater, Ordering::Less) => CalendarBlockRelation::During,
+            (Ordering::Greater, Ordering::Equal) => CalendarBlockRelation::Finishes,
+            (Ordering::Greater, Ordering::Greater) => CalendarBlockRelation::OverlappedBy,
         }
+    }
+
+    /// Whether the two blocks share at least one minute, which a zero-length block never does.
+    pub fn intersects(&self, block: &CalendarBlock) -> bool {
+        !self.is_empty() && !block.is_empty() && !self.relation(block).is_disjoint()
+    }
+}
+
+/// How the point `minute` relates to a block that isn't empty.
+fn get_point_relation(minute: u32, block: &CalendarBlock) -> CalendarBlockRelation {
+    match (
+        minute.cmp(&block.start_minute),
+        minute.cmp(&block.end_minute),
+    ) {
+        (Ordering::Less, _) => CalendarBlockRelation::Before,
+        (Ordering::Equal, _) => CalendarBlockRelation::Meets,
+        (_, Ordering::Less) => CalendarBlockRelation::During,
+        (_, Ordering::Equal) => CalendarBlockRelation::MetBy,
+        (_, Ordering::Greater) => CalendarBlockRelation::After,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_block(start_minute: u32, end_minute: u32) -> CalendarBlock {
+        CalendarBlock {
+            id: Uuid::new_v4(),
+            start_minute,
+            end_minute,
+            block_type: CalendarBlockType::Busy,
+            subtree_depth: 0,
+            label: format!("{start_minute}-{end_minute}"),
+            calendar_id: DEFAULT_CALENDAR_ID.to_string(),
+            metadata: BlockMetadata::default(),
+        }
+    }
+
+    fn assert_relation(first: (u32, u32), second: (u32, u32), expected: CalendarBlockRelation) {
+        let first = get_block(first.0, first.1);
+        let second = get_block(second.0, second.1);
+        assert_eq!(first.relation(&second), expected, "{first:?} to {second:?}");
+        assert_eq!(
+            second.relation(&first),
+            expected.inverse(),
+            "{second:?} to {first:?}"
+        );
+    }
+
+    #[test]
+    fn validate_keeps_blocks_within_the_day() {
+        assert_eq!(get_block(540, 600).validate(), Ok(()));
+        assert_eq!(get_block(600, 600).validate(), Ok(()));
+        assert_eq!(get_block(0, 1440).validate(), Ok(()));
+        assert!(matches!(
+            get_block(600, 500).validate(),
+            Err(ValidationError::EndsBeforeStart(_))
+        ));
+        assert!(matches!(
+            get_block(1380, 1441).validate(),
+            Err(ValidationError::EndsAfterDay(_))
+        ));
+    }
+
+    #[test]
+    fn relation_covers_all_thirteen_relations_and_their_inverses() {
+        use CalendarBlockRelation::*;
+
+        assert_relation((60, 120), (180, 240), Before);
+        assert_relation((60, 120), (120, 240), Meets);
+        assert_relation((60, 150), (120, 240), Overlaps);
+        assert_relation((60, 120), (60, 240), Starts);
+        assert_relation((90, 120), (60, 240), During);
+        assert_relation((90, 240), (60, 240), Finishes);
+        assert_relation((60, 240), (60, 240), Equals);
+        assert_relation((60, 240), (90, 240), FinishedBy);
+        assert_relation((60, 240), (90, 120), Contains);
+        assert_relation((60, 240), (60, 120), StartedBy);
+        assert_relation((120, 240), (60, 150), OverlappedBy);
+        assert_relation((120, 240), (60, 120), MetBy);
+        assert_relation((180, 240), (60, 120), After);
+    }
+
+    #[test]
+    fn relation_treats_zero_length_blocks_as_points() {
+        use CalendarBlockRelation::*;
+
+        assert_relation((30, 30), (60, 120), Before);
+        assert_relation((60, 60), (60, 120), Meets);
+        assert_relation((90, 90), (60, 120), During);
+        assert_relation((120, 120), (60, 120), MetBy);
+        assert_relation((150, 150), (60, 120), After);
+        assert_relation((60, 60), (60, 60), Equals);
+        assert_relation((60, 60), (90, 90), Before);
+    }
+
+    #[test]
+    fn zero_length_blocks_intersect_nothing() {
+        let point = get_block(60, 60);
+
+        [(60, 120), (30, 90), (0, 60), (60, 60)]
+            .iter()
+            .for_each(|(start_minute, end_minute)| {
+                let block = get_block(*start_minute, *end_minute);
+                assert!(!point.intersects(&block), "{block:?}");
+                assert!(!block.intersects(&point), "{block:?}");
+            });
+    }
+
+    /// Allen's definitions, straight from the endpoints of two non-empty intervals.
+    fn get_expected_relation(
+        first: &CalendarBlock,
+        second: &CalendarBlock,
+    ) -> CalendarBlockRelation {
+        use CalendarBlockRelation::*;
+
+        let (s1, e1, s2, e2) = (
+            first.start_minute,
+            first.end_minute,
+            second.start_minute,
+            second.end_minute,
+        );
+        let candidates = [
+            (Before, e1 < s2),
+            (Meets, e1 == s2),
+            (Overlaps, s1 < s2 && s2 < e1 && e1 < e2),
+            (Starts, s1 == s2 && e1 < e2),
+            (During, s2 < s1 && e1 < e2),
+            (Finishes, s2 < s1 && e1 == e2),
+            (Equals, s1 == s2 && e1 == e2),
+            (FinishedBy, s1 < s2 && e1 == e2),
+            (Contains, s1 < s2 && e2 < e1),
+            (StartedBy, s1 == s2 && e2 < e1),
+            (OverlappedBy, s2 < s1 && s1 < e2 && e2 < e1),
+            (MetBy, s1 == e2),
+            (After, e2 < s1),
+        ];
+        let matching: Vec<CalendarBlockRelation> = candidates
+            .iter()
+            .filter(|(_, holds)| *holds)
+            .map(|(relation, _)| *relation)
+            .collect();
+        assert_eq!(matching.len(), 1, "{first:?} to {second:?}: {matching:?}");
+        matching[0]
+    }
+
+    #[test]
+    fn relation_matches_allen_for_every_pair_of_small_intervals() {
+        let intervals: Vec<CalendarBlock> = (0..8)
+            .flat_map(|start_minute| {
+                (start_minute..8).map(move |end_minute| get_block(start_minute, end_minute))
+            })
+            .collect();
+
+        intervals.iter().for_each(|first| {
+            intervals.iter().for_each(|second| {
+                let relation = first.relation(second);
+                assert_eq!(second.relation(first), relation.inverse());
+
+                let shares_minute = (first.start_minute..first.end_minute)
+                    .any(|minute| (second.start_minute..second.end_minute).contains(&minute));
+                assert_eq!(
+                    first.intersects(second),
+                    shares_minute,
+                    "{first:?} {second:?}"
+                );
+
+                if !first.is_empty() && !second.is_empty() {
+                    assert_eq!(relation, get_expected_relation(first, second));
+                    assert_eq!(relation.is_contained(), {
+                        (first.start_minute..first.end_minute).all(|minute| {
+                            (second.start_minute..second.end_minute).contains(&minute)
+                        })
+                    });
+                }
+            });
+        });
+    }
+
+    #[test]
+    fn inverse_is_an_involution() {
+        use CalendarBlockRelation::*;
 
-        Some(CalendarBlockOverlap::Swallows)
+        [
+            Before,
+            Meets,
+            Overlaps,
+            Starts,
+            During,
+            Finishes,
+            Equals,
+            FinishedBy,
+            Contains,
+            StartedBy,
+            OverlappedBy,
+            MetBy,
+            After,
+        ]
+        .iter()
+        .for_each(|relation| assert_eq!(relation.inverse().inverse(), *relation));
     }
 }
@@ -1,39 +1,77 @@
 use crate::prelude::*;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct FlattenedCalendarBlock {
     pub block: CalendarBlock,
+    /// 1 for top-level blocks, one more for each block containing it.
     pub stack_position: usize,
+    /// `None` for top-level blocks.
+    pub parent_id: Option<Uuid>,
+    /// Position among the parent's children, which are sorted by block order.
+    pub sibling_index: usize,
+    /// Siblings that partially overlap share a cluster and are laid out side by
+    /// side; `column` is the block's place among the cluster's `column_count`.
+    pub column: usize,
+    pub column_count: usize,
 }
 
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TraversalOrder {
+    #[default]
+    BreadthFirst,
+    /// Pre-order: each block is followed by its whole subtree.
+    DepthFirst,
+}
+
+/// Siblings chained together by partial overlaps, with the minute each column frees up.
+#[derive(Default)]
+struct SiblingCluster {
+    members: Vec<(NodeIndex, usize)>,
+    column_ends: Vec<u32>,
+}
+
+#[derive(Debug, Error, Clone, PartialEq)]
+pub enum CalendarTreeError {
+    #[error("block {0} is added more than once")]
+    DuplicateId(Uuid),
+}
+
+/// Blocks nested by containment. Top-level blocks hang off a root node that
+/// stands for the whole day; it has the nil id and no block of its own.
 pub struct CalendarBlockTree {
     root_idx: NodeIndex,
     adjacency: Graph<Uuid, usize>,
     id_to_block_map: HashMap<Uuid, CalendarBlock>,
+    id_to_node_map: HashMap<Uuid, NodeIndex>,
 }
 
-impl CalendarBlockTree {
-    pub fn new() -> Self {
-        let mut id_to_block_map = HashMap::new();
-
-        let root_node = CalendarBlock {
-            id: Uuid::new_v4(),
-            start_minute: 0,
-            end_minute: 1440,
-            block_type: CalendarBlockType::Wrapper,
-            subtree_depth: 0,
-            label: String::from("Today's Date"),
-        };
+impl Default for CalendarBlockTree {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
-        id_to_block_map.insert(root_node.id, root_node.clone());
+impl CalendarBlockTree {
+    /// Builds the tree in canonical block order, so the shape doesn't depend on the input order.
+    pub fn from_blocks(blocks: &[CalendarBlock]) -> Result<Self, CalendarTreeError> {
+        let mut calendar_tree = Self::new();
+        let mut blocks = blocks.to_vec();
+        blocks.sort_by_key(CalendarBlock::sort_key);
+        blocks
+            .into_iter()
+            .try_for_each(|block| calendar_tree.add(block, None))?;
+        Ok(calendar_tree)
+    }
 
+    pub fn new() -> Self {
         let mut adjacency_map = Graph::new();
-        let root_idx = adjacency_map.add_node(root_node.id);
+        let root_idx = adjacency_map.add_node(Uuid::nil());
 
         Self {
             root_idx,
             adjacency: adjacency_map,
-            id_to_block_map,
+            id_to_block_map: HashMap::new(),
+            id_to_node_map: HashMap::new(),
         }
     }
 
@@ -41,109 +79,765 @@ impl CalendarBlockTree {
         &mut self,
         block: CalendarBlock,
         destination: Option<NodeIndex>,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        // Recursive Add
-        // 1. find overlaps
-        //      if no overlap
-        //          add edge from destination to new block
-        //          return
-        //      else if new block gets swallowed
-        //          call add with new destination
-        // TODO:else
-        //          add edge from destination to new block
-        //          add edges from new block to overlapping blocks
-        //          remove edges from destination to overlapping blocks
+    ) -> Result<(), CalendarTreeError> {
+        if self.id_to_node_map.contains_key(&block.id) {
+            return Err(CalendarTreeError::DuplicateId(block.id));
+        }
 
+        // Recursive Add
+        // 1. if a child of destination contains the new block
+        //      call add with the child as the new destination
+        // 2. else
+        //      add edge from destination to new block
+        //      move children of destination contained by the new block under it
+        //      partially overlapping children stay siblings of the new block
         let destination = destination.unwrap_or(self.root_idx);
 
-        let mut forward_neighbors = self
-            .adjacency
-            .edges_directed(destination, petgraph::Direction::Outgoing)
-            .map(|e| e.target());
+        let children = self.get_child_indices(destination);
 
-        let adjacency_list = &mut self.adjacency.clone();
+        let container = children.iter().find(|child_idx| {
+            let child = self.get_block_at(**child_idx);
+            block.relation(child).is_contained()
+        });
 
-        let overlap = forward_neighbors.find_map(|forward_n_idx| {
-            let current_block = adjacency_list[forward_n_idx];
-            let current_block = self.id_to_block_map.get(&current_block).unwrap();
+        if let Some(container_idx) = container {
+            return self.add(block, Some(*container_idx));
+        }
 
-            block
-                .does_overlap(current_block.clone())
-                .map(|o| (o, forward_n_idx))
-        });
+        let contained_children: Vec<NodeIndex> = children
+            .into_iter()
+            .filter(|child_idx| {
+                let child = self.get_block_at(*child_idx);
+                block.relation(child).is_containing()
+            })
+            .collect();
 
-        let _ = match overlap {
-            Some((_, node_idx)) => self.add(block, Some(node_idx)),
-            None => {
-                let idx = adjacency_list.add_node(block.id);
-                adjacency_list.add_edge(destination, idx, 1);
-                self.adjacency = adjacency_list.clone();
+        let idx = self.adjacency.add_node(block.id);
+        self.adjacency.add_edge(destination, idx, 1);
+        self.id_to_node_map.insert(block.id, idx);
+        self.id_to_block_map.insert(block.id, block);
 
-                self.update_subtree_depth_until_root(destination, 1);
-                self.id_to_block_map.insert(block.id, block);
-                Ok(())
+        contained_children.iter().for_each(|child_idx| {
+            if let Some(edge) = self.adjacency.find_edge(destination, *child_idx) {
+                self.adjacency.remove_edge(edge);
             }
-        };
+            self.adjacency.add_edge(idx, *child_idx, 1);
+        });
+
+        self.update_subtree_depth_until_root(idx);
 
         Ok(())
     }
 
-    fn update_subtree_depth_until_root(&mut self, node_idx: NodeIndex, value: usize) {
-        let node_id = self.adjacency[node_idx];
-        let maybe_node = self.id_to_block_map.get_mut(&node_id);
-
-        if let Some(node) = maybe_node {
-            if node.subtree_depth < value {
-                node.subtree_depth = value;
-
-                let mut parent = self
-                    .adjacency
-                    .edges_directed(node_idx, petgraph::Direction::Incoming)
-                    .map(|e| e.source());
-                if let Some(p) = parent.next() {
-                    self.update_subtree_depth_until_root(p, value + 1);
-                }
+    /// Number of blocks added to the tree.
+    pub fn len(&self) -> usize {
+        self.id_to_block_map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, id: Uuid) -> Option<&CalendarBlock> {
+        self.get_node_idx(id)
+            .map(|node_idx| self.get_block_at(node_idx))
+    }
+
+    /// The innermost block containing the given block, if any.
+    pub fn parent(&self, id: Uuid) -> Option<&CalendarBlock> {
+        self.get_node_idx(id)
+            .and_then(|node_idx| self.get_parent_idx(node_idx))
+            .filter(|parent_idx| *parent_idx != self.root_idx)
+            .map(|parent_idx| self.get_block_at(parent_idx))
+    }
+
+    /// Blocks directly inside the given block, in block order.
+    pub fn children(&self, id: Uuid) -> Vec<&CalendarBlock> {
+        self.get_node_idx(id)
+            .map(|node_idx| self.get_child_indices(node_idx))
+            .unwrap_or_default()
+            .into_iter()
+            .map(|child_idx| self.get_block_at(child_idx))
+            .collect()
+    }
+
+    /// Blocks containing the given block, innermost first.
+    pub fn ancestors(&self, id: Uuid) -> Vec<&CalendarBlock> {
+        let mut ancestors = Vec::new();
+        let mut node_idx = self.get_node_idx(id);
+
+        while let Some(parent_idx) = node_idx.and_then(|n| self.get_parent_idx(n)) {
+            if parent_idx == self.root_idx {
+                break;
+            }
+            ancestors.push(self.get_block_at(parent_idx));
+            node_idx = Some(parent_idx);
+        }
+
+        ancestors
+    }
+
+    /// Every block nested inside the given block, depth first.
+    pub fn descendants(&self, id: Uuid) -> Vec<&CalendarBlock> {
+        let mut descendants = Vec::new();
+        let mut stack: Vec<NodeIndex> = self.get_node_idx(id).into_iter().collect();
+
+        while let Some(node_idx) = stack.pop() {
+            if self.adjacency[node_idx] != id {
+                descendants.push(self.get_block_at(node_idx));
             }
+            stack.extend(self.get_child_indices(node_idx).into_iter().rev());
+        }
+
+        descendants
+    }
+
+    /// Blocks covering the given minute, outermost first.
+    pub fn blocks_at(&self, minute: u32) -> Vec<&CalendarBlock> {
+        let mut blocks: Vec<&CalendarBlock> = self
+            .id_to_block_map
+            .values()
+            .filter(|block| block.start_minute <= minute && minute < block.end_minute)
+            .collect();
+        blocks.sort_by_key(|block| block.sort_key());
+        blocks
+    }
+
+    fn get_node_idx(&self, id: Uuid) -> Option<NodeIndex> {
+        self.id_to_node_map.get(&id).copied()
+    }
+
+    fn get_parent_idx(&self, node_idx: NodeIndex) -> Option<NodeIndex> {
+        self.adjacency
+            .edges_directed(node_idx, petgraph::Direction::Incoming)
+            .map(|e| e.source())
+            .next()
+    }
+
+    fn get_block_at(&self, node_idx: NodeIndex) -> &CalendarBlock {
+        self.id_to_block_map.get(&self.adjacency[node_idx]).unwrap()
+    }
+
+    fn update_subtree_depth_until_root(&mut self, node_idx: NodeIndex) {
+        let subtree_depth = self
+            .adjacency
+            .edges_directed(node_idx, petgraph::Direction::Outgoing)
+            .map(|e| self.get_block_at(e.target()).subtree_depth + 1)
+            .max()
+            .unwrap_or(0);
+
+        let node_id = self.adjacency[node_idx];
+        if let Some(node) = self.id_to_block_map.get_mut(&node_id) {
+            node.subtree_depth = subtree_depth;
+        }
+
+        if let Some(parent_idx) = self.get_parent_idx(node_idx) {
+            self.update_subtree_depth_until_root(parent_idx);
         }
     }
 
+    /// Assigns each sibling a (column, column_count), packing partially
+    /// overlapping siblings into as few columns as possible.
+    fn get_sibling_columns(&self, siblings: &[NodeIndex]) -> HashMap<NodeIndex, (usize, usize)> {
+        let mut siblings: Vec<(NodeIndex, &CalendarBlock)> = siblings
+            .iter()
+            .map(|sibling_idx| (*sibling_idx, self.get_block_at(*sibling_idx)))
+            .collect();
+        siblings.sort_by_key(|(_, block)| block.sort_key());
+
+        let mut clusters: Vec<SiblingCluster> = Vec::new();
+        let mut cluster_end = 0;
+
+        siblings.iter().for_each(|(sibling_idx, block)| {
+            if clusters.is_empty() || block.start_minute >= cluster_end {
+                clusters.push(SiblingCluster::default());
+            }
+            let SiblingCluster {
+                members,
+                column_ends,
+            } = clusters.last_mut().unwrap();
+
+            let column = match column_ends
+                .iter()
+                .position(|column_end| *column_end <= block.start_minute)
+            {
+                Some(column) => {
+                    column_ends[column] = block.end_minute;
+                    column
+                }
+                None => {
+                    column_ends.push(block.end_minute);
+                    column_ends.len() - 1
+                }
+            };
+
+            members.push((*sibling_idx, column));
+            cluster_end = cluster_end.max(block.end_minute);
+        });
+
+        let mut columns = HashMap::new();
+        clusters.iter().for_each(|cluster| {
+            let column_count = cluster.column_ends.len();
+            cluster.members.iter().for_each(|(sibling_idx, column)| {
+                columns.insert(*sibling_idx, (*column, column_count));
+            });
+        });
+
+        columns
+    }
+
     pub fn display(&self) {
-        info!("{}", Dot::new(&self.adjacency));
+        info!("{}", self.to_dot());
     }
 
+    /// Graphviz DOT source for the tree, one node per block labelled with its
+    /// time range, type and subtree depth, filled with the block type's light palette color.
+    pub fn to_dot(&self) -> String {
+        let time_format = TimeFormat::default();
+        let palette = Palette::light();
+        let mut dot = String::from("digraph calendar {\n");
+        dot.push_str("    node [shape=box, style=\"rounded,filled\", fontname=\"Verdana\"];\n");
+
+        self.adjacency
+            .node_indices()
+            .filter(|node_idx| *node_idx != self.root_idx)
+            .for_each(|node_idx| {
+                let block = self.get_block_at(node_idx);
+                let block_colors = palette.get_block_colors(block.block_type);
+                let label = format!(
+                    "{}\\n{}\\n{}, depth {}",
+                    escape_dot_label(&block.label),
+                    time_format.format_range(block.start_minute, block.end_minute),
+                    block.block_type,
+                    block.subtree_depth
+                );
+                dot.push_str(&format!(
+                    "    \"{}\" [label=\"{label}\", fillcolor=\"{}\", color=\"{}\"];\n",
+                    block.id, block_colors.fill, block_colors.border
+                ));
+            });
+
+        self.adjacency
+            .edge_references()
+            .filter(|edge| edge.source() != self.root_idx)
+            .for_each(|edge| {
+                dot.push_str(&format!(
+                    "    \"{}\" -> \"{}\";\n",
+                    self.adjacency[edge.source()],
+                    self.adjacency[edge.target()]
+                ));
+            });
+
+        dot.push_str("}\n");
+        dot
+    }
+
+    pub fn write_dot(&self, path: impl AsRef<std::path::Path>) -> std::io::Result<()> {
+        std::fs::write(path, self.to_dot())
+    }
+
+    /// Breadth-first traversal, see `traverse_with`.
     pub fn traverse(&self) -> Vec<FlattenedCalendarBlock> {
-        let mut traversal_queue: VecDeque<(NodeIndex, usize)> =
-            VecDeque::with_capacity(self.id_to_block_map.iter().len());
+        self.traverse_with(TraversalOrder::BreadthFirst)
+    }
 
-        let mut buffer: Vec<(NodeIndex, usize)> =
-            Vec::with_capacity(self.id_to_block_map.iter().len());
+    /// Flattens the tree, top-level blocks first. Siblings are always visited in
+    /// canonical block order, and a parent always comes before its children.
+    pub fn traverse_with(&self, order: TraversalOrder) -> Vec<FlattenedCalendarBlock> {
+        let mut traversal_queue: VecDeque<(NodeIndex, FlattenedCalendarBlock)> =
+            VecDeque::with_capacity(self.len());
 
-        traversal_queue.push_back((self.root_idx, 0));
+        let mut buffer: Vec<FlattenedCalendarBlock> = Vec::with_capacity(self.len());
 
-        while !traversal_queue.is_empty() {
-            let (node_idx, stack_position) = traversal_queue.pop_front().unwrap();
-            buffer.push((node_idx, stack_position));
+        let top_level_blocks = self.get_flattened_children(self.root_idx, None);
+        match order {
+            TraversalOrder::BreadthFirst => traversal_queue.extend(top_level_blocks),
+            TraversalOrder::DepthFirst => {
+                traversal_queue.extend(top_level_blocks.into_iter().rev())
+            }
+        }
 
-            let forward_neighbors = self
-                .adjacency
-                .edges_directed(node_idx, petgraph::Direction::Outgoing)
-                .map(|e| e.target());
+        loop {
+            let next = match order {
+                TraversalOrder::BreadthFirst => traversal_queue.pop_front(),
+                TraversalOrder::DepthFirst => traversal_queue.pop_back(),
+            };
+            let (node_idx, flattened_block) = match next {
+                Some(next) => next,
+                None => break,
+            };
 
-            forward_neighbors.for_each(|n| {
-                traversal_queue.push_back((n, stack_position + 1));
-            });
+            let children = self.get_flattened_children(node_idx, Some(&flattened_block));
+
+            // the stack pops from the back, so push children last to first
+            match order {
+                TraversalOrder::BreadthFirst => traversal_queue.extend(children),
+                TraversalOrder::DepthFirst => traversal_queue.extend(children.into_iter().rev()),
+            }
+
+            buffer.push(flattened_block);
         }
 
         buffer
+    }
+
+    fn get_flattened_children(
+        &self,
+        node_idx: NodeIndex,
+        parent: Option<&FlattenedCalendarBlock>,
+    ) -> Vec<(NodeIndex, FlattenedCalendarBlock)> {
+        let children = self.get_child_indices(node_idx);
+        let columns = self.get_sibling_columns(&children);
+
+        children
             .iter()
-            .map(|(node_idx, stack_position)| {
-                let current_block_id = self.adjacency[*node_idx];
-                let current_block = self.id_to_block_map.get(&current_block_id).unwrap();
-                FlattenedCalendarBlock {
-                    block: current_block.clone(),
-                    stack_position: *stack_position,
-                }
+            .enumerate()
+            .map(|(sibling_index, child_idx)| {
+                let (column, column_count) = columns.get(child_idx).copied().unwrap_or((0, 1));
+                (
+                    *child_idx,
+                    FlattenedCalendarBlock {
+                        block: self.get_block_at(*child_idx).clone(),
+                        stack_position: parent.map_or(1, |parent| parent.stack_position + 1),
+                        parent_id: parent.map(|parent| parent.block.id),
+                        sibling_index,
+                        column,
+                        column_count,
+                    },
+                )
+            })
+            .collect()
+    }
+
+    /// Children of a node in canonical block order; petgraph yields edges newest first.
+    fn get_child_indices(&self, node_idx: NodeIndex) -> Vec<NodeIndex> {
+        let mut children: Vec<NodeIndex> = self
+            .adjacency
+            .edges_directed(node_idx, petgraph::Direction::Outgoing)
+            .map(|e| e.target())
+            .collect();
+        children.sort_by_key(|n| self.get_block_at(*n).sort_key());
+        children
+    }
+}
+
+fn escape_dot_label(label: &str) -> String {
+    label.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_block(label: &str, start_minute: u32, end_minute: u32) -> CalendarBlock {
+        CalendarBlock {
+            id: Uuid::new_v4(),
+            start_minute,
+            end_minute,
+            block_type: CalendarBlockType::Busy,
+            subtree_depth: 0,
+            label: label.to_string(),
+            calendar_id: DEFAULT_CALENDAR_ID.to_string(),
+            metadata: BlockMetadata::default(),
+        }
+    }
+
+    /// Each block's label with its (column, column_count).
+    fn get_columns(blocks: &[CalendarBlock]) -> Vec<(String, (usize, usize))> {
+        CalendarBlockTree::from_blocks(blocks)
+            .unwrap()
+            .traverse()
+            .into_iter()
+            .map(|flattened_block| {
+                (
+                    flattened_block.block.label,
+                    (flattened_block.column, flattened_block.column_count),
+                )
             })
             .collect()
     }
+
+    #[test]
+    fn partially_overlapping_siblings_share_the_columns() {
+        let blocks = vec![
+            get_block("Review", 540, 600),
+            get_block("Interview", 570, 630),
+        ];
+
+        assert_eq!(
+            get_columns(&blocks),
+            vec![
+                (String::from("Review"), (0, 2)),
+                (String::from("Interview"), (1, 2)),
+            ]
+        );
+    }
+
+    #[test]
+    fn a_chain_of_overlaps_reuses_columns_that_free_up() {
+        // A overlaps B and B overlaps C, but A ends before C starts
+        let blocks = vec![
+            get_block("A", 540, 600),
+            get_block("B", 570, 660),
+            get_block("C", 630, 690),
+        ];
+
+        assert_eq!(
+            get_columns(&blocks),
+            vec![
+                (String::from("A"), (0, 2)),
+                (String::from("B"), (1, 2)),
+                (String::from("C"), (0, 2)),
+            ]
+        );
+    }
+
+    #[test]
+    fn disjoint_clusters_count_their_columns_separately() {
+        let blocks = vec![
+            get_block("A", 540, 600),
+            get_block("B", 570, 630),
+            get_block("C", 590, 640),
+            get_block("Lunch", 720, 780),
+            get_block("D", 900, 960),
+            get_block("E", 930, 990),
+        ];
+
+        assert_eq!(
+            get_columns(&blocks),
+            vec![
+                (String::from("A"), (0, 3)),
+                (String::from("B"), (1, 3)),
+                (String::from("C"), (2, 3)),
+                (String::from("Lunch"), (0, 1)),
+                (String::from("D"), (0, 2)),
+                (String::from("E"), (1, 2)),
+            ]
+        );
+    }
+
+    /// Each block's id, stack position, parent and column, in traversal order.
+    fn get_shape(
+        calendar_tree: &CalendarBlockTree,
+        order: TraversalOrder,
+    ) -> Vec<(Uuid, usize, Option<Uuid>, usize, usize)> {
+        calendar_tree
+            .traverse_with(order)
+            .iter()
+            .map(|flattened_block| {
+                (
+                    flattened_block.block.id,
+                    flattened_block.stack_position,
+                    flattened_block.parent_id,
+                    flattened_block.sibling_index,
+                    flattened_block.column,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn from_blocks_gives_the_same_tree_for_any_input_order() {
+        let available = CalendarBlock {
+            block_type: CalendarBlockType::Available,
+            ..get_block("Open", 540, 720)
+        };
+        let blocks = vec![
+            available,
+            get_block("Review", 540, 720),
+            get_block("Standup", 540, 555),
+            get_block("Sync", 540, 555),
+            get_block("Interview", 600, 660),
+            get_block("Call", 630, 690),
+            get_block("Lunch", 720, 780),
+        ];
+        let calendar_tree = CalendarBlockTree::from_blocks(&blocks).unwrap();
+        let breadth_first = get_shape(&calendar_tree, TraversalOrder::BreadthFirst);
+        let depth_first = get_shape(&calendar_tree, TraversalOrder::DepthFirst);
+
+        // every rotation, reversed and not, moves each block to every position
+        (0..blocks.len()).for_each(|offset| {
+            let mut shuffled = blocks.clone();
+            shuffled.rotate_left(offset);
+            [shuffled.clone(), shuffled.into_iter().rev().collect()]
+                .iter()
+                .for_each(|shuffled: &Vec<CalendarBlock>| {
+                    let shuffled_tree = CalendarBlockTree::from_blocks(shuffled).unwrap();
+                    assert_eq!(
+                        get_shape(&shuffled_tree, TraversalOrder::BreadthFirst),
+                        breadth_first
+                    );
+                    assert_eq!(
+                        get_shape(&shuffled_tree, TraversalOrder::DepthFirst),
+                        depth_first
+                    );
+                });
+        });
+    }
+
+    /// Two top-level blocks, the first holding two blocks and the second one.
+    fn get_two_level_blocks() -> Vec<CalendarBlock> {
+        vec![
+            get_block("Morning", 540, 660),
+            get_block("Standup", 540, 570),
+            get_block("Review", 600, 630),
+            get_block("Afternoon", 720, 840),
+            get_block("Retro", 750, 780),
+        ]
+    }
+
+    #[test]
+    fn traverse_with_visits_levels_or_subtrees_in_turn() {
+        let blocks = get_two_level_blocks();
+        let calendar_tree = CalendarBlockTree::from_blocks(&blocks).unwrap();
+        let ids: Vec<Uuid> = blocks.iter().map(|block| block.id).collect();
+        let (morning, standup, review, afternoon, retro) = (ids[0], ids[1], ids[2], ids[3], ids[4]);
+
+        assert_eq!(
+            get_shape(&calendar_tree, TraversalOrder::BreadthFirst),
+            vec![
+                (morning, 1, None, 0, 0),
+                (afternoon, 1, None, 1, 0),
+                (standup, 2, Some(morning), 0, 0),
+                (review, 2, Some(morning), 1, 0),
+                (retro, 2, Some(afternoon), 0, 0),
+            ]
+        );
+        assert_eq!(
+            get_shape(&calendar_tree, TraversalOrder::DepthFirst),
+            vec![
+                (morning, 1, None, 0, 0),
+                (standup, 2, Some(morning), 0, 0),
+                (review, 2, Some(morning), 1, 0),
+                (afternoon, 1, None, 1, 0),
+                (retro, 2, Some(afternoon), 0, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn traverse_is_breadth_first() {
+        let calendar_tree = CalendarBlockTree::from_blocks(&get_two_level_blocks()).unwrap();
+
+        assert_eq!(
+            get_shape(&calendar_tree, TraversalOrder::default()),
+            get_shape(&calendar_tree, TraversalOrder::BreadthFirst)
+        );
+        let labels: Vec<String> = calendar_tree
+            .traverse()
+            .into_iter()
+            .map(|flattened_block| flattened_block.block.label)
+            .collect();
+        assert_eq!(
+            labels,
+            vec!["Morning", "Afternoon", "Standup", "Review", "Retro"]
+        );
+    }
+
+    /// A workday holding a morning, which holds a standup and a review, and a retro.
+    fn get_nested_tree() -> (CalendarBlockTree, HashMap<&'static str, Uuid>) {
+        let blocks = vec![
+            get_block("Workday", 480, 1020),
+            get_block("Morning", 540, 660),
+            get_block("Standup", 540, 570),
+            get_block("Review", 600, 630),
+            get_block("Retro", 750, 780),
+        ];
+        let ids = ["Workday", "Morning", "Standup", "Review", "Retro"]
+            .into_iter()
+            .zip(blocks.iter().map(|block| block.id))
+            .collect();
+        (CalendarBlockTree::from_blocks(&blocks).unwrap(), ids)
+    }
+
+    fn get_labels(blocks: Vec<&CalendarBlock>) -> Vec<&str> {
+        blocks.iter().map(|block| block.label.as_str()).collect()
+    }
+
+    #[test]
+    fn children_are_the_blocks_directly_inside() {
+        let (calendar_tree, ids) = get_nested_tree();
+
+        assert_eq!(
+            get_labels(calendar_tree.children(ids["Workday"])),
+            vec!["Morning", "Retro"]
+        );
+        assert_eq!(
+            get_labels(calendar_tree.children(ids["Morning"])),
+            vec!["Standup", "Review"]
+        );
+        assert!(calendar_tree.children(ids["Standup"]).is_empty());
+        assert!(calendar_tree.children(Uuid::new_v4()).is_empty());
+    }
+
+    #[test]
+    fn ancestors_are_the_containing_blocks_innermost_first() {
+        let (calendar_tree, ids) = get_nested_tree();
+
+        assert_eq!(
+            get_labels(calendar_tree.ancestors(ids["Standup"])),
+            vec!["Morning", "Workday"]
+        );
+        assert_eq!(
+            get_labels(calendar_tree.ancestors(ids["Retro"])),
+            vec!["Workday"]
+        );
+        assert!(calendar_tree.ancestors(ids["Workday"]).is_empty());
+        assert_eq!(calendar_tree.parent(ids["Workday"]), None);
+        assert!(calendar_tree.ancestors(Uuid::new_v4()).is_empty());
+    }
+
+    #[test]
+    fn descendants_are_every_nested_block_depth_first() {
+        let (calendar_tree, ids) = get_nested_tree();
+
+        assert_eq!(
+            get_labels(calendar_tree.descendants(ids["Workday"])),
+            vec!["Morning", "Standup", "Review", "Retro"]
+        );
+        assert_eq!(
+            get_labels(calendar_tree.descendants(ids["Morning"])),
+            vec!["Standup", "Review"]
+        );
+        assert!(calendar_tree.descendants(ids["Review"]).is_empty());
+        assert!(calendar_tree.descendants(Uuid::new_v4()).is_empty());
+    }
+
+    #[test]
+    fn blocks_at_lists_the_blocks_covering_a_minute_outermost_first() {
+        let (calendar_tree, _) = get_nested_tree();
+
+        assert_eq!(
+            get_labels(calendar_tree.blocks_at(540)),
+            vec!["Workday", "Morning", "Standup"]
+        );
+        assert_eq!(
+            get_labels(calendar_tree.blocks_at(569)),
+            vec!["Workday", "Morning", "Standup"]
+        );
+        // blocks are half-open, the standup is over at 09:30
+        assert_eq!(
+            get_labels(calendar_tree.blocks_at(570)),
+            vec!["Workday", "Morning"]
+        );
+        assert_eq!(get_labels(calendar_tree.blocks_at(1019)), vec!["Workday"]);
+        assert!(calendar_tree.blocks_at(1020).is_empty());
+        assert!(calendar_tree.blocks_at(479).is_empty());
+    }
+
+    #[test]
+    fn len_counts_the_blocks_but_not_the_root() {
+        let (calendar_tree, _) = get_nested_tree();
+
+        assert_eq!(calendar_tree.len(), 5);
+        assert!(!calendar_tree.is_empty());
+        assert!(CalendarBlockTree::new().is_empty());
+        assert_eq!(calendar_tree.get(Uuid::nil()), None);
+    }
+
+    #[test]
+    fn to_dot_labels_every_block_and_draws_parent_to_child_edges() {
+        let (calendar_tree, ids) = get_nested_tree();
+
+        let dot = calendar_tree.to_dot();
+
+        assert!(dot.starts_with("digraph calendar {\n"));
+        assert!(dot.ends_with("}\n"));
+        assert!(dot.contains(&format!(
+            "    \"{}\" [label=\"Morning\\n09:00–11:00\\nBusy, depth 1\", fillcolor=\"#FFFFFF\", color=\"#494949\"];\n",
+            ids["Morning"]
+        )));
+        ["Workday", "Standup", "Review", "Retro"]
+            .iter()
+            .for_each(|label| assert!(dot.contains(&format!("[label=\"{label}\\n")), "{label}"));
+
+        let edges: Vec<&str> = dot.lines().filter(|line| line.contains("->")).collect();
+        assert_eq!(edges.len(), 4);
+        [
+            ("Workday", "Morning"),
+            ("Morning", "Standup"),
+            ("Morning", "Review"),
+            ("Workday", "Retro"),
+        ]
+        .iter()
+        .for_each(|(parent, child)| {
+            let edge = format!("    \"{}\" -> \"{}\";", ids[parent], ids[child]);
+            assert!(edges.contains(&edge.as_str()), "{parent} -> {child}");
+        });
+        // the root stands for the day and is left out
+        assert!(!dot.contains(&Uuid::nil().to_string()));
+    }
+
+    #[test]
+    fn to_dot_escapes_labels() {
+        let block = get_block("The \"big\" one \\ part 2", 540, 600);
+
+        let dot = CalendarBlockTree::from_blocks(&[block]).unwrap().to_dot();
+
+        assert!(dot.contains("[label=\"The \\\"big\\\" one \\\\ part 2\\n"));
+    }
+
+    #[test]
+    fn write_dot_writes_the_dot_source() {
+        let (calendar_tree, _) = get_nested_tree();
+        let path = std::env::temp_dir().join(format!("cal-ender-{}.dot", Uuid::new_v4()));
+
+        calendar_tree.write_dot(&path).unwrap();
+        let written = std::fs::read_to_string(&path);
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(written.unwrap(), calendar_tree.to_dot());
+    }
+
+    #[test]
+    fn from_blocks_nests_blocks_by_containment() {
+        let outer = get_block("Outer", 540, 720);
+        let inner = get_block("Inner", 600, 660);
+        let calendar_tree =
+            CalendarBlockTree::from_blocks(&[inner.clone(), outer.clone()]).unwrap();
+
+        assert_eq!(calendar_tree.len(), 2);
+        assert_eq!(
+            calendar_tree.parent(inner.id).map(|parent| parent.id),
+            Some(outer.id)
+        );
+    }
+
+    #[test]
+    fn from_blocks_rejects_duplicate_ids() {
+        let block = get_block("Planning", 540, 600);
+        let moved = CalendarBlock {
+            start_minute: 660,
+            end_minute: 720,
+            ..block.clone()
+        };
+
+        assert_eq!(
+            CalendarBlockTree::from_blocks(&[block.clone(), moved]).err(),
+            Some(CalendarTreeError::DuplicateId(block.id))
+        );
+    }
+
+    #[test]
+    fn add_rejects_a_duplicate_id_and_keeps_the_tree() {
+        let block = get_block("Planning", 540, 600);
+        let mut calendar_tree =
+            CalendarBlockTree::from_blocks(std::slice::from_ref(&block)).unwrap();
+        let relabelled = CalendarBlock {
+            label: String::from("Retro"),
+            ..block.clone()
+        };
+
+        assert_eq!(
+            calendar_tree.add(relabelled, None),
+            Err(CalendarTreeError::DuplicateId(block.id))
+        );
+        assert_eq!(calendar_tree.len(), 1);
+        assert_eq!(
+            calendar_tree
+                .get(block.id)
+                .map(|block| block.label.as_str()),
+            Some("Planning")
+        );
+    }
 }
@@ -0,0 +1,205 @@
+use crate::prelude::*;
+use std::collections::HashSet;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictKind {
+    /// One block lies entirely within the other.
+    Containment,
+    /// The blocks share some, but not all, of the shorter block's time.
+    Partial,
+}
+
+impl Display for ConflictKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let conflict_kind = match self {
+            ConflictKind::Containment => "containment",
+            ConflictKind::Partial => "partial",
+        };
+        write!(f, "{conflict_kind}")
+    }
+}
+
+/// Two busy blocks that share time, with the interval they share.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Conflict {
+    pub first: CalendarBlock,
+    pub second: CalendarBlock,
+    pub kind: ConflictKind,
+    pub start_minute: u32,
+    pub end_minute: u32, // exclusive
+    pub overlap_minutes: u32,
+}
+
+impl Conflict {
+    fn new(first: &CalendarBlock, second: &CalendarBlock) -> Self {
+        let start_minute = first.start_minute.max(second.start_minute);
+        let end_minute = first.end_minute.min(second.end_minute);
+        let kind = match first.relation(second).is_partial_overlap() {
+            true => ConflictKind::Partial,
+            false => ConflictKind::Containment,
+        };
+
+        Self {
+            first: first.clone(),
+            second: second.clone(),
+            kind,
+            start_minute,
+            end_minute,
+            overlap_minutes: end_minute - start_minute,
+        }
+    }
+}
+
+/// Lists every pair of overlapping blocks whose types can conflict, ordered by
+/// when the overlap starts.
+pub fn find_conflicts(blocks: &[CalendarBlock]) -> Vec<Conflict> {
+    let busy_blocks: Vec<&CalendarBlock> = blocks
+        .iter()
+        .filter(|block| block.block_type.info().can_conflict)
+        .collect();
+
+    let mut conflicts: Vec<Conflict> = busy_blocks
+        .iter()
+        .enumerate()
+        .flat_map(|(idx, first)| {
+            busy_blocks[idx + 1..]
+                .iter()
+                .filter(|second| first.intersects(second))
+                .map(|second| Conflict::new(first, second))
+        })
+        .collect();
+
+    conflicts.sort_by_key(|conflict| (conflict.start_minute, conflict.end_minute));
+    conflicts
+}
+
+pub fn get_conflicting_block_ids(conflicts: &[Conflict]) -> HashSet<Uuid> {
+    conflicts
+        .iter()
+        .flat_map(|conflict| [conflict.first.id, conflict.second.id])
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_block(
+        label: &str,
+        start_minute: u32,
+        end_minute: u32,
+        block_type: CalendarBlockType,
+    ) -> CalendarBlock {
+        CalendarBlock {
+            id: Uuid::new_v4(),
+            start_minute,
+            end_minute,
+            block_type,
+            subtree_depth: 0,
+            label: label.to_string(),
+            calendar_id: DEFAULT_CALENDAR_ID.to_string(),
+            metadata: BlockMetadata::default(),
+        }
+    }
+
+    fn get_labels(conflicts: &[Conflict]) -> Vec<(&str, &str)> {
+        conflicts
+            .iter()
+            .map(|conflict| {
+                (
+                    conflict.first.label.as_str(),
+                    conflict.second.label.as_str(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn find_conflicts_ignores_adjacent_blocks() {
+        let blocks = vec![
+            get_block("Standup", 540, 555, CalendarBlockType::Busy),
+            get_block("Review", 555, 600, CalendarBlockType::Busy),
+            get_block("Commute", 600, 630, CalendarBlockType::Travel),
+        ];
+
+        assert_eq!(find_conflicts(&blocks), vec![]);
+    }
+
+    #[test]
+    fn find_conflicts_classifies_containment_and_partial_overlap() {
+        let blocks = vec![
+            get_block("Review", 540, 660, CalendarBlockType::Busy),
+            get_block("Standup", 540, 555, CalendarBlockType::Busy),
+            get_block("Interview", 630, 690, CalendarBlockType::Busy),
+        ];
+
+        let conflicts = find_conflicts(&blocks);
+
+        assert_eq!(
+            get_labels(&conflicts),
+            vec![("Review", "Standup"), ("Review", "Interview")]
+        );
+        assert_eq!(conflicts[0].kind, ConflictKind::Containment);
+        assert_eq!(
+            (
+                conflicts[0].start_minute,
+                conflicts[0].end_minute,
+                conflicts[0].overlap_minutes
+            ),
+            (540, 555, 15)
+        );
+        assert_eq!(conflicts[1].kind, ConflictKind::Partial);
+        assert_eq!(
+            (
+                conflicts[1].start_minute,
+                conflicts[1].end_minute,
+                conflicts[1].overlap_minutes
+            ),
+            (630, 660, 30)
+        );
+    }
+
+    #[test]
+    fn find_conflicts_counts_equal_blocks_as_containment() {
+        let blocks = vec![
+            get_block("Review", 540, 600, CalendarBlockType::Busy),
+            get_block("Offsite", 540, 600, CalendarBlockType::OutOfOffice),
+        ];
+
+        let conflicts = find_conflicts(&blocks);
+
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].kind, ConflictKind::Containment);
+    }
+
+    #[test]
+    fn find_conflicts_leaves_out_types_that_cannot_conflict() {
+        let blocks = vec![
+            get_block("Review", 540, 660, CalendarBlockType::Busy),
+            get_block("Open", 540, 720, CalendarBlockType::Available),
+            get_block("Maybe", 600, 630, CalendarBlockType::Tentative),
+            get_block("Deep work", 570, 690, CalendarBlockType::Focus),
+            get_block("Writing", 600, 615, CalendarBlockType::Focus),
+            get_block("Zero", 600, 600, CalendarBlockType::Busy),
+        ];
+
+        assert_eq!(find_conflicts(&blocks), vec![]);
+    }
+
+    #[test]
+    fn get_conflicting_block_ids_lists_both_sides() {
+        let blocks = vec![
+            get_block("Review", 540, 660, CalendarBlockType::Busy),
+            get_block("Commute", 600, 630, CalendarBlockType::Travel),
+            get_block("Lunch", 720, 780, CalendarBlockType::Busy),
+        ];
+
+        let conflicting_block_ids = get_conflicting_block_ids(&find_conflicts(&blocks));
+
+        assert_eq!(
+            conflicting_block_ids,
+            HashSet::from([blocks[0].id, blocks[1].id])
+        );
+    }
+}
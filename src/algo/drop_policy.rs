@@ -0,0 +1,234 @@
+use crate::prelude::*;
+
+const MINUTES_IN_DAY: i64 = 1440;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum DropPolicy {
+    #[default]
+    AllowAll,
+    ForbidBusyOverlap,
+    RequireAvailable,
+}
+
+impl DropPolicy {
+    pub fn next(&self) -> DropPolicy {
+        match self {
+            DropPolicy::AllowAll => DropPolicy::ForbidBusyOverlap,
+            DropPolicy::ForbidBusyOverlap => DropPolicy::RequireAvailable,
+            DropPolicy::RequireAvailable => DropPolicy::AllowAll,
+        }
+    }
+}
+
+impl Display for DropPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let drop_policy = match self {
+            DropPolicy::AllowAll => "Allow All",
+            DropPolicy::ForbidBusyOverlap => "Forbid Busy Overlap",
+            DropPolicy::RequireAvailable => "Require Available",
+        };
+        write!(f, "{drop_policy}")
+    }
+}
+
+#[derive(Debug, Error, Clone, PartialEq)]
+pub enum DropRejection {
+    #[error("block would not fit inside the day")]
+    OutsideDay,
+    #[error("block would overlap busy block \"{0}\"")]
+    BusyOverlap(String),
+    #[error("block must land inside an available block")]
+    OutsideAvailable,
+}
+
+impl DropPolicy {
+    /// Moves `block` to `start_minute` and checks the result against the day's other blocks.
+    /// Blocks are never allowed to leave the day, regardless of the policy.
+    pub fn evaluate(
+        &self,
+        block: &CalendarBlock,
+        start_minute: i64,
+        blocks: &[CalendarBlock],
+    ) -> Result<CalendarBlock, DropRejection> {
+        let duration = (block.end_minute - block.start_minute) as i64;
+        if start_minute < 0 || start_minute + duration > MINUTES_IN_DAY {
+            return Err(DropRejection::OutsideDay);
+        }
+
+        let moved_block = CalendarBlock {
+            start_minute: start_minute as u32,
+            end_minute: (start_minute + duration) as u32,
+            ..block.clone()
+        };
+
+        let mut others = blocks.iter().filter(|other| other.id != block.id);
+
+        match (self, moved_block.block_type.busyness()) {
+            (DropPolicy::ForbidBusyOverlap, Busyness::Busy) => {
+                match others.find(|other| {
+                    other.block_type.busyness() == Busyness::Busy && moved_block.intersects(other)
+                }) {
+                    Some(other) => Err(DropRejection::BusyOverlap(other.label.clone())),
+                    None => Ok(moved_block),
+                }
+            }
+            (DropPolicy::RequireAvailable, Busyness::Busy | Busyness::Tentative) => {
+                match others.any(|other| {
+                    other.block_type.busyness() == Busyness::Free
+                        && other.start_minute <= moved_block.start_minute
+                        && moved_block.end_minute <= other.end_minute
+                }) {
+                    true => Ok(moved_block),
+                    false => Err(DropRejection::OutsideAvailable),
+                }
+            }
+            _ => Ok(moved_block),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_block(
+        label: &str,
+        start_minute: u32,
+        end_minute: u32,
+        block_type: CalendarBlockType,
+    ) -> CalendarBlock {
+        CalendarBlock {
+            id: Uuid::new_v4(),
+            start_minute,
+            end_minute,
+            block_type,
+            subtree_depth: 0,
+            label: label.to_string(),
+            calendar_id: DEFAULT_CALENDAR_ID.to_string(),
+            metadata: BlockMetadata::default(),
+        }
+    }
+
+    /// A busy review, an out-of-office afternoon and an available morning.
+    fn get_blocks() -> Vec<CalendarBlock> {
+        vec![
+            get_block("Review", 600, 660, CalendarBlockType::Busy),
+            get_block("Offsite", 780, 1020, CalendarBlockType::OutOfOffice),
+            get_block("Open", 480, 600, CalendarBlockType::Available),
+        ]
+    }
+
+    #[test]
+    fn evaluate_moves_the_block_keeping_its_duration() {
+        let standup = get_block("Standup", 540, 555, CalendarBlockType::Busy);
+
+        let moved = DropPolicy::AllowAll
+            .evaluate(&standup, 700, &get_blocks())
+            .unwrap();
+
+        assert_eq!((moved.start_minute, moved.end_minute), (700, 715));
+        assert_eq!(moved.id, standup.id);
+    }
+
+    #[test]
+    fn evaluate_keeps_blocks_inside_the_day_under_every_policy() {
+        let standup = get_block("Standup", 540, 555, CalendarBlockType::Busy);
+
+        [
+            DropPolicy::AllowAll,
+            DropPolicy::ForbidBusyOverlap,
+            DropPolicy::RequireAvailable,
+        ]
+        .iter()
+        .for_each(|drop_policy| {
+            assert_eq!(
+                drop_policy.evaluate(&standup, -1, &[]),
+                Err(DropRejection::OutsideDay)
+            );
+            assert_eq!(
+                drop_policy.evaluate(&standup, 1426, &[]),
+                Err(DropRejection::OutsideDay)
+            );
+        });
+        assert!(DropPolicy::AllowAll.evaluate(&standup, 1425, &[]).is_ok());
+    }
+
+    #[test]
+    fn forbid_busy_overlap_rejects_busy_and_out_of_office_blocks() {
+        let blocks = get_blocks();
+        let standup = get_block("Standup", 540, 555, CalendarBlockType::Busy);
+
+        assert_eq!(
+            DropPolicy::ForbidBusyOverlap.evaluate(&standup, 650, &blocks),
+            Err(DropRejection::BusyOverlap(String::from("Review")))
+        );
+        assert_eq!(
+            DropPolicy::ForbidBusyOverlap.evaluate(&standup, 900, &blocks),
+            Err(DropRejection::BusyOverlap(String::from("Offsite")))
+        );
+        assert!(DropPolicy::AllowAll
+            .evaluate(&standup, 650, &blocks)
+            .is_ok());
+    }
+
+    #[test]
+    fn forbid_busy_overlap_allows_adjacent_drops_and_drops_onto_the_block_itself() {
+        let blocks = get_blocks();
+        let standup = get_block("Standup", 540, 555, CalendarBlockType::Busy);
+
+        assert!(DropPolicy::ForbidBusyOverlap
+            .evaluate(&standup, 585, &blocks)
+            .is_ok());
+        assert!(DropPolicy::ForbidBusyOverlap
+            .evaluate(&standup, 660, &blocks)
+            .is_ok());
+        assert!(DropPolicy::ForbidBusyOverlap
+            .evaluate(&standup, 765, &blocks)
+            .is_ok());
+
+        let review = &blocks[0];
+        assert!(DropPolicy::ForbidBusyOverlap
+            .evaluate(review, 620, &blocks)
+            .is_ok());
+    }
+
+    #[test]
+    fn forbid_busy_overlap_lets_free_blocks_overlap() {
+        let lunch = get_block("Lunch", 720, 780, CalendarBlockType::Available);
+
+        assert!(DropPolicy::ForbidBusyOverlap
+            .evaluate(&lunch, 610, &get_blocks())
+            .is_ok());
+    }
+
+    #[test]
+    fn require_available_needs_an_available_block_around_the_drop() {
+        let blocks = get_blocks();
+        let standup = get_block("Standup", 700, 715, CalendarBlockType::Busy);
+
+        assert!(DropPolicy::RequireAvailable
+            .evaluate(&standup, 480, &blocks)
+            .is_ok());
+        assert!(DropPolicy::RequireAvailable
+            .evaluate(&standup, 585, &blocks)
+            .is_ok());
+        assert_eq!(
+            DropPolicy::RequireAvailable.evaluate(&standup, 590, &blocks),
+            Err(DropRejection::OutsideAvailable)
+        );
+        assert_eq!(
+            DropPolicy::RequireAvailable.evaluate(&standup, 700, &blocks),
+            Err(DropRejection::OutsideAvailable)
+        );
+
+        let maybe = get_block("Maybe", 700, 715, CalendarBlockType::Tentative);
+        assert_eq!(
+            DropPolicy::RequireAvailable.evaluate(&maybe, 700, &blocks),
+            Err(DropRejection::OutsideAvailable)
+        );
+        let open = &blocks[2];
+        assert!(DropPolicy::RequireAvailable
+            .evaluate(open, 1000, &blocks)
+            .is_ok());
+    }
+}
@@ -0,0 +1,122 @@
+use crate::prelude::*;
+
+#[derive(Debug, Error)]
+pub enum EditError {
+    #[error(transparent)]
+    Rejected(#[from] DropRejection),
+    #[error(transparent)]
+    Store(#[from] StoreError),
+}
+
+/// Where a block is being dragged to, in minutes.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct DragPosition {
+    pub block_id: Uuid,
+    pub start_minute: i64,
+}
+
+/// Moves a stored block to `start_minute`, checking the drop policy against the
+/// other blocks of its day. Fails with a version conflict if the block changed
+/// since it was read.
+pub fn move_stored_block(
+    store: &mut impl CalendarStore,
+    id: Uuid,
+    start_minute: i64,
+    drop_policy: DropPolicy,
+) -> Result<StoredBlock, EditError> {
+    let record = store.get(id)?;
+    let blocks: Vec<CalendarBlock> = store
+        .list(&record.day)?
+        .into_iter()
+        .map(|record| record.block)
+        .collect();
+
+    let moved_block = drop_policy.evaluate(&record.block, start_minute, &blocks)?;
+    Ok(store.update(moved_block, record.version)?)
+}
+
+/// Moves a block of a day held in memory, as `Calendar` does on a drop. The
+/// blocks go into an `InMemoryStore` and through `move_stored_block`, so the
+/// page edits them the same way the CLI and server edit their stores.
+/// Returns the moved block and the day's blocks in canonical order.
+pub fn move_day_block(
+    blocks: &[CalendarBlock],
+    id: Uuid,
+    start_minute: i64,
+    drop_policy: DropPolicy,
+) -> Result<(CalendarBlock, Vec<CalendarBlock>), EditError> {
+    // the page holds a single day, so any name does
+    let day = Day(String::from("page"));
+    let mut store = InMemoryStore::new();
+    store.insert_all(&day, blocks.to_vec())?;
+
+    let record = move_stored_block(&mut store, id, start_minute, drop_policy)?;
+    let updated_blocks = store
+        .list(&day)?
+        .into_iter()
+        .map(|record| record.block)
+        .collect();
+    Ok((record.block, updated_blocks))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_block(
+        label: &str,
+        start_minute: u32,
+        end_minute: u32,
+        block_type: CalendarBlockType,
+    ) -> CalendarBlock {
+        CalendarBlock {
+            id: Uuid::new_v4(),
+            start_minute,
+            end_minute,
+            block_type,
+            subtree_depth: 0,
+            label: label.to_string(),
+            calendar_id: DEFAULT_CALENDAR_ID.to_string(),
+            metadata: BlockMetadata::default(),
+        }
+    }
+
+    #[test]
+    fn move_day_block_moves_the_block_and_keeps_canonical_order() {
+        let standup = get_block("Standup", 540, 555, CalendarBlockType::Busy);
+        let review = get_block("Review", 600, 660, CalendarBlockType::Busy);
+
+        let (moved_block, blocks) = move_day_block(
+            &[standup.clone(), review.clone()],
+            standup.id,
+            720,
+            DropPolicy::ForbidBusyOverlap,
+        )
+        .unwrap();
+
+        assert_eq!(
+            (moved_block.start_minute, moved_block.end_minute),
+            (720, 735)
+        );
+        let labels: Vec<&str> = blocks.iter().map(|block| block.label.as_str()).collect();
+        assert_eq!(labels, ["Review", "Standup"]);
+    }
+
+    #[test]
+    fn move_day_block_goes_through_the_drop_policy() {
+        let standup = get_block("Standup", 540, 555, CalendarBlockType::Busy);
+        let review = get_block("Review", 600, 660, CalendarBlockType::Busy);
+
+        let result = move_day_block(
+            &[standup.clone(), review],
+            standup.id,
+            610,
+            DropPolicy::ForbidBusyOverlap,
+        );
+
+        assert!(matches!(
+            result,
+            Err(EditError::Rejected(DropRejection::BusyOverlap(label))) if label == "Review"
+        ));
+    }
+}
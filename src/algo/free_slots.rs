@@ -0,0 +1,104 @@
+use crate::prelude::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct FreeSlot {
+    pub start_minute: u32,
+    pub end_minute: u32, // exclusive
+}
+
+/// Gaps of at least `min_duration` minutes inside `start_minute..end_minute`
+/// that only free blocks cover. Tentative blocks are not counted as free,
+/// and zero-length blocks are ignored.
+pub fn find_free_slots(
+    blocks: &[CalendarBlock],
+    start_minute: u32,
+    end_minute: u32,
+    min_duration: u32,
+) -> Vec<FreeSlot> {
+    let mut busy_intervals: Vec<(u32, u32)> = blocks
+        .iter()
+        .filter(|block| block.block_type.busyness() > Busyness::Free)
+        // a block without length takes no time, and would only split a slot in two
+        .filter(|block| block.start_minute < block.end_minute)
+        .map(|block| (block.start_minute, block.end_minute))
+        .collect();
+    busy_intervals.sort();
+
+    let mut free_slots = Vec::new();
+    let mut cursor = start_minute;
+
+    busy_intervals
+        .iter()
+        .chain(std::iter::once(&(end_minute, end_minute)))
+        .for_each(|(busy_start, busy_end)| {
+            let slot_end = (*busy_start).min(end_minute);
+            if slot_end > cursor && slot_end - cursor >= min_duration.max(1) {
+                free_slots.push(FreeSlot {
+                    start_minute: cursor,
+                    end_minute: slot_end,
+                });
+            }
+            cursor = cursor.max(*busy_end);
+        });
+
+    free_slots
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_block(
+        label: &str,
+        start_minute: u32,
+        end_minute: u32,
+        block_type: CalendarBlockType,
+    ) -> CalendarBlock {
+        CalendarBlock {
+            id: Uuid::new_v4(),
+            start_minute,
+            end_minute,
+            block_type,
+            subtree_depth: 0,
+            label: label.to_string(),
+            calendar_id: DEFAULT_CALENDAR_ID.to_string(),
+            metadata: BlockMetadata::default(),
+        }
+    }
+
+    fn get_slots(free_slots: &[FreeSlot]) -> Vec<(u32, u32)> {
+        free_slots
+            .iter()
+            .map(|slot| (slot.start_minute, slot.end_minute))
+            .collect()
+    }
+
+    #[test]
+    fn find_free_slots_leaves_out_busy_time() {
+        let blocks = [
+            get_block("Standup", 540, 555, CalendarBlockType::Busy),
+            get_block("Lunch", 720, 780, CalendarBlockType::Available),
+            get_block("Review", 840, 900, CalendarBlockType::Tentative),
+            get_block("Focus", 870, 960, CalendarBlockType::Focus),
+        ];
+
+        let free_slots = find_free_slots(&blocks, 480, 1020, 30);
+
+        assert_eq!(
+            get_slots(&free_slots),
+            [(480, 540), (555, 840), (960, 1020)]
+        );
+    }
+
+    #[test]
+    fn find_free_slots_ignores_zero_length_blocks() {
+        let blocks = [
+            get_block("Reminder", 600, 600, CalendarBlockType::Busy),
+            get_block("Standup", 540, 555, CalendarBlockType::Busy),
+        ];
+
+        let free_slots = find_free_slots(&blocks, 480, 720, 15);
+
+        assert_eq!(get_slots(&free_slots), [(480, 540), (555, 720)]);
+    }
+}
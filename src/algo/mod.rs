@@ -1,2 +1,7 @@
+pub mod block_metadata;
 pub mod calendar_block;
 pub mod calendar_tree;
+pub mod conflicts;
+pub mod drop_policy;
+pub mod editing;
+pub mod free_slots;
@@ -0,0 +1,732 @@
+use std::io::Read;
+use std::process::ExitCode;
+
+use cal_ender::prelude::*;
+
+const USAGE: &str = "\
+Usage: cal-ender-cli <COMMAND> [OPTIONS] [INPUT]
+
+Reads a day's blocks from a JSON array or an .ics file (`-` reads JSON from stdin),
+or from the --date day of a --store file when no INPUT is given.
+
+Commands:
+  layout        Print the stacked layout of every block
+  conflicts     List overlapping busy blocks, exiting with 1 if there are any
+  free-slots    List the gaps between blocks that aren't free
+  import        Add the INPUT blocks to the --store file under --date, all or none,
+                skipping blocks imported before
+  move          Move the --id block of the --store file to --start
+
+Options:
+  --format <json|csv|ascii>                    Output format [default: ascii]
+  --strategy <subtree-depth|stack-position>    Stacking strategy [default: subtree-depth]
+  --date <YYYY-MM-DD>                          Day to read from an .ics file [default: first event's day]
+  --start-hour <HOUR>                          Start of the visible range [default: 0]
+  --end-hour <HOUR>                            End of the visible range [default: 24]
+  --min-duration <MINUTES>                     Shortest free slot to report [default: 15]
+  --store <FILE>                               JSON file blocks are kept in between runs
+  --id <UUID>                                  Block to move
+  --start <MINUTE>                             Minute of the day the moved block starts at
+  --policy <allow-all|forbid-busy-overlap|require-available>
+                                               Drop policy checked by move [default: allow-all]
+";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Command {
+    Layout,
+    Conflicts,
+    FreeSlots,
+    Import,
+    Move,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum OutputFormat {
+    Json,
+    Csv,
+    Ascii,
+}
+
+#[derive(Debug)]
+struct Args {
+    command: Command,
+    input: Option<String>,
+    format: OutputFormat,
+    stacking_strategy: StackingStrategy,
+    date: Option<String>,
+    config: CalendarConfig,
+    min_duration: u32,
+    store: Option<String>,
+    id: Option<Uuid>,
+    start_minute: Option<u32>,
+    drop_policy: DropPolicy,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Args, String> {
+    let command = match args.next().as_deref() {
+        Some("layout") => Command::Layout,
+        Some("conflicts") => Command::Conflicts,
+        Some("free-slots") => Command::FreeSlots,
+        Some("import") => Command::Import,
+        Some("move") => Command::Move,
+        Some(command) => return Err(format!("unknown command \"{command}\"")),
+        None => return Err(String::from("missing command")),
+    };
+
+    let mut input = None;
+    let mut format = OutputFormat::Ascii;
+    let mut stacking_strategy = StackingStrategy::default();
+    let mut date = None;
+    let mut config = CalendarConfig::default();
+    let mut min_duration = 15;
+    let mut store = None;
+    let mut id = None;
+    let mut start_minute = None;
+    let mut drop_policy = DropPolicy::default();
+
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("missing value for {arg}"));
+        match arg.as_str() {
+            "--format" => {
+                format = match value()?.as_str() {
+                    "json" => OutputFormat::Json,
+                    "csv" => OutputFormat::Csv,
+                    "ascii" => OutputFormat::Ascii,
+                    other => return Err(format!("unknown format \"{other}\"")),
+                }
+            }
+            "--strategy" => {
+                stacking_strategy = match value()?.as_str() {
+                    "subtree-depth" => StackingStrategy::SubtreeDepth,
+                    "stack-position" => StackingStrategy::StackPosition,
+                    other => return Err(format!("unknown strategy \"{other}\"")),
+                }
+            }
+            "--date" => {
+                let day = Day(value()?);
+                day.validate().map_err(|e| e.to_string())?;
+                date = Some(day.0)
+            }
+            "--start-hour" => config.start_hour = parse_number(&arg, &value()?)?,
+            "--end-hour" => config.end_hour = parse_number(&arg, &value()?)?,
+            "--min-duration" => min_duration = parse_number(&arg, &value()?)?,
+            "--store" => store = Some(value()?),
+            "--id" => {
+                let value = value()?;
+                id = Some(
+                    Uuid::parse_str(&value)
+                        .map_err(|_| format!("--id expects a uuid, got \"{value}\""))?,
+                )
+            }
+            "--start" => start_minute = Some(parse_number(&arg, &value()?)?),
+            "--policy" => {
+                drop_policy = match value()?.as_str() {
+                    "allow-all" => DropPolicy::AllowAll,
+                    "forbid-busy-overlap" => DropPolicy::ForbidBusyOverlap,
+                    "require-available" => DropPolicy::RequireAvailable,
+                    other => return Err(format!("unknown policy \"{other}\"")),
+                }
+            }
+            _ if input.is_none() => input = Some(arg),
+            _ => return Err(format!("unexpected argument \"{arg}\"")),
+        }
+    }
+
+    if config.start_hour >= config.end_hour || config.end_hour > 24 {
+        return Err(String::from(
+            "the visible range must lie within 0..=24 hours",
+        ));
+    }
+
+    match command {
+        Command::Import if input.is_none() || store.is_none() || date.is_none() => {
+            return Err(String::from("import needs an input, --store and --date"))
+        }
+        Command::Move if store.is_none() || id.is_none() || start_minute.is_none() => {
+            return Err(String::from("move needs --store, --id and --start"))
+        }
+        Command::Layout | Command::Conflicts | Command::FreeSlots
+            if input.is_none() && (store.is_none() || date.is_none()) =>
+        {
+            return Err(String::from("missing input, or --store and --date"))
+        }
+        _ => {}
+    }
+
+    Ok(Args {
+        command,
+        input,
+        format,
+        stacking_strategy,
+        date,
+        config,
+        min_duration,
+        store,
+        id,
+        start_minute,
+        drop_policy,
+    })
+}
+
+fn parse_number(arg: &str, value: &str) -> Result<u32, String> {
+    value
+        .parse()
+        .map_err(|_| format!("{arg} expects a number, got \"{value}\""))
+}
+
+fn read_blocks(input: &str, date: Option<&str>) -> Result<Vec<CalendarBlock>, String> {
+    let source = match input {
+        "-" => {
+            let mut source = String::new();
+            std::io::stdin()
+                .read_to_string(&mut source)
+                .map_err(|e| e.to_string())?;
+            source
+        }
+        path => std::fs::read_to_string(path).map_err(|e| format!("{path}: {e}"))?,
+    };
+
+    let mut blocks = match input.ends_with(".ics") {
+        true => {
+            let events = parse_ics(&source).map_err(|e| format!("{input}: {e}"))?;
+            let date = date
+                .map(String::from)
+                .or_else(|| events.first().map(|event| event.date.clone()));
+            events
+                .into_iter()
+                .filter(|event| Some(&event.date) == date.as_ref())
+                .map(|event| event.block)
+                .collect()
+        }
+        false => serde_json::from_str::<Vec<CalendarBlock>>(&source)
+            .map_err(|e| format!("{input}: {e}"))?,
+    };
+
+    blocks
+        .iter()
+        .try_for_each(CalendarBlock::validate)
+        .map_err(|e| format!("{input}: {e}"))?;
+    blocks.sort_by_key(CalendarBlock::sort_key);
+    Ok(blocks)
+}
+
+fn escape_csv(field: &str) -> String {
+    match field.contains([',', '"', '\n']) {
+        true => format!("\"{}\"", field.replace('"', "\"\"")),
+        false => field.to_string(),
+    }
+}
+
+fn print_layout(blocks: &[CalendarBlock], args: &Args) -> Result<(), String> {
+    let flattened_blocks = CalendarBlockTree::from_blocks(blocks)
+        .map_err(|e| e.to_string())?
+        .traverse();
+    let layouts = layout_blocks(&flattened_blocks, args.stacking_strategy, &args.config);
+
+    match args.format {
+        OutputFormat::Json => println!(
+            "{}",
+            serde_json::to_string_pretty(&layouts).map_err(|e| e.to_string())?
+        ),
+        OutputFormat::Csv => {
+            println!(
+                "id,label,block_type,start_minute,end_minute,stack_position,top,height,left,width"
+            );
+            layouts.iter().for_each(|layout| {
+                println!(
+                    "{},{},{},{},{},{},{},{},{},{}",
+                    layout.block.id,
+                    escape_csv(&layout.block.label),
+                    layout.block.block_type,
+                    layout.block.start_minute,
+                    layout.block.end_minute,
+                    layout.stack_position,
+                    layout.top,
+                    layout.height,
+                    layout.left,
+                    layout.width
+                )
+            });
+        }
+        OutputFormat::Ascii => println!(
+            "{}",
+            render_text(&layouts, &args.config, &TextRenderOptions::default())
+        ),
+    }
+
+    Ok(())
+}
+
+fn print_conflicts(conflicts: &[Conflict], args: &Args) -> Result<(), String> {
+    let time_format = args.config.time_format;
+
+    match args.format {
+        OutputFormat::Json => println!(
+            "{}",
+            serde_json::to_string_pretty(conflicts).map_err(|e| e.to_string())?
+        ),
+        OutputFormat::Csv => {
+            println!("first_id,first_label,second_id,second_label,kind,start_minute,end_minute,overlap_minutes");
+            conflicts.iter().for_each(|conflict| {
+                println!(
+                    "{},{},{},{},{},{},{},{}",
+                    conflict.first.id,
+                    escape_csv(&conflict.first.label),
+                    conflict.second.id,
+                    escape_csv(&conflict.second.label),
+                    conflict.kind,
+                    conflict.start_minute,
+                    conflict.end_minute,
+                    conflict.overlap_minutes
+                )
+            });
+        }
+        OutputFormat::Ascii => conflicts.iter().for_each(|conflict| {
+            println!(
+                "{} overlaps {} during {} ({}, {})",
+                conflict.first.label,
+                conflict.second.label,
+                time_format.format_range(conflict.start_minute, conflict.end_minute),
+                conflict.kind,
+                format_duration(conflict.overlap_minutes)
+            )
+        }),
+    }
+
+    Ok(())
+}
+
+fn print_free_slots(free_slots: &[FreeSlot], args: &Args) -> Result<(), String> {
+    let time_format = args.config.time_format;
+
+    match args.format {
+        OutputFormat::Json => println!(
+            "{}",
+            serde_json::to_string_pretty(free_slots).map_err(|e| e.to_string())?
+        ),
+        OutputFormat::Csv => {
+            println!("start_minute,end_minute");
+            free_slots.iter().for_each(|free_slot| {
+                println!("{},{}", free_slot.start_minute, free_slot.end_minute)
+            });
+        }
+        OutputFormat::Ascii => free_slots.iter().for_each(|free_slot| {
+            println!(
+                "{} ({})",
+                time_format.format_range(free_slot.start_minute, free_slot.end_minute),
+                format_duration(free_slot.end_minute - free_slot.start_minute)
+            )
+        }),
+    }
+
+    Ok(())
+}
+
+fn open_store(args: &Args) -> Result<FileStore, String> {
+    let path = args.store.as_deref().ok_or("missing --store")?;
+    FileStore::open(path).map_err(|e| e.to_string())
+}
+
+fn get_day(args: &Args) -> Result<Day, String> {
+    args.date
+        .clone()
+        .map(Day)
+        .ok_or_else(|| String::from("missing --date"))
+}
+
+fn read_day_blocks(args: &Args) -> Result<Vec<CalendarBlock>, String> {
+    match &args.input {
+        Some(input) => read_blocks(input, args.date.as_deref()),
+        None => Ok(open_store(args)?
+            .list(&get_day(args)?)
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .map(|record| record.block)
+            .collect()),
+    }
+}
+
+fn print_record(record: &StoredBlock, args: &Args) -> Result<(), String> {
+    let block = &record.block;
+
+    match args.format {
+        OutputFormat::Json => println!(
+            "{}",
+            serde_json::to_string_pretty(record).map_err(|e| e.to_string())?
+        ),
+        OutputFormat::Csv => println!(
+            "{},{},{},{},{},{},{}",
+            record.day,
+            record.version,
+            block.id,
+            escape_csv(&block.label),
+            block.block_type,
+            block.start_minute,
+            block.end_minute
+        ),
+        OutputFormat::Ascii => println!(
+            "{} {} {} (v{}) {}",
+            record.day,
+            block.label,
+            args.config
+                .time_format
+                .format_range(block.start_minute, block.end_minute),
+            record.version,
+            block.id
+        ),
+    }
+
+    Ok(())
+}
+
+/// Whether `block` was imported before: ICS events keep their id across
+/// imports, JSON blocks without an id can only be matched by their content.
+fn is_already_stored(block: &CalendarBlock, records: &[StoredBlock]) -> bool {
+    records.iter().any(|record| {
+        let stored_block = &record.block;
+        stored_block.id == block.id
+            || (stored_block.label == block.label
+                && stored_block.start_minute == block.start_minute
+                && stored_block.end_minute == block.end_minute
+                && stored_block.block_type == block.block_type
+                && stored_block.calendar_id == block.calendar_id)
+    })
+}
+
+fn import_blocks(args: &Args) -> Result<(), String> {
+    let mut store = open_store(args)?;
+    let day = get_day(args)?;
+    let records = store.list(&day).map_err(|e| e.to_string())?;
+
+    let (skipped_blocks, new_blocks): (Vec<CalendarBlock>, Vec<CalendarBlock>) =
+        read_day_blocks(args)?
+            .into_iter()
+            .partition(|block| is_already_stored(block, &records));
+    if !skipped_blocks.is_empty() {
+        eprintln!(
+            "skipping {} blocks already in the store",
+            skipped_blocks.len()
+        );
+    }
+
+    store
+        .insert_all(&day, new_blocks)
+        .map_err(|e| e.to_string())?
+        .iter()
+        .try_for_each(|record| print_record(record, args))
+}
+
+fn run(args: Args) -> Result<ExitCode, String> {
+    match args.command {
+        Command::Import => import_blocks(&args).map(|_| ExitCode::SUCCESS),
+        Command::Move => {
+            let mut store = open_store(&args)?;
+            let id = args.id.ok_or("missing --id")?;
+            let start_minute = args.start_minute.ok_or("missing --start")?;
+            let record = move_stored_block(&mut store, id, start_minute as i64, args.drop_policy)
+                .map_err(|e| e.to_string())?;
+            print_record(&record, &args).map(|_| ExitCode::SUCCESS)
+        }
+        Command::Layout => print_layout(&read_day_blocks(&args)?, &args).map(|_| ExitCode::SUCCESS),
+        Command::Conflicts => {
+            let conflicts = find_conflicts(&read_day_blocks(&args)?);
+            print_conflicts(&conflicts, &args)?;
+            Ok(match conflicts.is_empty() {
+                true => ExitCode::SUCCESS,
+                false => ExitCode::from(1),
+            })
+        }
+        Command::FreeSlots => {
+            let free_slots = find_free_slots(
+                &read_day_blocks(&args)?,
+                args.config.visible_start_minute(),
+                args.config.visible_end_minute(),
+                args.min_duration,
+            );
+            print_free_slots(&free_slots, &args).map(|_| ExitCode::SUCCESS)
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(message) => {
+            eprintln!("error: {message}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+
+    match run(args) {
+        Ok(exit_code) => exit_code,
+        Err(message) => {
+            eprintln!("error: {message}");
+            ExitCode::from(2)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    /// A path in the system's temp directory whose file is removed when dropped.
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(extension: &str) -> Self {
+            TempFile(std::env::temp_dir().join(format!("cal-ender-{}.{extension}", Uuid::new_v4())))
+        }
+
+        fn with_contents(extension: &str, contents: &str) -> Self {
+            let temp_file = TempFile::new(extension);
+            std::fs::write(&temp_file.0, contents).unwrap();
+            temp_file
+        }
+
+        fn path(&self) -> String {
+            self.0.display().to_string()
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    fn parse(args: &[&str]) -> Result<Args, String> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    const BLOCKS: &str = r#"[
+        { "label": "Lunch", "start_minute": 720, "end_minute": 780, "block_type": "Available" },
+        { "label": "Standup", "start_minute": 540, "end_minute": 555, "block_type": "Busy" }
+    ]"#;
+
+    #[test]
+    fn parse_args_reads_the_command_and_options() {
+        let args = parse(&[
+            "layout",
+            "day.json",
+            "--format",
+            "csv",
+            "--strategy",
+            "stack-position",
+            "--start-hour",
+            "8",
+            "--end-hour",
+            "18",
+        ])
+        .unwrap();
+
+        assert_eq!(args.command, Command::Layout);
+        assert_eq!(args.input.as_deref(), Some("day.json"));
+        assert_eq!(args.format, OutputFormat::Csv);
+        assert_eq!(args.stacking_strategy, StackingStrategy::StackPosition);
+        assert_eq!((args.config.start_hour, args.config.end_hour), (8, 18));
+
+        let args = parse(&[
+            "move",
+            "--store",
+            "calendar.json",
+            "--id",
+            &Uuid::nil().to_string(),
+            "--start",
+            "540",
+            "--policy",
+            "require-available",
+        ])
+        .unwrap();
+        assert_eq!(args.command, Command::Move);
+        assert_eq!(args.id, Some(Uuid::nil()));
+        assert_eq!(args.start_minute, Some(540));
+        assert_eq!(args.drop_policy, DropPolicy::RequireAvailable);
+    }
+
+    #[test]
+    fn parse_args_rejects_bad_arguments() {
+        [
+            (vec![], "missing command"),
+            (vec!["print"], "unknown command"),
+            (
+                vec!["layout", "day.json", "--format", "xml"],
+                "unknown format",
+            ),
+            (
+                vec!["layout", "day.json", "--start-hour", "nine"],
+                "expects a number",
+            ),
+            (
+                vec![
+                    "layout",
+                    "day.json",
+                    "--start-hour",
+                    "18",
+                    "--end-hour",
+                    "8",
+                ],
+                "visible range",
+            ),
+            (
+                vec!["layout", "day.json", "--end-hour", "25"],
+                "visible range",
+            ),
+            (
+                vec!["layout", "day.json", "--date", "2024-02-30"],
+                "invalid day",
+            ),
+            (
+                vec!["layout", "day.json", "--format"],
+                "missing value for --format",
+            ),
+            (
+                vec!["layout", "day.json", "other.json"],
+                "unexpected argument",
+            ),
+            (vec!["layout"], "missing input"),
+            (
+                vec!["import", "day.json", "--store", "calendar.json"],
+                "import needs",
+            ),
+            (
+                vec!["move", "--store", "calendar.json", "--start", "540"],
+                "move needs",
+            ),
+            (vec!["move", "--id", "42"], "expects a uuid"),
+        ]
+        .iter()
+        .for_each(|(args, message)| {
+            let error = parse(args).unwrap_err();
+            assert!(error.contains(message), "{args:?}: {error}");
+        });
+    }
+
+    #[test]
+    fn escape_csv_quotes_fields_that_need_it() {
+        assert_eq!(escape_csv("Standup"), "Standup");
+        assert_eq!(escape_csv("Lunch, late"), "\"Lunch, late\"");
+        assert_eq!(escape_csv("The \"big\" one"), "\"The \"\"big\"\" one\"");
+        assert_eq!(escape_csv("two\nlines"), "\"two\nlines\"");
+    }
+
+    #[test]
+    fn read_blocks_reads_json_in_block_order() {
+        let input = TempFile::with_contents("json", BLOCKS);
+
+        let blocks = read_blocks(&input.path(), None).unwrap();
+
+        let labels: Vec<&str> = blocks.iter().map(|block| block.label.as_str()).collect();
+        assert_eq!(labels, vec!["Standup", "Lunch"]);
+        assert_eq!(blocks[0].calendar_id, DEFAULT_CALENDAR_ID);
+    }
+
+    #[test]
+    fn read_blocks_reads_one_day_of_an_ics_file() {
+        let input = TempFile::with_contents(
+            "ics",
+            "BEGIN:VCALENDAR\r
+BEGIN:VEVENT\r
+UID:planning\r
+DTSTART:20240501T090000\r
+DTEND:20240501T100000\r
+SUMMARY:Planning\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+UID:retro\r
+DTSTART:20240502T160000\r
+DTEND:20240502T170000\r
+SUMMARY:Retro\r
+END:VEVENT\r
+END:VCALENDAR\r
+",
+        );
+
+        let blocks = read_blocks(&input.path(), None).unwrap();
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].label, "Planning");
+
+        let blocks = read_blocks(&input.path(), Some("2024-05-02")).unwrap();
+        assert_eq!(blocks.len(), 1);
+        assert_eq!((blocks[0].start_minute, blocks[0].end_minute), (960, 1020));
+    }
+
+    #[test]
+    fn read_blocks_rejects_blocks_outside_the_day() {
+        let backwards = TempFile::with_contents(
+            "json",
+            r#"[{ "label": "Backwards", "start_minute": 600, "end_minute": 500, "block_type": "Busy" }]"#,
+        );
+        let error = read_blocks(&backwards.path(), None).unwrap_err();
+        assert!(error.contains("ends before it starts"), "{error}");
+
+        let too_late = TempFile::with_contents(
+            "json",
+            r#"[{ "label": "Late", "start_minute": 600, "end_minute": 2000, "block_type": "Busy" }]"#,
+        );
+        let error = read_blocks(&too_late.path(), None).unwrap_err();
+        assert!(error.contains("ends after the end of the day"), "{error}");
+
+        let missing = TempFile::new("json");
+        assert!(read_blocks(&missing.path(), None).is_err());
+    }
+
+    #[test]
+    fn import_skips_blocks_imported_before() {
+        let input = TempFile::with_contents("json", BLOCKS);
+        let store = TempFile::new("json");
+        let args = parse(&[
+            "import",
+            &input.path(),
+            "--store",
+            &store.path(),
+            "--date",
+            "2024-05-01",
+        ])
+        .unwrap();
+
+        import_blocks(&args).unwrap();
+        import_blocks(&args).unwrap();
+
+        let records = FileStore::open(&store.0)
+            .unwrap()
+            .list(&Day(String::from("2024-05-01")))
+            .unwrap();
+        let labels: Vec<&str> = records
+            .iter()
+            .map(|record| record.block.label.as_str())
+            .collect();
+        assert_eq!(labels, vec!["Standup", "Lunch"]);
+    }
+
+    #[test]
+    fn is_already_stored_matches_by_id_or_content() {
+        let input = TempFile::with_contents("json", BLOCKS);
+        let blocks = read_blocks(&input.path(), None).unwrap();
+        let records = vec![StoredBlock {
+            day: Day(String::from("2024-05-01")),
+            version: 1,
+            block: blocks[0].clone(),
+        }];
+
+        let same_id = CalendarBlock {
+            label: String::from("Renamed"),
+            ..blocks[0].clone()
+        };
+        let same_content = CalendarBlock {
+            id: Uuid::new_v4(),
+            ..blocks[0].clone()
+        };
+        let moved = CalendarBlock {
+            start_minute: 600,
+            end_minute: 615,
+            ..same_content.clone()
+        };
+
+        assert!(is_already_stored(&same_id, &records));
+        assert!(is_already_stored(&same_content, &records));
+        assert!(!is_already_stored(&moved, &records));
+        assert!(!is_already_stored(&blocks[1], &records));
+    }
+}
@@ -0,0 +1,355 @@
+use std::collections::HashSet;
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use cal_ender::prelude::*;
+use tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tungstenite::http::StatusCode;
+use tungstenite::{Error as WsError, Message, WebSocket};
+
+const USAGE: &str = "\
+Usage: cal-ender-collab [--host <ADDR>] [--port <PORT>] [--allow-origin <ORIGIN>]...
+
+Relays edits between everyone editing the same day. Open the web calendar with
+`?collab=ws://localhost:<PORT>` to join.
+There is no authentication, so it listens on 127.0.0.1 unless --host says
+otherwise, and turns away browsers on pages other than the ORIGINs.
+";
+
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Every operation seen so far, in arrival order, and a channel to each client.
+#[derive(Default)]
+struct Room {
+    ops: Vec<Op>,
+    seen: HashSet<Timestamp>,
+    clients: HashMap<usize, Sender<String>>,
+    next_client_id: usize,
+    /// The client asked to share its blocks with the empty room, if any.
+    seeder: Option<usize>,
+}
+
+impl Room {
+    fn join(&mut self) -> (usize, Receiver<String>, String) {
+        let (sender, receiver) = mpsc::channel();
+        let client_id = self.next_client_id;
+        self.next_client_id += 1;
+        self.clients.insert(client_id, sender);
+
+        // whoever joins an empty room first shares its blocks, the others
+        // wait for them, so two windows joining at once don't both seed
+        let seed = self.ops.is_empty() && self.seeder.is_none();
+        if seed {
+            self.seeder = Some(client_id);
+        }
+
+        let sync = CollabMessage::Sync {
+            ops: self.ops.clone(),
+            seed,
+        };
+        (client_id, receiver, to_json(&sync))
+    }
+
+    /// Lets the next client to join seed the room if this one left before sharing anything.
+    fn leave(&mut self, client_id: usize) {
+        self.clients.remove(&client_id);
+        if self.seeder == Some(client_id) && self.ops.is_empty() {
+            self.seeder = None;
+        }
+    }
+
+    fn broadcast(&mut self, from: usize, message: &CollabMessage) {
+        let json = to_json(message);
+        self.clients
+            .retain(|client_id, sender| *client_id == from || sender.send(json.clone()).is_ok());
+    }
+
+    fn receive(&mut self, from: usize, message: CollabMessage) {
+        match &message {
+            CollabMessage::Op { op } => {
+                if self.seen.insert(op.stamp) {
+                    self.ops.push(op.clone());
+                    self.broadcast(from, &message);
+                }
+            }
+            CollabMessage::Drag { .. } => self.broadcast(from, &message),
+            // only the server sends the log
+            CollabMessage::Sync { .. } => {}
+        }
+    }
+}
+
+fn to_json(message: &CollabMessage) -> String {
+    serde_json::to_string(message).expect("messages always serialize")
+}
+
+/// Browsers send the page's Origin when opening a socket, which is only let
+/// through from the `--allow-origin` pages, so other sites can't join a room.
+fn check_origin(request: &Request, allowed_origins: &[String]) -> Result<(), String> {
+    let origin = match request.headers().get("Origin") {
+        Some(origin) => String::from_utf8_lossy(origin.as_bytes()).into_owned(),
+        None => return Ok(()),
+    };
+    match allowed_origins.contains(&origin) {
+        true => Ok(()),
+        false => Err(format!("origin {origin} is not allowed")),
+    }
+}
+
+fn serve(
+    room: Arc<Mutex<Room>>,
+    stream: TcpStream,
+    allowed_origins: &[String],
+) -> Result<(), Box<WsError>> {
+    // tungstenite wants the whole error response back from the handshake callback
+    #[allow(clippy::result_large_err)]
+    let accept_origin = |request: &Request, response: Response| {
+        check_origin(request, allowed_origins)
+            .map(|_| response)
+            .map_err(|message| {
+                let mut error_response = ErrorResponse::new(Some(message));
+                *error_response.status_mut() = StatusCode::FORBIDDEN;
+                error_response
+            })
+    };
+    let mut socket: WebSocket<TcpStream> =
+        tungstenite::accept_hdr(stream, accept_origin).map_err(|e| match e {
+            tungstenite::HandshakeError::Failure(e) => e,
+            tungstenite::HandshakeError::Interrupted(_) => WsError::ConnectionClosed,
+        })?;
+    // read with a short timeout so queued broadcasts are written in between
+    socket
+        .get_ref()
+        .set_read_timeout(Some(POLL_INTERVAL))
+        .map_err(WsError::Io)?;
+
+    let (client_id, receiver, sync) = room.lock().unwrap().join();
+    socket.send(Message::Text(sync))?;
+    let mut replicas = HashSet::new();
+
+    let result = loop {
+        match socket.read() {
+            Ok(Message::Text(json)) => match serde_json::from_str::<CollabMessage>(&json) {
+                Ok(message) => {
+                    if let CollabMessage::Drag { replica, .. } = &message {
+                        replicas.insert(*replica);
+                    }
+                    room.lock().unwrap().receive(client_id, message);
+                }
+                Err(e) => eprintln!("client {client_id} sent an invalid message: {e}"),
+            },
+            Ok(Message::Close(_)) => break Ok(()),
+            Ok(_) => {}
+            Err(WsError::Io(e))
+                if matches!(
+                    e.kind(),
+                    std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+                ) => {}
+            Err(WsError::ConnectionClosed) => break Ok(()),
+            Err(e) => break Err(Box::new(e)),
+        }
+
+        if let Some(e) = receiver
+            .try_iter()
+            .find_map(|json| socket.send(Message::Text(json)).err())
+        {
+            break Err(Box::new(e));
+        }
+    };
+
+    // drop the ghosts of drags the client left unfinished
+    let mut room = room.lock().unwrap();
+    room.leave(client_id);
+    replicas.into_iter().for_each(|replica| {
+        room.broadcast(
+            client_id,
+            &CollabMessage::Drag {
+                replica,
+                drag: None,
+            },
+        )
+    });
+
+    result
+}
+
+fn main() -> std::process::ExitCode {
+    let mut args = std::env::args().skip(1);
+    let mut host = String::from("127.0.0.1");
+    let mut port = 9001;
+    let mut allowed_origins = Vec::new();
+
+    while let Some(arg) = args.next() {
+        if arg == "--help" {
+            println!("{USAGE}");
+            return std::process::ExitCode::SUCCESS;
+        }
+        match (arg.as_str(), args.next()) {
+            ("--port", Some(value)) if value.parse::<u16>().is_ok() => {
+                port = value.parse().unwrap()
+            }
+            ("--host", Some(value)) => host = value,
+            ("--allow-origin", Some(value)) => allowed_origins.push(value),
+            _ => {
+                eprintln!("error: unexpected argument \"{arg}\"\n\n{USAGE}");
+                return std::process::ExitCode::from(2);
+            }
+        }
+    }
+
+    let listener = match TcpListener::bind((host.as_str(), port)) {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!("error: {e}");
+            return std::process::ExitCode::from(2);
+        }
+    };
+    println!("listening on ws://{host}:{port}");
+
+    let room = Arc::new(Mutex::new(Room::default()));
+    let allowed_origins: Arc<[String]> = allowed_origins.into();
+    for stream in listener.incoming().flatten() {
+        let room = room.clone();
+        let allowed_origins = allowed_origins.clone();
+        std::thread::spawn(move || {
+            if let Err(e) = serve(room, stream, &allowed_origins) {
+                eprintln!("error: {e}");
+            }
+        });
+    }
+
+    std::process::ExitCode::SUCCESS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_seed(sync: &str) -> bool {
+        match serde_json::from_str(sync).unwrap() {
+            CollabMessage::Sync { seed, .. } => seed,
+            message => panic!("expected a sync, got {message:?}"),
+        }
+    }
+
+    fn get_create_op() -> Op {
+        let block = CalendarBlock {
+            id: Uuid::new_v4(),
+            start_minute: 60,
+            end_minute: 120,
+            block_type: CalendarBlockType::Busy,
+            subtree_depth: 0,
+            label: String::from("Shared"),
+            calendar_id: DEFAULT_CALENDAR_ID.to_string(),
+            metadata: BlockMetadata::default(),
+        };
+        CollabDoc::new(Uuid::new_v4()).local(block.id, OpKind::Create { block })
+    }
+
+    /// Serves one connection on a free local port and opens a socket to it from `origin`.
+    fn connect(
+        origin: Option<&str>,
+        allowed_origins: &[&str],
+    ) -> Result<WebSocket<TcpStream>, Box<WsError>> {
+        use tungstenite::client::IntoClientRequest;
+
+        let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let address = listener.local_addr().unwrap();
+        let allowed_origins: Vec<String> = allowed_origins.iter().map(|o| o.to_string()).collect();
+        std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let _ = serve(Arc::default(), stream, &allowed_origins);
+        });
+
+        let mut request = format!("ws://{address}").into_client_request().unwrap();
+        if let Some(origin) = origin {
+            request
+                .headers_mut()
+                .insert("Origin", origin.parse().unwrap());
+        }
+        let stream = TcpStream::connect(address).unwrap();
+        tungstenite::client(request, stream)
+            .map(|(socket, _)| socket)
+            .map_err(|e| match e {
+                tungstenite::HandshakeError::Failure(e) => Box::new(e),
+                tungstenite::HandshakeError::Interrupted(_) => Box::new(WsError::ConnectionClosed),
+            })
+    }
+
+    #[test]
+    fn serve_rejects_pages_from_other_origins() {
+        match connect(Some("http://evil.example"), &["http://localhost:8080"]).map_err(|e| *e) {
+            Err(WsError::Http(response)) => assert_eq!(response.status(), StatusCode::FORBIDDEN),
+            other => panic!("expected a 403, got {other:?}"),
+        }
+        match connect(Some("http://localhost:8080"), &[]).map_err(|e| *e) {
+            Err(WsError::Http(response)) => assert_eq!(response.status(), StatusCode::FORBIDDEN),
+            other => panic!("expected a 403, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn serve_accepts_allowed_pages_and_non_browser_clients() {
+        [
+            connect(
+                Some("http://localhost:8080"),
+                &["http://127.0.0.1:8080", "http://localhost:8080"],
+            ),
+            connect(None, &[]),
+        ]
+        .into_iter()
+        .for_each(|socket| {
+            let message = socket.unwrap().read().unwrap();
+            assert!(is_seed(message.to_text().unwrap()));
+        });
+    }
+
+    #[test]
+    fn only_the_first_client_of_an_empty_room_seeds() {
+        let mut room = Room::default();
+
+        let (_, _first_receiver, first_sync) = room.join();
+        let (_, _second_receiver, second_sync) = room.join();
+
+        assert!(is_seed(&first_sync));
+        assert!(!is_seed(&second_sync));
+    }
+
+    #[test]
+    fn clients_joining_a_seeded_room_get_its_ops() {
+        let mut room = Room::default();
+        let (seeder, _seeder_receiver, _) = room.join();
+        let (_, waiting_receiver, _) = room.join();
+        let op = get_create_op();
+
+        room.receive(seeder, CollabMessage::Op { op: op.clone() });
+        room.leave(seeder);
+        let (_, _late_receiver, late_sync) = room.join();
+
+        let relayed: CollabMessage =
+            serde_json::from_str(&waiting_receiver.recv().unwrap()).unwrap();
+        assert_eq!(relayed, CollabMessage::Op { op: op.clone() });
+        let late_sync: CollabMessage = serde_json::from_str(&late_sync).unwrap();
+        assert_eq!(
+            late_sync,
+            CollabMessage::Sync {
+                ops: vec![op],
+                seed: false
+            }
+        );
+    }
+
+    #[test]
+    fn the_next_client_seeds_if_the_seeder_leaves_without_sharing() {
+        let mut room = Room::default();
+        let (seeder, _seeder_receiver, _) = room.join();
+        let (_, _waiting_receiver, _) = room.join();
+
+        room.leave(seeder);
+        let (_, _next_receiver, next_sync) = room.join();
+
+        assert!(is_seed(&next_sync));
+    }
+}
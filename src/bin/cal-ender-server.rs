@@ -0,0 +1,829 @@
+use cal_ender::prelude::*;
+use std::io::Read;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+/// Request bodies are read whole, so larger ones are refused rather than buffered.
+const MAX_BODY: u64 = 1024 * 1024;
+
+const USAGE: &str = "\
+Usage: cal-ender-server [--host <ADDR>] [--port <PORT>] [--store <FILE>]
+                        [--allow-origin <ORIGIN>]
+
+Serves the layout engine over HTTP. Blocks are kept in memory, or in FILE.
+There is no authentication, so it listens on 127.0.0.1 unless --host says
+otherwise, and rejects browser requests from pages other than ORIGIN.
+
+Endpoints:
+  GET    /days/<DAY>/blocks         List the day's blocks
+  PUT    /days/<DAY>/blocks         Replace the day's blocks with a JSON array of blocks
+  POST   /days/<DAY>/blocks         Add a block
+  GET    /days/<DAY>/layout         Stacked layout, see the query parameters below
+  GET    /days/<DAY>/free-slots     Gaps between blocks that aren't free
+  GET    /days/<DAY>/conflicts      Overlapping busy blocks
+  GET    /blocks/<ID>               A block, with its version as ETag
+  PUT    /blocks/<ID>               Replace a block, If-Match must hold its version
+  DELETE /blocks/<ID>               Remove a block, If-Match must hold its version
+
+Query parameters:
+  strategy=<subtree-depth|stack-position>, start_hour, end_hour,
+  pixels_per_hour, column_width, min_duration
+";
+
+/// An error answered as `{ "error": message }` with the given status.
+#[derive(Debug)]
+struct ApiError(u16, String);
+
+impl From<StoreError> for ApiError {
+    fn from(e: StoreError) -> Self {
+        let status = match e {
+            StoreError::NotFound(_) => 404,
+            StoreError::AlreadyExists(_) => 409,
+            StoreError::DuplicateId(_) => 400,
+            StoreError::VersionConflict { .. } => 412,
+            StoreError::Backend(_) => 500,
+        };
+        ApiError(status, e.to_string())
+    }
+}
+
+impl From<ValidationError> for ApiError {
+    fn from(e: ValidationError) -> Self {
+        ApiError(400, e.to_string())
+    }
+}
+
+impl From<serde_json::Error> for ApiError {
+    fn from(e: serde_json::Error) -> Self {
+        ApiError(400, e.to_string())
+    }
+}
+
+/// A block's place in the tree alongside its rectangle in pixels.
+#[derive(Serialize)]
+struct LayoutEntry {
+    #[serde(flatten)]
+    flattened_block: FlattenedCalendarBlock,
+    top: f64,
+    height: f64,
+    left: f64,
+    width: f64,
+}
+
+struct ApiResponse {
+    status: u16,
+    body: String,
+    version: Option<u64>,
+}
+
+impl ApiResponse {
+    fn json(status: u16, value: &impl Serialize) -> Result<Self, ApiError> {
+        Ok(ApiResponse {
+            status,
+            body: serde_json::to_string(value)?,
+            version: None,
+        })
+    }
+
+    fn record(status: u16, record: &StoredBlock) -> Result<Self, ApiError> {
+        Ok(ApiResponse {
+            version: Some(record.version),
+            ..ApiResponse::json(status, record)?
+        })
+    }
+}
+
+struct Query(HashMap<String, String>);
+
+impl Query {
+    fn parse(query: &str) -> Self {
+        Query(
+            query
+                .split('&')
+                .filter_map(|pair| pair.split_once('='))
+                .map(|(key, value)| (decode_query_component(key), decode_query_component(value)))
+                .collect(),
+        )
+    }
+
+    fn get<T: std::str::FromStr>(&self, key: &str, default: T) -> Result<T, ApiError> {
+        match self.0.get(key) {
+            Some(value) => value
+                .parse()
+                .map_err(|_| ApiError(400, format!("invalid {key} \"{value}\""))),
+            None => Ok(default),
+        }
+    }
+
+    fn get_config(&self) -> Result<CalendarConfig, ApiError> {
+        let default = CalendarConfig::default();
+        let config = CalendarConfig {
+            start_hour: self.get("start_hour", default.start_hour)?,
+            end_hour: self.get("end_hour", default.end_hour)?,
+            pixels_per_hour: self.get("pixels_per_hour", default.pixels_per_hour)?,
+            column_width: self.get("column_width", default.column_width)?,
+            ..default
+        };
+
+        match config.start_hour < config.end_hour && config.end_hour <= 24 {
+            true => Ok(config),
+            false => Err(ApiError(
+                400,
+                String::from("the visible range must lie within 0..=24 hours"),
+            )),
+        }
+    }
+
+    fn get_stacking_strategy(&self) -> Result<StackingStrategy, ApiError> {
+        match self.0.get("strategy").map(String::as_str) {
+            None | Some("subtree-depth") => Ok(StackingStrategy::SubtreeDepth),
+            Some("stack-position") => Ok(StackingStrategy::StackPosition),
+            Some(other) => Err(ApiError(400, format!("unknown strategy \"{other}\""))),
+        }
+    }
+}
+
+/// Decodes the `+` and `%XX` escapes of a query string key or value.
+fn decode_query_component(component: &str) -> String {
+    let bytes = component.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut idx = 0;
+    while idx < bytes.len() {
+        let escaped = component
+            .get(idx + 1..idx + 3)
+            .filter(|hex| hex.chars().all(|c| c.is_ascii_hexdigit()))
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[idx], escaped) {
+            (b'+', _) => decoded.push(b' '),
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                idx += 2;
+            }
+            (byte, _) => decoded.push(byte),
+        }
+        idx += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Browsers send an Origin with requests made by pages, which are only let
+/// through from the `--allow-origin` page, so other sites can't change the calendar.
+fn check_origin(origin: Option<&str>, allowed_origin: Option<&str>) -> Result<(), ApiError> {
+    match (origin, allowed_origin) {
+        (None, _) => Ok(()),
+        (Some(origin), Some(allowed_origin)) if origin == allowed_origin => Ok(()),
+        (Some(origin), _) => Err(ApiError(403, format!("origin {origin} is not allowed"))),
+    }
+}
+
+fn get_day_blocks(store: &dyn CalendarStore, day: &Day) -> Result<Vec<CalendarBlock>, ApiError> {
+    Ok(store
+        .list(day)?
+        .into_iter()
+        .map(|record| record.block)
+        .collect())
+}
+
+/// The version a write expects the block to be at, from an `If-Match: "<version>"` header.
+fn get_expected_version(if_match: Option<&str>) -> Result<u64, ApiError> {
+    let if_match = if_match
+        .ok_or_else(|| ApiError(428, String::from("If-Match must hold the block's version")))?;
+    if_match
+        .trim_matches('"')
+        .parse()
+        .map_err(|_| ApiError(400, format!("invalid If-Match \"{if_match}\"")))
+}
+
+fn parse_day(day: &str) -> Result<Day, ApiError> {
+    let day = Day(day.to_string());
+    day.validate()?;
+    Ok(day)
+}
+
+fn parse_id(id: &str) -> Result<Uuid, ApiError> {
+    Uuid::parse_str(id).map_err(|_| ApiError(400, format!("invalid block id \"{id}\"")))
+}
+
+fn get_layout(blocks: &[CalendarBlock], query: &Query) -> Result<Vec<LayoutEntry>, ApiError> {
+    let config = query.get_config()?;
+    // the store keeps ids unique, so a duplicate means it is corrupt
+    let flattened_blocks = CalendarBlockTree::from_blocks(blocks)
+        .map_err(|e| ApiError(500, e.to_string()))?
+        .traverse();
+    let mut layouts: HashMap<Uuid, BlockLayout> =
+        layout_blocks(&flattened_blocks, query.get_stacking_strategy()?, &config)
+            .into_iter()
+            .map(|layout| (layout.block.id, layout))
+            .collect();
+
+    // layout_blocks drops hidden blocks but keeps the traversal order
+    Ok(flattened_blocks
+        .into_iter()
+        .filter_map(|flattened_block| {
+            let layout = layouts.remove(&flattened_block.block.id)?;
+            Some(LayoutEntry {
+                flattened_block,
+                top: layout.top,
+                height: layout.height,
+                left: layout.left,
+                width: layout.width,
+            })
+        })
+        .collect())
+}
+
+fn handle(
+    store: &mut dyn CalendarStore,
+    method: &Method,
+    url: &str,
+    if_match: Option<&str>,
+    body: &str,
+) -> Result<ApiResponse, ApiError> {
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+    let query = Query::parse(query);
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+
+    match (method, segments.as_slice()) {
+        (Method::Get, ["days", day, "blocks"]) => {
+            ApiResponse::json(200, &store.list(&Day(day.to_string()))?)
+        }
+        (Method::Put, ["days", day, "blocks"]) => {
+            let day = parse_day(day)?;
+            let blocks: Vec<CalendarBlock> = serde_json::from_str(body)?;
+            blocks.iter().try_for_each(CalendarBlock::validate)?;
+            ApiResponse::json(200, &store.replace_day(&day, blocks)?)
+        }
+        (Method::Post, ["days", day, "blocks"]) => {
+            let day = parse_day(day)?;
+            let block: CalendarBlock = serde_json::from_str(body)?;
+            block.validate()?;
+            ApiResponse::record(201, &store.insert(&day, block)?)
+        }
+        (Method::Get, ["days", day, "layout"]) => {
+            let blocks = get_day_blocks(store, &Day(day.to_string()))?;
+            ApiResponse::json(200, &get_layout(&blocks, &query)?)
+        }
+        (Method::Get, ["days", day, "free-slots"]) => {
+            let blocks = get_day_blocks(store, &Day(day.to_string()))?;
+            let config = query.get_config()?;
+            let free_slots = find_free_slots(
+                &blocks,
+                config.visible_start_minute(),
+                config.visible_end_minute(),
+                query.get("min_duration", 15)?,
+            );
+            ApiResponse::json(200, &free_slots)
+        }
+        (Method::Get, ["days", day, "conflicts"]) => {
+            let blocks = get_day_blocks(store, &Day(day.to_string()))?;
+            ApiResponse::json(200, &find_conflicts(&blocks))
+        }
+        (Method::Get, ["blocks", id]) => ApiResponse::record(200, &store.get(parse_id(id)?)?),
+        (Method::Put, ["blocks", id]) => {
+            let block = CalendarBlock {
+                id: parse_id(id)?,
+                ..serde_json::from_str(body)?
+            };
+            block.validate()?;
+            ApiResponse::record(200, &store.update(block, get_expected_version(if_match)?)?)
+        }
+        (Method::Delete, ["blocks", id]) => {
+            store.delete(parse_id(id)?, get_expected_version(if_match)?)?;
+            Ok(ApiResponse {
+                status: 204,
+                body: String::new(),
+                version: None,
+            })
+        }
+        _ => Err(ApiError(404, format!("no route for {method} {path}"))),
+    }
+}
+
+fn header(field: &str, value: &str) -> Header {
+    Header::from_bytes(field.as_bytes(), value.as_bytes()).expect("header is ascii")
+}
+
+fn get_header(request: &Request, field: &'static str) -> Option<String> {
+    request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv(field))
+        .map(|header| header.value.as_str().to_string())
+}
+
+fn respond(
+    store: &mut dyn CalendarStore,
+    mut request: Request,
+    allowed_origin: Option<&str>,
+) -> std::io::Result<()> {
+    let origin = get_header(&request, "Origin");
+    let is_preflight = *request.method() == Method::Options;
+    let mut body = String::new();
+    let api_response = match check_origin(origin.as_deref(), allowed_origin) {
+        Err(e) => Err(e),
+        Ok(()) if is_preflight => Ok(ApiResponse {
+            status: 204,
+            body: String::new(),
+            version: None,
+        }),
+        Ok(()) => match request
+            .as_reader()
+            .take(MAX_BODY + 1)
+            .read_to_string(&mut body)
+        {
+            Ok(length) if length as u64 > MAX_BODY => Err(ApiError(
+                413,
+                format!("the body must not exceed {MAX_BODY} bytes"),
+            )),
+            Ok(_) => handle(
+                store,
+                request.method(),
+                request.url(),
+                get_header(&request, "If-Match").as_deref(),
+                &body,
+            ),
+            Err(e) => Err(ApiError(400, e.to_string())),
+        },
+    };
+
+    let api_response = api_response.unwrap_or_else(|ApiError(status, message)| ApiResponse {
+        status,
+        body: serde_json::json!({ "error": message }).to_string(),
+        version: None,
+    });
+
+    let mut response = Response::from_string(api_response.body)
+        .with_status_code(api_response.status)
+        .with_header(header("Content-Type", "application/json"));
+    if let (Some(origin), Some(allowed_origin)) = (&origin, allowed_origin) {
+        if origin == allowed_origin {
+            response = response
+                .with_header(header("Access-Control-Allow-Origin", allowed_origin))
+                .with_header(header(
+                    "Access-Control-Allow-Methods",
+                    "GET, POST, PUT, DELETE",
+                ))
+                .with_header(header(
+                    "Access-Control-Allow-Headers",
+                    "Content-Type, If-Match",
+                ))
+                .with_header(header("Access-Control-Expose-Headers", "ETag"))
+                .with_header(header("Vary", "Origin"));
+        }
+    }
+    if let Some(version) = api_response.version {
+        response = response.with_header(header("ETag", &format!("\"{version}\"")));
+    }
+    request.respond(response)
+}
+
+fn main() -> std::process::ExitCode {
+    let mut args = std::env::args().skip(1);
+    let mut host = String::from("127.0.0.1");
+    let mut port = 3000;
+    let mut store_path = None;
+    let mut allowed_origin = None;
+
+    while let Some(arg) = args.next() {
+        if arg == "--help" {
+            println!("{USAGE}");
+            return std::process::ExitCode::SUCCESS;
+        }
+        match (arg.as_str(), args.next()) {
+            ("--port", Some(value)) if value.parse::<u16>().is_ok() => {
+                port = value.parse().unwrap()
+            }
+            ("--host", Some(value)) => host = value,
+            ("--store", Some(value)) => store_path = Some(value),
+            ("--allow-origin", Some(value)) => allowed_origin = Some(value),
+            _ => {
+                eprintln!("error: unexpected argument \"{arg}\"\n\n{USAGE}");
+                return std::process::ExitCode::from(2);
+            }
+        }
+    }
+
+    let mut store: Box<dyn CalendarStore> = match store_path {
+        Some(path) => match FileStore::open(&path) {
+            Ok(store) => Box::new(store),
+            Err(e) => {
+                eprintln!("error: {e}");
+                return std::process::ExitCode::from(2);
+            }
+        },
+        None => Box::new(InMemoryStore::new()),
+    };
+
+    let server = match Server::http((host.as_str(), port)) {
+        Ok(server) => server,
+        Err(e) => {
+            eprintln!("error: {e}");
+            return std::process::ExitCode::from(2);
+        }
+    };
+    println!("listening on http://{host}:{port}");
+
+    // requests are handled one at a time, so the store needs no locking
+    for request in server.incoming_requests() {
+        if let Err(e) = respond(store.as_mut(), request, allowed_origin.as_deref()) {
+            eprintln!("error: {e}");
+        }
+    }
+
+    std::process::ExitCode::SUCCESS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    const DAY: &str = "/days/2024-05-01";
+
+    /// Answers a request the way `respond` does, as status, JSON body and version.
+    fn request(
+        store: &mut InMemoryStore,
+        method: Method,
+        url: &str,
+        if_match: Option<&str>,
+        body: &str,
+    ) -> (u16, Value, Option<u64>) {
+        match handle(store, &method, url, if_match, body) {
+            Ok(api_response) => (
+                api_response.status,
+                match api_response.body.is_empty() {
+                    true => Value::Null,
+                    false => serde_json::from_str(&api_response.body).unwrap(),
+                },
+                api_response.version,
+            ),
+            Err(ApiError(status, message)) => (status, json!({ "error": message }), None),
+        }
+    }
+
+    fn get_block(label: &str, start_minute: u32, end_minute: u32, block_type: &str) -> Value {
+        json!({
+            "id": Uuid::new_v4(),
+            "label": label,
+            "start_minute": start_minute,
+            "end_minute": end_minute,
+            "block_type": block_type,
+        })
+    }
+
+    /// A store holding a busy standup inside a review, and an available lunch.
+    fn get_store() -> (InMemoryStore, Vec<Value>) {
+        let blocks = vec![
+            get_block("Review", 540, 660, "Busy"),
+            get_block("Standup", 540, 555, "Busy"),
+            get_block("Lunch", 720, 780, "Available"),
+        ];
+        let mut store = InMemoryStore::new();
+        let (status, _, _) = request(
+            &mut store,
+            Method::Put,
+            &format!("{DAY}/blocks"),
+            None,
+            &Value::from(blocks.clone()).to_string(),
+        );
+        assert_eq!(status, 200);
+        (store, blocks)
+    }
+
+    fn get_labels(store: &mut InMemoryStore) -> Vec<String> {
+        let (_, body, _) = request(store, Method::Get, &format!("{DAY}/blocks"), None, "");
+        body.as_array()
+            .unwrap()
+            .iter()
+            .map(|record| record["block"]["label"].as_str().unwrap().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn get_day_blocks_lists_the_days_records() {
+        let (mut store, _) = get_store();
+
+        let (status, body, _) =
+            request(&mut store, Method::Get, &format!("{DAY}/blocks"), None, "");
+
+        assert_eq!(status, 200);
+        assert_eq!(body[0]["version"], 1);
+        assert_eq!(get_labels(&mut store), vec!["Review", "Standup", "Lunch"]);
+        let (_, body, _) = request(&mut store, Method::Get, "/days/2024-05-02/blocks", None, "");
+        assert_eq!(body, json!([]));
+    }
+
+    #[test]
+    fn put_day_blocks_replaces_the_day() {
+        let (mut store, blocks) = get_store();
+        let body = json!([blocks[0], get_block("Retro", 960, 1020, "Busy")]).to_string();
+
+        let (status, body, _) = request(
+            &mut store,
+            Method::Put,
+            &format!("{DAY}/blocks"),
+            None,
+            &body,
+        );
+
+        assert_eq!(status, 200);
+        assert_eq!(body[0]["version"], 2);
+        assert_eq!(body[1]["version"], 1);
+        assert_eq!(get_labels(&mut store), vec!["Review", "Retro"]);
+    }
+
+    #[test]
+    fn put_day_blocks_changes_nothing_on_failure() {
+        let (mut store, blocks) = get_store();
+        let url = format!("{DAY}/blocks");
+
+        let duplicates = json!([blocks[0], blocks[0]]).to_string();
+        let (status, body, _) = request(&mut store, Method::Put, &url, None, &duplicates);
+        assert_eq!(status, 400);
+        assert!(body["error"].as_str().unwrap().contains("more than once"));
+
+        let invalid = json!([get_block("Retro", 960, 1020, "Busy"), { "label": "No times" }]);
+        let (status, _, _) = request(&mut store, Method::Put, &url, None, &invalid.to_string());
+        assert_eq!(status, 400);
+
+        let other_day = json!([blocks[2]]).to_string();
+        let (status, _, _) = request(
+            &mut store,
+            Method::Put,
+            "/days/2024-05-02/blocks",
+            None,
+            &other_day,
+        );
+        assert_eq!(status, 409);
+
+        assert_eq!(get_labels(&mut store), vec!["Review", "Standup", "Lunch"]);
+    }
+
+    #[test]
+    fn put_day_blocks_rejects_invalid_blocks_and_days() {
+        let (mut store, _) = get_store();
+        let url = format!("{DAY}/blocks");
+
+        let backwards = json!([get_block("Retro", 1020, 960, "Busy")]).to_string();
+        let (status, body, _) = request(&mut store, Method::Put, &url, None, &backwards);
+        assert_eq!(status, 400);
+        assert!(body["error"]
+            .as_str()
+            .unwrap()
+            .contains("ends before it starts"));
+
+        let too_late = json!([get_block("Retro", 960, 2000, "Busy")]).to_string();
+        assert_eq!(
+            request(&mut store, Method::Put, &url, None, &too_late).0,
+            400
+        );
+
+        let retro = json!([get_block("Retro", 960, 1020, "Busy")]).to_string();
+        let (status, body, _) = request(
+            &mut store,
+            Method::Put,
+            "/days/2024-02-30/blocks",
+            None,
+            &retro,
+        );
+        assert_eq!(status, 400);
+        assert!(body["error"].as_str().unwrap().contains("invalid day"));
+
+        assert_eq!(get_labels(&mut store), vec!["Review", "Standup", "Lunch"]);
+    }
+
+    #[test]
+    fn post_day_blocks_rejects_invalid_blocks_and_days() {
+        let (mut store, _) = get_store();
+        let url = format!("{DAY}/blocks");
+
+        let backwards = get_block("Retro", 600, 500, "Busy").to_string();
+        assert_eq!(
+            request(&mut store, Method::Post, &url, None, &backwards).0,
+            400
+        );
+        let too_late = get_block("Retro", 960, 1441, "Busy").to_string();
+        assert_eq!(
+            request(&mut store, Method::Post, &url, None, &too_late).0,
+            400
+        );
+        let retro = get_block("Retro", 960, 1020, "Busy").to_string();
+        assert_eq!(
+            request(
+                &mut store,
+                Method::Post,
+                "/days/tomorrow/blocks",
+                None,
+                &retro
+            )
+            .0,
+            400
+        );
+
+        assert_eq!(get_labels(&mut store), vec!["Review", "Standup", "Lunch"]);
+    }
+
+    #[test]
+    fn post_day_blocks_adds_a_block_once() {
+        let (mut store, _) = get_store();
+        let block = get_block("Retro", 960, 1020, "Busy").to_string();
+        let url = format!("{DAY}/blocks");
+
+        let (status, body, version) = request(&mut store, Method::Post, &url, None, &block);
+        assert_eq!((status, version), (201, Some(1)));
+        assert_eq!(body["block"]["label"], "Retro");
+
+        let (status, _, _) = request(&mut store, Method::Post, &url, None, &block);
+        assert_eq!(status, 409);
+    }
+
+    #[test]
+    fn get_layout_positions_blocks_with_the_query() {
+        let (mut store, _) = get_store();
+
+        let url = format!(
+            "{DAY}/layout?strategy=stack-position&start_hour=9&end_hour=13&column_width=200"
+        );
+        let (status, body, _) = request(&mut store, Method::Get, &url, None, "");
+
+        assert_eq!(status, 200);
+        let entries = body.as_array().unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0]["block"]["label"], "Review");
+        assert_eq!(entries[0]["stack_position"], 1);
+        assert_eq!(entries[0]["top"], 0.);
+        assert_eq!(entries[0]["width"], 200.);
+        assert_eq!(entries[1]["block"]["label"], "Lunch");
+        assert_eq!(entries[2]["parent_id"], entries[0]["block"]["id"]);
+    }
+
+    #[test]
+    fn get_layout_rejects_invalid_queries() {
+        let (mut store, _) = get_store();
+
+        [
+            "strategy=tetris",
+            "start_hour=12&end_hour=9",
+            "end_hour=25",
+            "column_width=wide",
+        ]
+        .iter()
+        .for_each(|query| {
+            let url = format!("{DAY}/layout?{query}");
+            let (status, _, _) = request(&mut store, Method::Get, &url, None, "");
+            assert_eq!(status, 400, "{query}");
+        });
+    }
+
+    #[test]
+    fn get_free_slots_and_conflicts() {
+        let (mut store, _) = get_store();
+
+        let url = format!("{DAY}/free-slots?start_hour=9&end_hour=14&min_duration=30");
+        let (status, body, _) = request(&mut store, Method::Get, &url, None, "");
+        assert_eq!(status, 200);
+        assert_eq!(
+            body,
+            json!([
+                { "start_minute": 660, "end_minute": 840 },
+            ])
+        );
+
+        let (status, body, _) = request(
+            &mut store,
+            Method::Get,
+            &format!("{DAY}/conflicts"),
+            None,
+            "",
+        );
+        assert_eq!(status, 200);
+        assert_eq!(body.as_array().unwrap().len(), 1);
+        assert_eq!(body[0]["overlap_minutes"], 15);
+    }
+
+    #[test]
+    fn get_block_returns_the_version() {
+        let (mut store, blocks) = get_store();
+
+        let url = format!("/blocks/{}", blocks[1]["id"].as_str().unwrap());
+        let (status, body, version) = request(&mut store, Method::Get, &url, None, "");
+        assert_eq!((status, version), (200, Some(1)));
+        assert_eq!(body["block"]["label"], "Standup");
+
+        let url = format!("/blocks/{}", Uuid::new_v4());
+        assert_eq!(request(&mut store, Method::Get, &url, None, "").0, 404);
+        assert_eq!(
+            request(&mut store, Method::Get, "/blocks/42", None, "").0,
+            400
+        );
+    }
+
+    #[test]
+    fn put_block_needs_the_current_version() {
+        let (mut store, blocks) = get_store();
+        let url = format!("/blocks/{}", blocks[1]["id"].as_str().unwrap());
+        let moved = get_block("Standup", 570, 585, "Busy").to_string();
+
+        assert_eq!(request(&mut store, Method::Put, &url, None, &moved).0, 428);
+        assert_eq!(
+            request(&mut store, Method::Put, &url, Some("\"v1\""), &moved).0,
+            400
+        );
+        let (status, body, version) = request(&mut store, Method::Put, &url, Some("\"1\""), &moved);
+        assert_eq!((status, version), (200, Some(2)));
+        assert_eq!(body["block"]["id"], blocks[1]["id"]);
+        assert_eq!(body["block"]["start_minute"], 570);
+
+        assert_eq!(
+            request(&mut store, Method::Put, &url, Some("\"1\""), &moved).0,
+            412
+        );
+    }
+
+    #[test]
+    fn put_block_rejects_invalid_blocks() {
+        let (mut store, blocks) = get_store();
+        let url = format!("/blocks/{}", blocks[1]["id"].as_str().unwrap());
+
+        let backwards = get_block("Standup", 585, 570, "Busy").to_string();
+        let (status, body, _) = request(&mut store, Method::Put, &url, Some("\"1\""), &backwards);
+        assert_eq!(status, 400);
+        assert!(body["error"]
+            .as_str()
+            .unwrap()
+            .contains("ends before it starts"));
+        let too_late = get_block("Standup", 1430, 1445, "Busy").to_string();
+        assert_eq!(
+            request(&mut store, Method::Put, &url, Some("\"1\""), &too_late).0,
+            400
+        );
+
+        let (_, body, version) = request(&mut store, Method::Get, &url, None, "");
+        assert_eq!(version, Some(1));
+        assert_eq!(body["block"]["start_minute"], 540);
+    }
+
+    #[test]
+    fn delete_block_needs_the_current_version() {
+        let (mut store, blocks) = get_store();
+        let url = format!("/blocks/{}", blocks[2]["id"].as_str().unwrap());
+
+        assert_eq!(request(&mut store, Method::Delete, &url, None, "").0, 428);
+        assert_eq!(
+            request(&mut store, Method::Delete, &url, Some("\"2\""), "").0,
+            412
+        );
+        assert_eq!(
+            request(&mut store, Method::Delete, &url, Some("\"1\""), ""),
+            (204, Value::Null, None)
+        );
+        assert_eq!(request(&mut store, Method::Get, &url, None, "").0, 404);
+    }
+
+    #[test]
+    fn unknown_routes_are_not_found() {
+        let (mut store, _) = get_store();
+
+        assert_eq!(request(&mut store, Method::Get, "/days", None, "").0, 404);
+        assert_eq!(
+            request(
+                &mut store,
+                Method::Delete,
+                &format!("{DAY}/blocks"),
+                None,
+                ""
+            )
+            .0,
+            404
+        );
+    }
+
+    #[test]
+    fn decode_query_component_decodes_escapes() {
+        assert_eq!(decode_query_component("stack-position"), "stack-position");
+        assert_eq!(decode_query_component("a+b%20c%2Bd"), "a b c+d");
+        assert_eq!(decode_query_component("100%"), "100%");
+        assert_eq!(decode_query_component("%zz%4"), "%zz%4");
+        assert_eq!(decode_query_component("caf%C3%A9"), "café");
+    }
+
+    #[test]
+    fn check_origin_lets_through_the_allowed_page_only() {
+        let allowed = Some("http://localhost:8080");
+
+        assert!(check_origin(None, None).is_ok());
+        assert!(check_origin(None, allowed).is_ok());
+        assert!(check_origin(Some("http://localhost:8080"), allowed).is_ok());
+        assert_eq!(
+            check_origin(Some("http://evil.example"), allowed)
+                .unwrap_err()
+                .0,
+            403
+        );
+        assert_eq!(
+            check_origin(Some("http://localhost:8080"), None)
+                .unwrap_err()
+                .0,
+            403
+        );
+    }
+}
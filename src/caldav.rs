@@ -0,0 +1,503 @@
+use crate::prelude::*;
+use base64::Engine;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+
+const CALENDAR_QUERY: &str = r#"<?xml version="1.0" encoding="utf-8" ?>
+<C:calendar-query xmlns:D="DAV:" xmlns:C="urn:ietf:params:xml:ns:caldav">
+  <D:prop>
+    <D:getetag/>
+    <C:calendar-data/>
+  </D:prop>
+  <C:filter>
+    <C:comp-filter name="VCALENDAR">
+      <C:comp-filter name="VEVENT">
+        <C:time-range start="{start}" end="{end}"/>
+      </C:comp-filter>
+    </C:comp-filter>
+  </C:filter>
+</C:calendar-query>"#;
+
+#[derive(Debug, Error)]
+pub enum CalDavError {
+    #[error("request to {0} failed: {1}")]
+    Transport(String, String),
+    #[error("{0} answered with status {1}")]
+    Status(String, u16),
+    #[error("{0} changed on the server since it was fetched")]
+    Conflict(String),
+    #[error("invalid multistatus response: {0}")]
+    InvalidResponse(String),
+    #[error("invalid date \"{0}\", expected YYYY-MM-DD")]
+    InvalidDay(String),
+    #[error(transparent)]
+    Ics(#[from] IcsError),
+    #[error(transparent)]
+    Store(#[from] StoreError),
+}
+
+/// An event as fetched from the server. `etag` identifies the version of the
+/// resource at `href`, pushes fail with `Conflict` once it no longer matches.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RemoteEvent {
+    pub href: String,
+    pub etag: Option<String>,
+    pub calendar_data: String,
+    pub event: IcsEvent,
+}
+
+/// The events as they were at the last pull, by block id. Local moves are
+/// found by comparing against these, and pushed with their ETags, so kept
+/// between syncs it lets the server reject writes over changes made since.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SyncState {
+    events: HashMap<Uuid, RemoteEvent>,
+}
+
+impl SyncState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, id: Uuid) -> Option<&RemoteEvent> {
+        self.events.get(&id)
+    }
+
+    pub fn record(&mut self, remote_event: RemoteEvent) {
+        self.events
+            .insert(remote_event.event.block.id, remote_event);
+    }
+}
+
+/// A CalDAV calendar collection, such as `http://localhost:5232/user/calendar/`.
+pub struct CalDavClient {
+    calendar_url: String,
+    authorization: Option<String>,
+    agent: ureq::Agent,
+}
+
+#[derive(Default)]
+struct MultistatusResponse {
+    href: String,
+    etag: Option<String>,
+    calendar_data: String,
+}
+
+impl CalDavClient {
+    pub fn new(calendar_url: &str) -> Self {
+        let calendar_url = match calendar_url.ends_with('/') {
+            true => calendar_url.to_string(),
+            false => format!("{calendar_url}/"),
+        };
+
+        Self {
+            calendar_url,
+            authorization: None,
+            agent: ureq::Agent::new(),
+        }
+    }
+
+    pub fn with_basic_auth(self, username: &str, password: &str) -> Self {
+        let credentials =
+            base64::engine::general_purpose::STANDARD.encode(format!("{username}:{password}"));
+        Self {
+            authorization: Some(format!("Basic {credentials}")),
+            ..self
+        }
+    }
+
+    fn request(&self, method: &str, url: &str) -> ureq::Request {
+        let request = self.agent.request(method, url);
+        match &self.authorization {
+            Some(authorization) => request.set("Authorization", authorization),
+            None => request,
+        }
+    }
+
+    /// Events starting on the days from `start` up to, but excluding, `end`.
+    /// Recurring events are not expanded, only their first occurrence is returned.
+    pub fn fetch_events(&self, start: &Day, end: &Day) -> Result<Vec<RemoteEvent>, CalDavError> {
+        // times are wall-clock, so widen the UTC range by a day on both sides
+        // and filter by the events' own dates afterwards
+        let invalid_day = |day: &Day| CalDavError::InvalidDay(day.0.clone());
+        let query_start = shift_day(&start.0, -1).ok_or_else(|| invalid_day(start))?;
+        let query_end = shift_day(&end.0, 1).ok_or_else(|| invalid_day(end))?;
+        let body = CALENDAR_QUERY
+            .replace("{start}", &format!("{query_start}T000000Z"))
+            .replace("{end}", &format!("{query_end}T000000Z"));
+
+        let response = self
+            .request("REPORT", &self.calendar_url)
+            .set("Depth", "1")
+            .set("Content-Type", "application/xml; charset=utf-8")
+            .send_string(&body);
+        let xml = read_response(&self.calendar_url, response)?
+            .into_string()
+            .map_err(|e| CalDavError::Transport(self.calendar_url.clone(), e.to_string()))?;
+
+        let mut remote_events = Vec::new();
+        for response in parse_multistatus(&xml)? {
+            let href = self.resolve_href(&response.href);
+            for event in parse_ics(&response.calendar_data)? {
+                if start.0 <= event.date && event.date < end.0 {
+                    remote_events.push(RemoteEvent {
+                        href: href.clone(),
+                        etag: response.etag.clone(),
+                        calendar_data: response.calendar_data.clone(),
+                        event,
+                    });
+                }
+            }
+        }
+
+        Ok(remote_events)
+    }
+
+    /// Writes the block's times into the fetched event, guarded by its ETag.
+    pub fn push_move(
+        &self,
+        remote_event: &RemoteEvent,
+        block: &CalendarBlock,
+    ) -> Result<RemoteEvent, CalDavError> {
+        let uid = remote_event
+            .event
+            .uid
+            .clone()
+            .unwrap_or_else(|| block.id.to_string());
+        let calendar_data = reschedule_ics(
+            &remote_event.calendar_data,
+            &uid,
+            remote_event.event.recurrence_id.as_deref(),
+            &remote_event.event.date,
+            block.start_minute,
+            block.end_minute,
+        )?;
+
+        let request = self
+            .request("PUT", &remote_event.href)
+            .set("Content-Type", "text/calendar; charset=utf-8");
+        let request = match &remote_event.etag {
+            Some(etag) => request.set("If-Match", etag),
+            None => request,
+        };
+        let response = read_response(&remote_event.href, request.send_string(&calendar_data))?;
+
+        Ok(RemoteEvent {
+            etag: response.header("ETag").map(String::from),
+            calendar_data,
+            event: IcsEvent {
+                block: CalendarBlock {
+                    start_minute: block.start_minute,
+                    end_minute: block.end_minute,
+                    ..remote_event.event.block.clone()
+                },
+                ..remote_event.event.clone()
+            },
+            ..remote_event.clone()
+        })
+    }
+
+    /// Creates a new event for the block, failing with `Conflict` if one with
+    /// the same UID already exists.
+    pub fn create_event(
+        &self,
+        day: &Day,
+        block: &CalendarBlock,
+    ) -> Result<RemoteEvent, CalDavError> {
+        let event = IcsEvent {
+            uid: Some(block.id.to_string()),
+            recurrence_id: None,
+            date: day.0.clone(),
+            block: block.clone(),
+        };
+        let calendar_data = write_ics(std::slice::from_ref(&event), &get_utc_timestamp())?;
+        let href = format!("{}{}.ics", self.calendar_url, block.id);
+
+        let response = self
+            .request("PUT", &href)
+            .set("Content-Type", "text/calendar; charset=utf-8")
+            .set("If-None-Match", "*")
+            .send_string(&calendar_data);
+        let response = read_response(&href, response)?;
+
+        Ok(RemoteEvent {
+            etag: response.header("ETag").map(String::from),
+            href,
+            calendar_data,
+            event,
+        })
+    }
+
+    /// Copies the server's events into the store, overwriting local changes
+    /// to the same blocks, and records them in `sync_state` for the next push.
+    pub fn pull(
+        &self,
+        store: &mut impl CalendarStore,
+        sync_state: &mut SyncState,
+        start: &Day,
+        end: &Day,
+    ) -> Result<Vec<RemoteEvent>, CalDavError> {
+        let remote_events = self.fetch_events(start, end)?;
+
+        for remote_event in &remote_events {
+            sync_state.record(remote_event.clone());
+            let block = remote_event.event.block.clone();
+            let day = Day(remote_event.event.date.clone());
+            match store.get(block.id) {
+                Ok(record) if record.day == day && record.block == block => {}
+                Ok(record) if record.day == day => {
+                    store.update(block, record.version)?;
+                }
+                // updates keep the block's day, so a block moved to another day is stored anew
+                Ok(record) => {
+                    store.delete(block.id, record.version)?;
+                    store.insert(&day, block)?;
+                }
+                Err(StoreError::NotFound(_)) => {
+                    store.insert(&day, block)?;
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        Ok(remote_events)
+    }
+
+    /// Pushes every stored block whose times differ from the event recorded at
+    /// the last pull, guarded by that event's ETag. Returns the refreshed events,
+    /// which replace the recorded ones, or why each push failed.
+    pub fn push_moves(
+        &self,
+        store: &impl CalendarStore,
+        sync_state: &mut SyncState,
+    ) -> Vec<Result<RemoteEvent, CalDavError>> {
+        let mut moved: Vec<(RemoteEvent, CalendarBlock)> = sync_state
+            .events
+            .values()
+            .filter_map(|remote_event| {
+                let remote_block = &remote_event.event.block;
+                let local_block = store.get(remote_block.id).ok()?.block;
+                match local_block.start_minute == remote_block.start_minute
+                    && local_block.end_minute == remote_block.end_minute
+                {
+                    true => None,
+                    false => Some((remote_event.clone(), local_block)),
+                }
+            })
+            .collect();
+        moved.sort_by_key(|(_, local_block)| local_block.sort_key());
+
+        moved
+            .into_iter()
+            .map(|(remote_event, local_block)| {
+                let pushed = self.push_move(&remote_event, &local_block)?;
+                sync_state.record(pushed.clone());
+                Ok(pushed)
+            })
+            .collect()
+    }
+
+    /// Hrefs in multistatus responses are usually absolute paths on the server.
+    fn resolve_href(&self, href: &str) -> String {
+        match href.starts_with('/') {
+            true => {
+                let origin_end = self
+                    .calendar_url
+                    .find("://")
+                    .and_then(|scheme_end| {
+                        self.calendar_url[scheme_end + 3..]
+                            .find('/')
+                            .map(|path_start| scheme_end + 3 + path_start)
+                    })
+                    .unwrap_or(self.calendar_url.len());
+                format!("{}{href}", &self.calendar_url[..origin_end])
+            }
+            false => href.to_string(),
+        }
+    }
+}
+
+fn read_response(
+    url: &str,
+    response: Result<ureq::Response, ureq::Error>,
+) -> Result<ureq::Response, CalDavError> {
+    match response {
+        Ok(response) => Ok(response),
+        Err(ureq::Error::Status(412, _)) => Err(CalDavError::Conflict(url.to_string())),
+        Err(ureq::Error::Status(status, _)) => Err(CalDavError::Status(url.to_string(), status)),
+        Err(e) => Err(CalDavError::Transport(url.to_string(), e.to_string())),
+    }
+}
+
+/// Collects href, etag and calendar data of each `response` in a multistatus body.
+fn parse_multistatus(xml: &str) -> Result<Vec<MultistatusResponse>, CalDavError> {
+    let mut reader = Reader::from_str(xml);
+    let mut responses = Vec::new();
+    let mut response: Option<MultistatusResponse> = None;
+    let mut element = Vec::new();
+
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(start)) => {
+                element = start.local_name().as_ref().to_vec();
+                if element == b"response" {
+                    response = Some(MultistatusResponse::default());
+                }
+            }
+            Ok(Event::End(end)) => {
+                if end.local_name().as_ref() == b"response" {
+                    responses.extend(response.take());
+                }
+                element.clear();
+            }
+            Ok(Event::Text(text)) => {
+                let text = text
+                    .unescape()
+                    .map_err(|e| CalDavError::InvalidResponse(e.to_string()))?;
+                push_text(response.as_mut(), &element, &text);
+            }
+            Ok(Event::CData(data)) => {
+                let text = String::from_utf8_lossy(&data);
+                push_text(response.as_mut(), &element, &text);
+            }
+            Ok(Event::Eof) => break,
+            Ok(_) => {}
+            Err(e) => return Err(CalDavError::InvalidResponse(e.to_string())),
+        }
+    }
+
+    // responses without calendar data are collections or failed propstats
+    Ok(responses
+        .into_iter()
+        .filter(|response| !response.calendar_data.trim().is_empty())
+        .collect())
+}
+
+fn push_text(response: Option<&mut MultistatusResponse>, element: &[u8], text: &str) {
+    if let Some(response) = response {
+        match element {
+            b"href" => response.href.push_str(text.trim()),
+            b"getetag" => response.etag = Some(text.trim().to_string()),
+            b"calendar-data" => response.calendar_data.push_str(text),
+            _ => {}
+        }
+    }
+}
+
+fn get_utc_timestamp() -> String {
+    let seconds = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_secs() as i64)
+        .unwrap_or(0);
+    let (year, month, day) = civil_from_days(seconds.div_euclid(86400));
+    let second_of_day = seconds.rem_euclid(86400);
+
+    format!(
+        "{year:04}{month:02}{day:02}T{:02}{:02}{:02}Z",
+        second_of_day / 3600,
+        second_of_day / 60 % 60,
+        second_of_day % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MULTISTATUS: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<D:multistatus xmlns:D="DAV:" xmlns:C="urn:ietf:params:xml:ns:caldav">
+  <D:response>
+    <D:href>/user/calendar/</D:href>
+    <D:propstat><D:prop><D:getetag/></D:prop></D:propstat>
+  </D:response>
+  <D:response>
+    <D:href>/user/calendar/standup.ics</D:href>
+    <D:propstat>
+      <D:prop>
+        <D:getetag>"1-standup"</D:getetag>
+        <C:calendar-data>BEGIN:VCALENDAR&#13;
+BEGIN:VEVENT&#13;
+UID:standup&#13;
+DTSTART:20240501T090000&#13;
+DTEND:20240501T091500&#13;
+SUMMARY:Standup &amp; coffee&#13;
+END:VEVENT&#13;
+END:VCALENDAR&#13;
+</C:calendar-data>
+      </D:prop>
+    </D:propstat>
+  </D:response>
+  <response xmlns="DAV:">
+    <href>/user/calendar/review.ics</href>
+    <propstat><prop>
+      <getetag>"7-review"</getetag>
+      <calendar-data xmlns="urn:ietf:params:xml:ns:caldav"><![CDATA[BEGIN:VCALENDAR
+BEGIN:VEVENT
+UID:review
+DTSTART:20240501T140000
+DURATION:PT1H
+SUMMARY:Review
+END:VEVENT
+END:VCALENDAR
+]]></calendar-data>
+    </prop></propstat>
+  </response>
+</D:multistatus>"#;
+
+    #[test]
+    fn parse_multistatus_skips_responses_without_calendar_data() {
+        let responses = parse_multistatus(MULTISTATUS).unwrap();
+
+        let hrefs: Vec<&str> = responses
+            .iter()
+            .map(|response| response.href.as_str())
+            .collect();
+        assert_eq!(
+            hrefs,
+            ["/user/calendar/standup.ics", "/user/calendar/review.ics"]
+        );
+    }
+
+    #[test]
+    fn parse_multistatus_reads_etags_and_unescapes_calendar_data() {
+        let responses = parse_multistatus(MULTISTATUS).unwrap();
+
+        assert_eq!(responses[0].etag.as_deref(), Some("\"1-standup\""));
+        assert_eq!(responses[1].etag.as_deref(), Some("\"7-review\""));
+
+        let standup = parse_ics(&responses[0].calendar_data).unwrap();
+        assert_eq!(standup[0].block.label, "Standup & coffee");
+        assert_eq!(
+            (standup[0].block.start_minute, standup[0].block.end_minute),
+            (540, 555)
+        );
+
+        let review = parse_ics(&responses[1].calendar_data).unwrap();
+        assert_eq!(
+            (review[0].block.start_minute, review[0].block.end_minute),
+            (840, 900)
+        );
+    }
+
+    #[test]
+    fn parse_multistatus_rejects_malformed_xml() {
+        assert!(matches!(
+            parse_multistatus("<D:multistatus><D:response></D:multistatus>"),
+            Err(CalDavError::InvalidResponse(_))
+        ));
+    }
+
+    #[test]
+    fn resolve_href_joins_absolute_paths_with_the_calendar_origin() {
+        let client = CalDavClient::new("http://localhost:5232/user/calendar");
+
+        assert_eq!(
+            client.resolve_href("/user/calendar/a.ics"),
+            "http://localhost:5232/user/calendar/a.ics"
+        );
+        assert_eq!(
+            client.resolve_href("http://other/a.ics"),
+            "http://other/a.ics"
+        );
+    }
+}
@@ -0,0 +1,176 @@
+use crate::prelude::*;
+
+const HOURS_IN_DAY: u32 = 24;
+
+/// Layout of the time grid, shared by everything that maps minutes to pixels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CalendarConfig {
+    pub snap_minutes: u32,
+    pub pixels_per_hour: f64,
+    pub start_hour: u32,
+    pub end_hour: u32, // exclusive
+    pub column_width: f64,
+    pub stack_padding: f64,
+    pub time_format: TimeFormat,
+}
+
+impl Default for CalendarConfig {
+    fn default() -> Self {
+        Self {
+            snap_minutes: 15,
+            pixels_per_hour: 60.,
+            start_hour: 0,
+            end_hour: 24,
+            column_width: 500.,
+            stack_padding: 10.,
+            time_format: TimeFormat::default(),
+        }
+    }
+}
+
+impl CalendarConfig {
+    pub fn compact() -> Self {
+        Self {
+            snap_minutes: 30,
+            pixels_per_hour: 30.,
+            start_hour: 6,
+            end_hour: 22,
+            column_width: 300.,
+            stack_padding: 6.,
+            ..Self::default()
+        }
+    }
+
+    pub fn detailed() -> Self {
+        Self {
+            snap_minutes: 5,
+            pixels_per_hour: 120.,
+            column_width: 700.,
+            ..Self::default()
+        }
+    }
+
+    /// Start of the visible range, with hours past the end of the day clamped to it.
+    pub fn visible_start_minute(&self) -> u32 {
+        self.start_hour.min(HOURS_IN_DAY) * 60
+    }
+
+    /// End of the visible range, clamped to the day and never before the start.
+    pub fn visible_end_minute(&self) -> u32 {
+        (self.end_hour.min(HOURS_IN_DAY) * 60).max(self.visible_start_minute())
+    }
+
+    pub fn is_visible(&self, start_minute: u32, end_minute: u32) -> bool {
+        start_minute < self.visible_end_minute() && self.visible_start_minute() < end_minute
+    }
+
+    pub fn duration_to_px(&self, minutes: u32) -> f64 {
+        minutes as f64 * self.pixels_per_hour / 60.
+    }
+
+    /// Offset of `minute` from the top of the visible range.
+    pub fn minute_to_px(&self, minute: f64) -> f64 {
+        (minute - self.visible_start_minute() as f64) * self.pixels_per_hour / 60.
+    }
+
+    pub fn px_to_minute(&self, px: f64) -> f64 {
+        px * 60. / self.pixels_per_hour + self.visible_start_minute() as f64
+    }
+
+    /// Rounds `minute` down to the snap interval.
+    pub fn snap(&self, minute: f64) -> i64 {
+        let snap_minutes = self.snap_minutes.max(1) as f64;
+        ((minute / snap_minutes).floor() * snap_minutes) as i64
+    }
+
+    pub fn height(&self) -> f64 {
+        self.duration_to_px(
+            self.visible_end_minute()
+                .saturating_sub(self.visible_start_minute()),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn height_covers_the_visible_hours() {
+        assert_eq!(CalendarConfig::default().height(), 1440.);
+        assert_eq!(CalendarConfig::compact().height(), 480.);
+        assert_eq!(CalendarConfig::detailed().height(), 2880.);
+    }
+
+    #[test]
+    fn out_of_range_hours_are_clamped_to_the_day() {
+        let past_midnight = CalendarConfig {
+            start_hour: 20,
+            end_hour: 30,
+            ..CalendarConfig::default()
+        };
+        assert_eq!(past_midnight.visible_end_minute(), 1440);
+        assert_eq!(past_midnight.height(), 240.);
+
+        let reversed = CalendarConfig {
+            start_hour: 18,
+            end_hour: 8,
+            ..CalendarConfig::default()
+        };
+        assert_eq!(
+            (
+                reversed.visible_start_minute(),
+                reversed.visible_end_minute()
+            ),
+            (1080, 1080)
+        );
+        assert_eq!(reversed.height(), 0.);
+
+        let after_the_day = CalendarConfig {
+            start_hour: 25,
+            end_hour: 26,
+            ..CalendarConfig::default()
+        };
+        assert_eq!(after_the_day.height(), 0.);
+        assert!(!after_the_day.is_visible(0, 1440));
+    }
+
+    #[test]
+    fn duration_to_px_scales_with_pixels_per_hour() {
+        assert_eq!(CalendarConfig::default().duration_to_px(90), 90.);
+        assert_eq!(CalendarConfig::compact().duration_to_px(90), 45.);
+        assert_eq!(CalendarConfig::detailed().duration_to_px(90), 180.);
+        assert_eq!(CalendarConfig::default().duration_to_px(0), 0.);
+    }
+
+    #[test]
+    fn minute_to_px_and_px_to_minute_are_offset_by_the_visible_start() {
+        let config = CalendarConfig::compact();
+
+        assert_eq!(config.minute_to_px(360.), 0.);
+        assert_eq!(config.minute_to_px(420.), 30.);
+        assert_eq!(config.px_to_minute(30.), 420.);
+    }
+
+    #[test]
+    fn snap_rounds_down_to_the_interval() {
+        let config = CalendarConfig::default();
+
+        assert_eq!(config.snap(540.), 540);
+        assert_eq!(config.snap(554.9), 540);
+        assert_eq!(config.snap(555.), 555);
+        assert_eq!(config.snap(-1.), -15);
+        assert_eq!(CalendarConfig::compact().snap(589.), 570);
+        assert_eq!(CalendarConfig::detailed().snap(544.), 540);
+    }
+
+    #[test]
+    fn snap_treats_a_zero_interval_as_one_minute() {
+        let config = CalendarConfig {
+            snap_minutes: 0,
+            ..CalendarConfig::default()
+        };
+
+        assert_eq!(config.snap(541.7), 541);
+    }
+}
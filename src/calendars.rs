@@ -0,0 +1,244 @@
+use crate::prelude::*;
+use std::collections::HashSet;
+
+/// Colors handed out to calendars in the order they're listed, repeating after the last.
+const CALENDAR_COLORS: [&str; 6] = [
+    "#4C5FD5", "#2E9E6A", "#D5544C", "#E0912F", "#8E4CD5", "#3AA7C2",
+];
+
+/// A calendar some of the blocks belong to, as listed in the legend.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CalendarSource {
+    pub id: String,
+    pub color: &'static str,
+}
+
+/// Every calendar the blocks belong to, sorted by id so each keeps its color between renders.
+pub fn get_calendar_sources(blocks: &[CalendarBlock]) -> Vec<CalendarSource> {
+    let mut calendar_ids: Vec<&str> = blocks
+        .iter()
+        .map(|block| block.calendar_id.as_str())
+        .collect();
+    calendar_ids.sort_unstable();
+    calendar_ids.dedup();
+
+    calendar_ids
+        .into_iter()
+        .enumerate()
+        .map(|(idx, id)| CalendarSource {
+            id: id.to_string(),
+            color: CALENDAR_COLORS[idx % CALENDAR_COLORS.len()],
+        })
+        .collect()
+}
+
+/// Whether blocks of different calendars stack on each other or side by side.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CalendarLayering {
+    /// One tree for all calendars, so overlapping blocks nest regardless of calendar.
+    #[default]
+    Merged,
+    /// A tree per calendar, each in its own share of the column.
+    PerCalendar,
+}
+
+impl CalendarLayering {
+    pub fn next(&self) -> Self {
+        match self {
+            CalendarLayering::Merged => CalendarLayering::PerCalendar,
+            CalendarLayering::PerCalendar => CalendarLayering::Merged,
+        }
+    }
+}
+
+impl Display for CalendarLayering {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let calendar_layering = match self {
+            CalendarLayering::Merged => "merged",
+            CalendarLayering::PerCalendar => "per-calendar",
+        };
+        write!(f, "{calendar_layering}")
+    }
+}
+
+/// Trees of the blocks of every calendar not in `hidden_calendar_ids`, left to right.
+pub fn build_calendar_trees(
+    blocks: &[CalendarBlock],
+    hidden_calendar_ids: &HashSet<String>,
+    calendar_layering: CalendarLayering,
+) -> Result<Vec<CalendarBlockTree>, CalendarTreeError> {
+    let visible_blocks: Vec<CalendarBlock> = blocks
+        .iter()
+        .filter(|block| !hidden_calendar_ids.contains(&block.calendar_id))
+        .cloned()
+        .collect();
+
+    match calendar_layering {
+        CalendarLayering::Merged => Ok(vec![CalendarBlockTree::from_blocks(&visible_blocks)?]),
+        CalendarLayering::PerCalendar => get_calendar_sources(&visible_blocks)
+            .iter()
+            .map(|calendar_source| {
+                let calendar_blocks: Vec<CalendarBlock> = visible_blocks
+                    .iter()
+                    .filter(|block| block.calendar_id == calendar_source.id)
+                    .cloned()
+                    .collect();
+                CalendarBlockTree::from_blocks(&calendar_blocks)
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_block(
+        label: &str,
+        start_minute: u32,
+        end_minute: u32,
+        calendar_id: &str,
+    ) -> CalendarBlock {
+        CalendarBlock {
+            id: Uuid::new_v4(),
+            start_minute,
+            end_minute,
+            block_type: CalendarBlockType::Busy,
+            subtree_depth: 0,
+            label: label.to_string(),
+            calendar_id: calendar_id.to_string(),
+            metadata: BlockMetadata::default(),
+        }
+    }
+
+    fn get_blocks() -> Vec<CalendarBlock> {
+        vec![
+            get_block("Standup", 540, 555, "work"),
+            get_block("Gym", 420, 480, "personal"),
+            get_block("Review", 540, 600, "work"),
+            get_block("Dentist", 840, 900, DEFAULT_CALENDAR_ID),
+        ]
+    }
+
+    fn get_labels(calendar_trees: &[CalendarBlockTree]) -> Vec<Vec<String>> {
+        calendar_trees
+            .iter()
+            .map(|calendar_tree| {
+                calendar_tree
+                    .traverse()
+                    .into_iter()
+                    .map(|flattened| flattened.block.label)
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn get_calendar_sources_lists_each_calendar_once_by_id() {
+        let calendar_sources = get_calendar_sources(&get_blocks());
+
+        let ids: Vec<&str> = calendar_sources
+            .iter()
+            .map(|source| source.id.as_str())
+            .collect();
+        assert_eq!(ids, [DEFAULT_CALENDAR_ID, "personal", "work"]);
+        let colors: Vec<&str> = calendar_sources.iter().map(|source| source.color).collect();
+        assert_eq!(colors, CALENDAR_COLORS[..3]);
+    }
+
+    #[test]
+    fn blocks_without_a_calendar_belong_to_the_default_calendar() {
+        let blocks: Vec<CalendarBlock> = serde_json::from_str(
+            r#"[{ "label": "Lunch", "start_minute": 720, "end_minute": 780, "block_type": "Busy" }]"#,
+        )
+        .unwrap();
+
+        assert_eq!(blocks[0].calendar_id, DEFAULT_CALENDAR_ID);
+        assert_eq!(
+            get_calendar_sources(&blocks),
+            [CalendarSource {
+                id: DEFAULT_CALENDAR_ID.to_string(),
+                color: CALENDAR_COLORS[0],
+            }]
+        );
+    }
+
+    #[test]
+    fn calendar_colors_repeat_after_the_last() {
+        let blocks: Vec<CalendarBlock> = (0..=CALENDAR_COLORS.len())
+            .map(|idx| get_block("Block", 540, 600, &format!("calendar-{idx}")))
+            .collect();
+
+        let calendar_sources = get_calendar_sources(&blocks);
+
+        assert_eq!(calendar_sources.last().unwrap().color, CALENDAR_COLORS[0]);
+    }
+
+    #[test]
+    fn build_calendar_trees_leaves_out_hidden_calendars() {
+        let hidden_calendar_ids = HashSet::from([String::from("work")]);
+
+        let merged = build_calendar_trees(
+            &get_blocks(),
+            &hidden_calendar_ids,
+            CalendarLayering::Merged,
+        );
+        let per_calendar = build_calendar_trees(
+            &get_blocks(),
+            &hidden_calendar_ids,
+            CalendarLayering::PerCalendar,
+        );
+
+        assert_eq!(get_labels(&merged.unwrap()), [vec!["Gym", "Dentist"]]);
+        assert_eq!(
+            get_labels(&per_calendar.unwrap()),
+            [vec!["Dentist"], vec!["Gym"]]
+        );
+    }
+
+    #[test]
+    fn build_calendar_trees_keeps_the_default_calendar_unless_hidden() {
+        let shown = build_calendar_trees(
+            &get_blocks(),
+            &HashSet::new(),
+            CalendarLayering::PerCalendar,
+        );
+        let hidden_calendar_ids = HashSet::from([DEFAULT_CALENDAR_ID.to_string()]);
+        let hidden = build_calendar_trees(
+            &get_blocks(),
+            &hidden_calendar_ids,
+            CalendarLayering::PerCalendar,
+        );
+
+        assert_eq!(
+            get_labels(&shown.unwrap()),
+            [vec!["Dentist"], vec!["Gym"], vec!["Review", "Standup"]]
+        );
+        assert_eq!(
+            get_labels(&hidden.unwrap()),
+            [vec!["Gym"], vec!["Review", "Standup"]]
+        );
+    }
+
+    #[test]
+    fn build_calendar_trees_with_every_calendar_hidden() {
+        let hidden_calendar_ids: HashSet<String> = get_calendar_sources(&get_blocks())
+            .into_iter()
+            .map(|calendar_source| calendar_source.id)
+            .collect();
+
+        let merged = build_calendar_trees(
+            &get_blocks(),
+            &hidden_calendar_ids,
+            CalendarLayering::Merged,
+        );
+        let per_calendar = build_calendar_trees(
+            &get_blocks(),
+            &hidden_calendar_ids,
+            CalendarLayering::PerCalendar,
+        );
+
+        assert_eq!(get_labels(&merged.unwrap()), [Vec::<String>::new()]);
+        assert!(per_calendar.unwrap().is_empty());
+    }
+}
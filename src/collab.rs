@@ -0,0 +1,456 @@
+use crate::prelude::*;
+use std::collections::HashSet;
+
+/// Lamport timestamp of an operation. Ties between replicas are broken by the
+/// replica id, so every replica orders any two operations the same way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Timestamp {
+    pub time: u64,
+    pub replica: Uuid,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum OpKind {
+    Create { block: CalendarBlock },
+    Move { start_minute: u32, end_minute: u32 },
+    Resize { start_minute: u32, end_minute: u32 },
+    Delete,
+}
+
+/// An edit of a single block.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Op {
+    pub block_id: Uuid,
+    pub stamp: Timestamp,
+    #[serde(flatten)]
+    pub kind: OpKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CollabMessage {
+    /// Every operation the server has seen, sent once to a joining client.
+    /// `seed` asks the client to share its blocks, which the server asks of
+    /// exactly one client while nothing has been shared yet.
+    Sync {
+        ops: Vec<Op>,
+        #[serde(default)]
+        seed: bool,
+    },
+    Op {
+        op: Op,
+    },
+    /// A drag in progress, or `None` once it ends.
+    Drag {
+        replica: Uuid,
+        drag: Option<DragPosition>,
+    },
+}
+
+#[derive(Debug, Clone)]
+struct BlockEntry {
+    block: CalendarBlock,
+    stamp: Timestamp,
+    is_deleted: bool,
+}
+
+/// A day's blocks as replicated between users. Applying the same operations in
+/// any order gives the same blocks: the latest move or resize of a block wins,
+/// and a deleted block stays deleted.
+#[derive(Debug, Clone)]
+pub struct CollabDoc {
+    replica: Uuid,
+    clock: u64,
+    entries: HashMap<Uuid, BlockEntry>,
+    applied: HashSet<Timestamp>,
+    // edits of blocks whose Create hasn't arrived yet
+    pending: Vec<Op>,
+    is_synced: bool,
+}
+
+impl CollabDoc {
+    pub fn new(replica: Uuid) -> Self {
+        Self {
+            replica,
+            clock: 0,
+            entries: HashMap::new(),
+            applied: HashSet::new(),
+            pending: Vec::new(),
+            is_synced: false,
+        }
+    }
+
+    pub fn replica(&self) -> Uuid {
+        self.replica
+    }
+
+    /// Whether the server's operations have been applied with `sync`.
+    pub fn is_synced(&self) -> bool {
+        self.is_synced
+    }
+
+    /// Applies every operation the server has seen. Edits still waiting for
+    /// their block afterwards are dropped, as the block was never shared.
+    pub fn sync(&mut self, ops: Vec<Op>) {
+        ops.into_iter().for_each(|op| {
+            self.apply(op);
+        });
+        self.pending.clear();
+        self.is_synced = true;
+    }
+
+    /// Turns the differences between `blocks` and the document into local
+    /// edits: new blocks are created, missing ones deleted, and blocks whose
+    /// times changed are moved, or resized when their length changed too.
+    pub fn local_edits(&mut self, blocks: &[CalendarBlock]) -> Vec<Op> {
+        let shared_blocks: HashMap<Uuid, CalendarBlock> = self
+            .blocks()
+            .into_iter()
+            .map(|block| (block.id, block))
+            .collect();
+
+        let mut edits: Vec<(Uuid, OpKind)> = blocks
+            .iter()
+            .filter_map(|block| {
+                let kind = match shared_blocks.get(&block.id) {
+                    None => OpKind::Create {
+                        block: block.clone(),
+                    },
+                    Some(shared_block)
+                        if (shared_block.start_minute, shared_block.end_minute)
+                            == (block.start_minute, block.end_minute) =>
+                    {
+                        return None
+                    }
+                    Some(shared_block)
+                        if shared_block
+                            .end_minute
+                            .saturating_sub(shared_block.start_minute)
+                            == block.end_minute.saturating_sub(block.start_minute) =>
+                    {
+                        OpKind::Move {
+                            start_minute: block.start_minute,
+                            end_minute: block.end_minute,
+                        }
+                    }
+                    Some(_) => OpKind::Resize {
+                        start_minute: block.start_minute,
+                        end_minute: block.end_minute,
+                    },
+                };
+                Some((block.id, kind))
+            })
+            .collect();
+
+        let ids: HashSet<Uuid> = blocks.iter().map(|block| block.id).collect();
+        let mut deleted_ids: Vec<Uuid> = shared_blocks
+            .into_keys()
+            .filter(|id| !ids.contains(id))
+            .collect();
+        deleted_ids.sort();
+        edits.extend(deleted_ids.into_iter().map(|id| (id, OpKind::Delete)));
+
+        edits
+            .into_iter()
+            .map(|(block_id, kind)| self.local(block_id, kind))
+            .collect()
+    }
+
+    /// Stamps and applies a local edit, returning the operation to send to others.
+    pub fn local(&mut self, block_id: Uuid, kind: OpKind) -> Op {
+        let op = Op {
+            block_id,
+            stamp: Timestamp {
+                time: self.clock + 1,
+                replica: self.replica,
+            },
+            kind,
+        };
+        self.apply(op.clone());
+        op
+    }
+
+    /// Applies an operation from any replica. Returns false for duplicates.
+    pub fn apply(&mut self, op: Op) -> bool {
+        if !self.applied.insert(op.stamp) {
+            return false;
+        }
+        self.clock = self.clock.max(op.stamp.time);
+
+        let is_create = matches!(op.kind, OpKind::Create { .. });
+        match (self.entries.get_mut(&op.block_id), op.kind.clone()) {
+            (None, OpKind::Create { block }) => {
+                self.entries.insert(
+                    op.block_id,
+                    BlockEntry {
+                        block: CalendarBlock {
+                            id: op.block_id,
+                            ..block
+                        },
+                        stamp: op.stamp,
+                        is_deleted: false,
+                    },
+                );
+            }
+            (None, _) => self.pending.push(op.clone()),
+            // a block is only ever created once, by whoever owns its id
+            (Some(_), OpKind::Create { .. }) => {}
+            (Some(entry), OpKind::Delete) => entry.is_deleted = true,
+            (
+                Some(entry),
+                OpKind::Move {
+                    start_minute,
+                    end_minute,
+                }
+                | OpKind::Resize {
+                    start_minute,
+                    end_minute,
+                },
+            ) => {
+                if op.stamp > entry.stamp {
+                    entry.block.start_minute = start_minute;
+                    entry.block.end_minute = end_minute;
+                    entry.stamp = op.stamp;
+                }
+            }
+        }
+
+        if is_create {
+            let (ready, pending) = self
+                .pending
+                .drain(..)
+                .partition(|pending_op| pending_op.block_id == op.block_id);
+            self.pending = pending;
+            ready.into_iter().for_each(|ready_op: Op| {
+                self.applied.remove(&ready_op.stamp);
+                self.apply(ready_op);
+            });
+        }
+
+        true
+    }
+
+    /// Live blocks in canonical block order.
+    pub fn blocks(&self) -> Vec<CalendarBlock> {
+        let mut blocks: Vec<CalendarBlock> = self
+            .entries
+            .values()
+            .filter(|entry| !entry.is_deleted)
+            .map(|entry| entry.block.clone())
+            .collect();
+        blocks.sort_by_key(CalendarBlock::sort_key);
+        blocks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_block(label: &str, start_minute: u32, end_minute: u32) -> CalendarBlock {
+        CalendarBlock {
+            id: Uuid::new_v4(),
+            start_minute,
+            end_minute,
+            block_type: CalendarBlockType::Busy,
+            subtree_depth: 0,
+            label: label.to_string(),
+            calendar_id: DEFAULT_CALENDAR_ID.to_string(),
+            metadata: BlockMetadata::default(),
+        }
+    }
+
+    fn get_permutations(ops: &[Op]) -> Vec<Vec<Op>> {
+        match ops.len() {
+            0 => vec![vec![]],
+            _ => (0..ops.len())
+                .flat_map(|i| {
+                    let mut rest = ops.to_vec();
+                    let op = rest.remove(i);
+                    get_permutations(&rest).into_iter().map(move |mut ops| {
+                        ops.insert(0, op.clone());
+                        ops
+                    })
+                })
+                .collect(),
+        }
+    }
+
+    /// Two users editing after both saw the same two blocks: one moves the
+    /// standup and deletes the review, the other resizes the standup and
+    /// moves the review at the same time.
+    fn get_concurrent_ops() -> (Vec<Op>, CalendarBlock, CalendarBlock) {
+        let standup = get_block("Standup", 540, 555);
+        let review = get_block("Review", 840, 900);
+        let mut alice = CollabDoc::new(Uuid::new_v4());
+        let mut bob = CollabDoc::new(Uuid::new_v4());
+
+        let mut ops = alice.local_edits(&[standup.clone(), review.clone()]);
+        bob.sync(ops.clone());
+        ops.push(alice.local(
+            standup.id,
+            OpKind::Move {
+                start_minute: 570,
+                end_minute: 585,
+            },
+        ));
+        ops.push(alice.local(review.id, OpKind::Delete));
+        ops.push(bob.local(
+            standup.id,
+            OpKind::Resize {
+                start_minute: 540,
+                end_minute: 570,
+            },
+        ));
+        ops.push(bob.local(
+            review.id,
+            OpKind::Move {
+                start_minute: 900,
+                end_minute: 960,
+            },
+        ));
+
+        (ops, standup, review)
+    }
+
+    #[test]
+    fn applying_ops_in_any_order_converges() {
+        let (ops, standup, _) = get_concurrent_ops();
+        // the second op of each replica has the same time, the replica id breaks the tie
+        let (alice_move, bob_resize) = (&ops[2], &ops[4]);
+        let (start_minute, end_minute) = match alice_move.stamp > bob_resize.stamp {
+            true => (570, 585),
+            false => (540, 570),
+        };
+        let expected_blocks = vec![CalendarBlock {
+            start_minute,
+            end_minute,
+            ..standup
+        }];
+
+        get_permutations(&ops).into_iter().for_each(|ops| {
+            let mut doc = CollabDoc::new(Uuid::new_v4());
+            ops.into_iter().for_each(|op| {
+                doc.apply(op);
+            });
+            assert_eq!(doc.blocks(), expected_blocks);
+        });
+    }
+
+    #[test]
+    fn duplicate_ops_are_ignored() {
+        let (ops, _, _) = get_concurrent_ops();
+        let mut doc = CollabDoc::new(Uuid::new_v4());
+        ops.iter().for_each(|op| assert!(doc.apply(op.clone())));
+
+        let blocks = doc.blocks();
+        assert!(ops.into_iter().all(|op| !doc.apply(op)));
+        assert_eq!(doc.blocks(), blocks);
+    }
+
+    #[test]
+    fn later_edits_of_a_block_win() {
+        let block = get_block("Standup", 540, 555);
+        let mut doc = CollabDoc::new(Uuid::new_v4());
+        let mut ops = doc.local_edits(std::slice::from_ref(&block));
+        [570, 600].iter().for_each(|start_minute| {
+            ops.push(doc.local(
+                block.id,
+                OpKind::Move {
+                    start_minute: *start_minute,
+                    end_minute: start_minute + 15,
+                },
+            ))
+        });
+
+        // the first move arriving last doesn't undo the second
+        let mut other = CollabDoc::new(Uuid::new_v4());
+        [&ops[0], &ops[2], &ops[1]].into_iter().for_each(|op| {
+            other.apply(op.clone());
+        });
+        assert_eq!(other.blocks(), doc.blocks());
+        assert_eq!(other.blocks()[0].start_minute, 600);
+    }
+
+    #[test]
+    fn local_edits_turn_changed_blocks_into_ops() {
+        let standup = get_block("Standup", 540, 555);
+        let review = get_block("Review", 840, 900);
+        let lunch = get_block("Lunch", 720, 780);
+        let mut doc = CollabDoc::new(Uuid::new_v4());
+        let created = doc.local_edits(&[standup.clone(), review.clone(), lunch.clone()]);
+        assert_eq!(created.len(), 3);
+        assert!(created
+            .iter()
+            .all(|op| matches!(op.kind, OpKind::Create { .. })));
+
+        let retro = get_block("Retro", 960, 1020);
+        let blocks = vec![
+            CalendarBlock {
+                start_minute: 570,
+                end_minute: 585,
+                ..standup.clone()
+            },
+            CalendarBlock {
+                end_minute: 930,
+                ..review.clone()
+            },
+            retro.clone(),
+        ];
+        let kinds: HashMap<Uuid, OpKind> = doc
+            .local_edits(&blocks)
+            .into_iter()
+            .map(|op| (op.block_id, op.kind))
+            .collect();
+
+        assert_eq!(kinds.len(), 4);
+        assert_eq!(
+            kinds[&standup.id],
+            OpKind::Move {
+                start_minute: 570,
+                end_minute: 585
+            }
+        );
+        assert_eq!(
+            kinds[&review.id],
+            OpKind::Resize {
+                start_minute: 840,
+                end_minute: 930
+            }
+        );
+        assert_eq!(kinds[&retro.id], OpKind::Create { block: retro });
+        assert_eq!(kinds[&lunch.id], OpKind::Delete);
+        assert_eq!(
+            doc.blocks()
+                .iter()
+                .map(|block| block.id)
+                .collect::<Vec<_>>(),
+            blocks.iter().map(|block| block.id).collect::<Vec<_>>()
+        );
+        assert!(doc.local_edits(&blocks).is_empty());
+    }
+
+    #[test]
+    fn sync_drops_edits_of_blocks_never_shared() {
+        let block = get_block("Standup", 540, 555);
+        let mut creator = CollabDoc::new(Uuid::new_v4());
+        let create = creator.local_edits(std::slice::from_ref(&block));
+        let orphan_move = creator.local(
+            Uuid::new_v4(),
+            OpKind::Move {
+                start_minute: 0,
+                end_minute: 15,
+            },
+        );
+
+        let mut doc = CollabDoc::new(Uuid::new_v4());
+        assert!(!doc.is_synced());
+        doc.apply(orphan_move);
+        doc.sync(create);
+
+        assert!(doc.is_synced());
+        assert!(doc.pending.is_empty());
+        assert_eq!(doc.blocks(), vec![block]);
+    }
+}
@@ -0,0 +1,51 @@
+use crate::prelude::*;
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::JsCast;
+use web_sys::{MessageEvent, WebSocket};
+
+/// Browser connection to a collab server, exchanging `CollabMessage`s as JSON.
+#[derive(Clone)]
+pub struct CollabSocket {
+    socket: WebSocket,
+}
+
+impl CollabSocket {
+    /// Opens the connection, calling `on_message` for every message received.
+    /// The connection stays open for the lifetime of the page.
+    pub fn connect(
+        url: &str,
+        mut on_message: impl FnMut(&CollabSocket, CollabMessage) + 'static,
+    ) -> Option<Self> {
+        let collab_socket = CollabSocket {
+            socket: WebSocket::new(url).ok()?,
+        };
+
+        let handle_message = {
+            let collab_socket = collab_socket.clone();
+            Closure::wrap(Box::new(move |evt: MessageEvent| {
+                let message = evt
+                    .data()
+                    .as_string()
+                    .map(|json| serde_json::from_str::<CollabMessage>(&json));
+                match message {
+                    Some(Ok(message)) => on_message(&collab_socket, message),
+                    Some(Err(e)) => info!("Ignoring collab message: {e}"),
+                    None => {}
+                }
+            }) as Box<dyn FnMut(MessageEvent)>)
+        };
+        collab_socket
+            .socket
+            .set_onmessage(Some(handle_message.as_ref().unchecked_ref()));
+        handle_message.forget();
+
+        Some(collab_socket)
+    }
+
+    /// Best effort: messages sent while the connection isn't open are dropped.
+    pub fn send(&self, message: &CollabMessage) {
+        if let Ok(json) = serde_json::to_string(message) {
+            let _ = self.socket.send_with_str(&json);
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use crate::prelude::*;
+
+#[derive(Props)]
+pub struct BlockDetailsProps<'details> {
+    block: CalendarBlock,
+    config: CalendarConfig,
+    /// Border drawn around the details, the same as the tooltips'.
+    #[props(default)]
+    variant: tooltip::TooltipVariant,
+    top: f64,
+    left: f64,
+    onclose: EventHandler<'details, ()>,
+}
+
+/// Everything known about a block, opened by clicking it.
+#[allow(non_snake_case)]
+pub fn BlockDetails<'details>(cx: Scope<'details, BlockDetailsProps<'details>>) -> Element {
+    let block = &cx.props.block;
+    let metadata = &block.metadata;
+    let range = cx
+        .props
+        .config
+        .time_format
+        .format_range(block.start_minute, block.end_minute);
+    let duration = format_duration(block.end_minute - block.start_minute);
+    let block_type = block.block_type;
+    let variant_class = match cx.props.variant {
+        tooltip::TooltipVariant::Light => "light",
+        tooltip::TooltipVariant::Dark => "dark",
+    };
+    let top = cx.props.top;
+    let left = cx.props.left;
+
+    let location = match &metadata.location {
+        Some(location) => rsx!(div { class: "block-details-location", "{location}" }),
+        None => rsx!(empty_element::EmptyElement {}),
+    };
+    let description = match &metadata.description {
+        Some(description) => rsx!(p { class: "block-details-description", "{description}" }),
+        None => rsx!(empty_element::EmptyElement {}),
+    };
+    let url = match (metadata.get_link(), &metadata.url) {
+        (Some(link), _) => rsx!(a {
+            class: "block-details-url",
+            href: "{link}",
+            target: "_blank",
+            rel: "noopener noreferrer",
+            "{link}"
+        }),
+        (None, Some(url)) => rsx!(div { class: "block-details-url", "{url}" }),
+        (None, None) => rsx!(empty_element::EmptyElement {}),
+    };
+
+    return cx.render(rsx!(div {
+        class: "absolute block-details {variant_class}",
+        top: "{top}px",
+        left: "{left}px",
+        button {
+            class: "block-details-close",
+            title: "Close",
+            onclick: move |_| cx.props.onclose.call(()),
+            "×"
+        }
+        div { class: "block-details-label", "{block.label}" }
+        div { class: "block-details-time", "{range} ({duration}), {block_type}" }
+        rsx!(location)
+        rsx!(description)
+        ul {
+            class: "block-details-attendees",
+            // the same address can be listed twice, the index keeps keys unique
+            metadata.attendees.iter().enumerate().map(|(idx, attendee)| {
+                let rsvp = attendee.rsvp;
+                let rsvp_class = rsvp.to_partstat().to_lowercase();
+                rsx!(li {
+                    key: "{idx}-{attendee.email}",
+                    class: "rsvp-{rsvp_class}",
+                    "{attendee} · {rsvp}"
+                })
+            })
+        }
+        rsx!(url)
+    }));
+}
@@ -1,104 +1,501 @@
+use crate::dom;
 use crate::prelude::*;
+use gloo_timers::future::TimeoutFuture;
+use std::collections::HashSet;
+
+const CALENDAR_ID: &str = "calendar";
+const CALENDAR_CONTAINER_ID: &str = "calendar-container";
+
+const LONG_PRESS_MS: u32 = 450;
+const LONG_PRESS_TOLERANCE: f64 = 8.;
+const AUTO_SCROLL_EDGE: f64 = 48.;
+const AUTO_SCROLL_MAX_STEP: f64 = 16.;
+const AUTO_SCROLL_INTERVAL_MS: u32 = 16;
+
+const BLOCK_DETAILS_WIDTH: f64 = 240.;
+const BLOCK_DETAILS_OFFSET: f64 = 24.;
+const TOOLTIP_GAP: f64 = 6.;
 
 #[derive(Props)]
 pub struct CalendarProps<'app> {
+    /// Drops are applied with `move_day_block`, which runs them through an
+    /// in-memory `CalendarStore` and `move_stored_block` like any other store.
     calendar_blocks: &'app UseState<Vec<CalendarBlock>>,
+    #[props(default)]
+    drop_policy: DropPolicy,
+    #[props(default)]
+    config: CalendarConfig,
+    #[props(default)]
+    tooltip_variant: tooltip::TooltipVariant,
+    /// Drags other users have in progress, shown as ghosts.
+    #[props(default)]
+    remote_drags: Vec<DragPosition>,
+    /// Called with a block after it was dropped at a new time.
+    onmove: Option<EventHandler<'app, CalendarBlock>>,
+    /// Called whenever a drag moves to another snapped time, and with `None` once it ends.
+    ondrag: Option<EventHandler<'app, Option<DragPosition>>>,
+}
+
+/// A touch that landed on a block but hasn't been held long enough to drag it.
+/// Moving past the tolerance turns it into a pan of the calendar container.
+#[derive(Clone)]
+struct PendingPress {
+    block: CalendarBlock,
+    pointer_id: i32,
+    client_y: f64,
+    is_panning: bool,
+}
+
+#[derive(Clone)]
+struct DragState {
+    config: CalendarConfig,
+    dragged_block: UseState<Option<CalendarBlock>>,
+    ghost_block_start: UseState<i64>,
+    /// Last start passed to `ondrag`, kept without rerendering.
+    reported_start: UseRef<i64>,
+    click_offset: UseState<f64>,
+    pointer_y: UseState<f64>,
+    is_auto_scrolling: UseState<bool>,
+}
+
+impl DragState {
+    fn start(&self, block: CalendarBlock, pointer_id: i32, client_y: f64) {
+        dom::capture_pointer(CALENDAR_ID, pointer_id);
+
+        let block_top = self.config.minute_to_px(block.start_minute as f64);
+        self.ghost_block_start.set(block.start_minute as i64);
+        *self.reported_start.write_silent() = block.start_minute as i64;
+        self.click_offset
+            .set(client_y - get_calendar_top() - block_top);
+        self.pointer_y.set(client_y);
+        self.dragged_block.set(Some(block));
+    }
+
+    fn update_ghost_block_start(&self) {
+        let position_y = *self.pointer_y.current() - get_calendar_top();
+        let destination_minute = self
+            .config
+            .px_to_minute(position_y - *self.click_offset.current());
+        self.ghost_block_start
+            .set(self.config.snap(destination_minute));
+    }
+
+    /// Tells `ondrag` where the ghost is whenever its start has changed since
+    /// the last call.
+    fn report_ghost_move(&self, ondrag: Option<&EventHandler<Option<DragPosition>>>) {
+        let start_minute = *self.ghost_block_start.current();
+        let block_id = match self.dragged_block.current().as_ref() {
+            Some(d_block) if *self.reported_start.read() != start_minute => d_block.id,
+            _ => return,
+        };
+        *self.reported_start.write_silent() = start_minute;
+        if let Some(ondrag) = ondrag {
+            ondrag.call(Some(DragPosition {
+                block_id,
+                start_minute,
+            }));
+        }
+    }
+
+    fn auto_scroll(&self, cx: &ScopeState) {
+        if *self.is_auto_scrolling.current() {
+            return;
+        }
+        self.is_auto_scrolling.set(true);
+
+        let drag_state = self.clone();
+        cx.spawn(async move {
+            let mut unscrolled = 0.;
+            loop {
+                TimeoutFuture::new(AUTO_SCROLL_INTERVAL_MS).await;
+                if drag_state.dragged_block.current().is_none() {
+                    break;
+                }
+                match get_auto_scroll_step(*drag_state.pointer_y.current()) {
+                    Some(step) => {
+                        unscrolled += step;
+                        unscrolled -= dom::scroll_by(CALENDAR_CONTAINER_ID, unscrolled);
+                        // the task can't hold `ondrag`, so the rerender this
+                        // triggers reports the move with `report_ghost_move`
+                        drag_state.update_ghost_block_start();
+                    }
+                    None => break,
+                }
+            }
+            drag_state.is_auto_scrolling.set(false);
+        });
+    }
+}
+
+/// The part of the calendar scrolled into view, in calendar coordinates.
+fn get_visible_bounds(config: &CalendarConfig) -> Rect {
+    let calendar = Rect {
+        left: 0.,
+        top: 0.,
+        width: config.column_width,
+        height: config.height(),
+    };
+
+    match (
+        dom::get_bounds(CALENDAR_ID),
+        dom::get_bounds(CALENDAR_CONTAINER_ID),
+    ) {
+        (Some(calendar_bounds), Some(container_bounds)) => Rect {
+            left: container_bounds.left - calendar_bounds.left,
+            top: container_bounds.top - calendar_bounds.top,
+            ..container_bounds
+        }
+        .intersect(&calendar),
+        _ => calendar,
+    }
+}
+
+fn get_calendar_top() -> f64 {
+    dom::get_vertical_bounds(CALENDAR_ID)
+        .map(|(top, _)| top)
+        .unwrap_or(0.)
 }
 
-fn get_time_from_minutes(minutes: u32) -> String {
-    let start_time = (minutes as f64) / 60.;
-    let start_hour = start_time.floor();
-    let start_time_remainder = (start_time - start_hour) * 60.;
+/// Scroll step for a pointer near the container edges, faster the closer it gets.
+fn get_auto_scroll_step(client_y: f64) -> Option<f64> {
+    let (top, bottom) = dom::get_vertical_bounds(CALENDAR_CONTAINER_ID)?;
 
-    format!("{:02}{:02}", start_hour as u32, start_time_remainder as u32)
+    if client_y < top + AUTO_SCROLL_EDGE {
+        let proximity = ((top + AUTO_SCROLL_EDGE - client_y) / AUTO_SCROLL_EDGE).min(1.);
+        Some(-AUTO_SCROLL_MAX_STEP * proximity)
+    } else if client_y > bottom - AUTO_SCROLL_EDGE {
+        let proximity = ((client_y - bottom + AUTO_SCROLL_EDGE) / AUTO_SCROLL_EDGE).min(1.);
+        Some(AUTO_SCROLL_MAX_STEP * proximity)
+    } else {
+        None
+    }
 }
 
 #[allow(non_snake_case)]
 pub fn Calendar<'app>(cx: Scope<'app, CalendarProps<'app>>) -> Element {
-    let ghost_block_top = use_state(&cx, || 0_f64);
+    let config = cx.props.config;
+    let ghost_block_start = use_state(&cx, || 0_i64);
+    let reported_start = use_ref(&cx, || 0_i64);
     let click_offset = use_state(&cx, || 0_f64);
-    let dragged_block = use_state(&cx, || None::<FlattenedCalendarBlock>);
-    let use_subtree_depth_algorithm = use_state(&cx, || true);
+    let dragged_block = use_state(&cx, || None::<CalendarBlock>);
+    let pointer_y = use_state(&cx, || 0_f64);
+    let is_auto_scrolling = use_state(&cx, || false);
+    let pending_press = use_state(&cx, || None::<PendingPress>);
+    let stacking_strategy = use_state(&cx, StackingStrategy::default);
+    let calendar_layering = use_state(&cx, CalendarLayering::default);
+    let hidden_calendar_ids = use_state(&cx, HashSet::<String>::new);
+    let selected_block_id = use_state(&cx, || None::<Uuid>);
+    let hovered_block_id = use_state(&cx, || None::<Uuid>);
+    // tooltips are kept inside the part scrolled into view, so scrolling hides them
+    use_effect(&cx, (), {
+        let hovered_block_id = hovered_block_id.clone();
+        |_| async move {
+            dom::on_scroll(CALENDAR_CONTAINER_ID, move || hovered_block_id.set(None));
+        }
+    });
+
+    let drag_state = DragState {
+        config,
+        dragged_block: dragged_block.clone(),
+        ghost_block_start: ghost_block_start.clone(),
+        reported_start: reported_start.clone(),
+        click_offset: click_offset.clone(),
+        pointer_y: pointer_y.clone(),
+        is_auto_scrolling: is_auto_scrolling.clone(),
+    };
+    // catches the ghost moving under an auto-scroll
+    drag_state.report_ghost_move(cx.props.ondrag.as_ref());
 
-    let mut calendar_tree = CalendarBlockTree::new();
-    cx.props.calendar_blocks.get().iter().for_each(|block| {
-        let block = block.clone();
-        let _ = calendar_tree.add(block, None);
+    let calendar_sources = get_calendar_sources(cx.props.calendar_blocks.get());
+    // a single calendar keeps the blocks' plain look
+    let calendar_colors: HashMap<String, &str> = match calendar_sources.len() > 1 {
+        true => calendar_sources
+            .iter()
+            .map(|calendar_source| (calendar_source.id.clone(), calendar_source.color))
+            .collect(),
+        false => HashMap::new(),
+    };
+
+    // duplicate ids have no single place in the tree, so show nothing rather than guess
+    let calendar_trees = build_calendar_trees(
+        cx.props.calendar_blocks.get(),
+        hidden_calendar_ids.get(),
+        *calendar_layering.get(),
+    )
+    .unwrap_or_else(|e| {
+        error!("{e}");
+        Vec::new()
     });
+    calendar_trees.iter().for_each(CalendarBlockTree::display);
+    let flattened_block_sets: Vec<Vec<FlattenedCalendarBlock>> = calendar_trees
+        .iter()
+        .map(CalendarBlockTree::traverse)
+        .collect();
+    let block_layouts =
+        layout_side_by_side(&flattened_block_sets, *stacking_strategy.get(), &config);
+
+    // hidden blocks can't be seen conflicting, but still reject drops onto their time
+    let visible_blocks: Vec<CalendarBlock> = cx
+        .props
+        .calendar_blocks
+        .get()
+        .iter()
+        .filter(|block| !hidden_calendar_ids.get().contains(&block.calendar_id))
+        .cloned()
+        .collect();
+    let conflicting_block_ids = get_conflicting_block_ids(&find_conflicts(&visible_blocks));
+    let handle_pointer_move = {
+        let drag_state = drag_state.clone();
+        move |evt: PointerEvent| {
+            let client_y = evt.client_y as f64;
+
+            if let Some(press) = pending_press.get() {
+                let delta_y = client_y - press.client_y;
+                if press.is_panning || delta_y.abs() > LONG_PRESS_TOLERANCE {
+                    let scrolled = dom::scroll_by(CALENDAR_CONTAINER_ID, -delta_y);
+                    // Keep the unscrolled part of the move for the next one.
+                    pending_press.set(Some(PendingPress {
+                        client_y: press.client_y - scrolled,
+                        is_panning: true,
+                        ..press.clone()
+                    }));
+                }
+                return;
+            }
 
-    calendar_tree.display();
-    let flattened_blocks = calendar_tree.traverse();
-    let handle_ghost_block_drag = move |evt: MouseEvent| {
-        if dragged_block.get().is_some() {
-            let position_y = evt.client_y as f64;
-            let destination_pos = ((position_y - click_offset.get()) / 15.).floor() * 15.;
-            ghost_block_top.set(destination_pos);
+            if dragged_block.get().is_some() {
+                drag_state.pointer_y.set(client_y);
+                drag_state.update_ghost_block_start();
+                drag_state.report_ghost_move(cx.props.ondrag.as_ref());
+
+                if get_auto_scroll_step(client_y).is_some() {
+                    drag_state.auto_scroll(&cx);
+                }
+            }
         }
     };
 
-    let handle_move_calendar_block = move |_| {
-        if let Some(dragged_block_value) = dragged_block.get() {
-            let mut updated_blocks: Vec<CalendarBlock> = cx
-                .props
-                .calendar_blocks
-                .get()
-                .iter()
-                .map(|block| {
-                    let offset = *ghost_block_top.get() as u32;
-                    let (start_minute, end_minute) = match block.id == dragged_block_value.block.id
-                    {
-                        true => (
-                            offset,
-                            dragged_block_value.block.end_minute
-                                - dragged_block_value.block.start_minute
-                                + offset,
-                        ),
-                        false => (block.start_minute, block.end_minute),
-                    };
-                    CalendarBlock {
-                        id: block.id,
-                        block_type: block.block_type,
-                        subtree_depth: block.subtree_depth,
-                        end_minute,
-                        start_minute,
-                        label: block.label.clone(),
+    let end_drag = move || {
+        if let (Some(ondrag), Some(_)) = (&cx.props.ondrag, dragged_block.get()) {
+            ondrag.call(None);
+        }
+        dragged_block.set(None);
+    };
+
+    let handle_pointer_cancel = move |evt: PointerEvent| {
+        dom::release_pointer(CALENDAR_ID, evt.pointer_id);
+        pending_press.set(None);
+        end_drag();
+    };
+
+    let drop_result = dragged_block.get().as_ref().map(|d_block| {
+        cx.props.drop_policy.evaluate(
+            d_block,
+            *ghost_block_start.get(),
+            cx.props.calendar_blocks.get(),
+        )
+    });
+    let is_drop_rejected = matches!(drop_result, Some(Err(_)));
+
+    let handle_move_calendar_block = move |evt: PointerEvent| {
+        dom::release_pointer(CALENDAR_ID, evt.pointer_id);
+
+        // a tap, or a drag released where it started, opens the block's details
+        let tapped_block_id = pending_press
+            .get()
+            .as_ref()
+            .filter(|press| !press.is_panning)
+            .map(|press| press.block.id);
+        let clicked_block_id = dragged_block
+            .get()
+            .as_ref()
+            .filter(|d_block| *ghost_block_start.get() == d_block.start_minute as i64)
+            .map(|d_block| d_block.id);
+        pending_press.set(None);
+
+        if let Some(id) = tapped_block_id.or(clicked_block_id) {
+            match *selected_block_id.get() == Some(id) {
+                true => selected_block_id.set(None),
+                false => selected_block_id.set(Some(id)),
+            }
+            end_drag();
+            return;
+        }
+
+        // rejected drops leave the blocks untouched, snapping the block back
+        if let Some(Ok(_)) = &drop_result {
+            let moved = dragged_block.get().as_ref().map(|d_block| {
+                move_day_block(
+                    cx.props.calendar_blocks.get(),
+                    d_block.id,
+                    *ghost_block_start.get(),
+                    cx.props.drop_policy,
+                )
+            });
+            match moved {
+                Some(Ok((moved_block, updated_blocks))) => {
+                    cx.props.calendar_blocks.set(updated_blocks);
+                    if let Some(onmove) = &cx.props.onmove {
+                        onmove.call(moved_block);
                     }
-                })
-                .collect();
-            updated_blocks.sort_by(|a, b| {
-                if a.start_minute < b.start_minute
-                    || a.start_minute == b.start_minute && a.end_minute >= b.end_minute
-                {
-                    Ordering::Less
-                } else {
-                    Ordering::Greater
                 }
-            });
-            cx.props.calendar_blocks.set(updated_blocks);
-            dragged_block.set(None);
+                Some(Err(e)) => error!("Dropping the block failed: {e}"),
+                None => {}
+            }
         };
+        end_drag();
     };
 
     let ghost_block = match dragged_block.get() {
         Some(d_block) => {
-            let height = (d_block.block.end_minute - d_block.block.start_minute) as f64;
+            let duration = d_block.end_minute - d_block.start_minute;
+            let height = config.duration_to_px(duration);
+            let top = config.minute_to_px(*ghost_block_start.get() as f64);
+            let width = config.column_width;
+            let ghost_start_minute = (*ghost_block_start.get()).max(0) as u32;
             let label = format!(
-                "{}, {}",
-                d_block.block.label,
-                get_time_from_minutes(d_block.block.start_minute)
+                "{}, {} ({})",
+                d_block.label,
+                config
+                    .time_format
+                    .format_range(ghost_start_minute, ghost_start_minute + duration),
+                format_duration(duration)
             );
 
+            let class = match is_drop_rejected {
+                true => "ghost rejected",
+                false => "ghost",
+            };
+
             rsx!(calendar_block::CalendarBlockListItem {
-                class: "ghost",
-                top: format!("{}px", *ghost_block_top.get()),
+                class: class,
+                top: format!("{top}px"),
                 left: format!("{}px", 0),
                 height: format!("{height}px"),
-                width: format!("{MAX_COL_WIDTH}px"),
+                width: format!("{width}px"),
                 opacity: 100,
                 label: "{label}",
-                block_type: d_block.block.block_type,
-                onmouseup: handle_move_calendar_block,
-                onmousemove: handle_ghost_block_drag,
+                block_type: d_block.block_type,
+            })
+        }
+        None => rsx!(empty_element::EmptyElement {}),
+    };
+
+    // (id, top, height, label, block type) of the blocks other users are dragging
+    let remote_ghost_blocks: Vec<(String, f64, f64, String, CalendarBlockType)> = cx
+        .props
+        .remote_drags
+        .iter()
+        .filter_map(|drag| {
+            let block = cx
+                .props
+                .calendar_blocks
+                .get()
+                .iter()
+                .find(|block| block.id == drag.block_id)?;
+            let duration = block.end_minute - block.start_minute;
+            let start_minute = drag.start_minute.max(0) as u32;
+            Some((
+                block.id.to_string(),
+                config.minute_to_px(drag.start_minute as f64),
+                config.duration_to_px(duration),
+                format!(
+                    "{}, {}",
+                    block.label,
+                    config
+                        .time_format
+                        .format_range(start_minute, start_minute + duration)
+                ),
+                block.block_type,
+            ))
+        })
+        .collect();
+
+    let legend = match calendar_sources.len() > 1 {
+        true => rsx!(calendar_legend::CalendarLegend {
+            calendar_sources: calendar_sources,
+            hidden_calendar_ids: hidden_calendar_ids,
+        }),
+        false => rsx!(empty_element::EmptyElement {}),
+    };
+
+    let calendar_width = config.column_width;
+    let calendar_height = config.height();
+
+    let selected_block_layout = selected_block_id.get().and_then(|id| {
+        block_layouts
+            .iter()
+            .find(|block_layout| block_layout.block.id == id)
+            .cloned()
+    });
+    let block_details = match selected_block_layout {
+        Some(block_layout) => rsx!(block_details::BlockDetails {
+            block: block_layout.block,
+            config: config,
+            variant: cx.props.tooltip_variant,
+            top: block_layout.top + block_layout.height.min(BLOCK_DETAILS_OFFSET),
+            left: (block_layout.left + BLOCK_DETAILS_OFFSET)
+                .min(calendar_width - BLOCK_DETAILS_WIDTH)
+                .max(0.),
+            onclose: move |_| selected_block_id.set(None),
+        }),
+        None => rsx!(empty_element::EmptyElement {}),
+    };
+
+    // hovering shows a summary, unless the block is being dragged or its details are open
+    let hovered_block_layout = hovered_block_id
+        .get()
+        .filter(|id| dragged_block.get().is_none() && *selected_block_id.get() != Some(*id))
+        .and_then(|id| {
+            block_layouts
+                .iter()
+                .find(|block_layout| block_layout.block.id == id)
+                .cloned()
+        });
+    let block_tooltip = match hovered_block_layout {
+        Some(block_layout) => {
+            let block = &block_layout.block;
+            let anchor = Rect {
+                left: block_layout.left,
+                top: block_layout.top,
+                width: block_layout.width,
+                height: block_layout.height,
+            };
+            let (left, top) = place_overlay(
+                &anchor,
+                tooltip::TOOLTIP_WIDTH,
+                tooltip::TOOLTIP_HEIGHT,
+                &get_visible_bounds(&config),
+                TOOLTIP_GAP,
+            );
+
+            let label = block.label.clone();
+            let range = config
+                .time_format
+                .format_range(block.start_minute, block.end_minute);
+            let duration = format_duration(block.end_minute - block.start_minute);
+            let parent = match calendar_trees
+                .iter()
+                .find_map(|calendar_tree| calendar_tree.parent(block.id))
+            {
+                Some(parent) => format!(
+                    "Inside {} {}",
+                    parent.label,
+                    config
+                        .time_format
+                        .format_range(parent.start_minute, parent.end_minute)
+                ),
+                None => String::new(),
+            };
+
+            rsx!(tooltip::Tooltip {
+                variant: cx.props.tooltip_variant,
+                top: top,
+                left: left,
+                div { class: "tooltip-label", "{label}" }
+                div { "{range}" }
+                div { "{duration}" }
+                div { class: "tooltip-parent", "{parent}" }
             })
         }
         None => rsx!(empty_element::EmptyElement {}),
@@ -108,59 +505,133 @@ pub fn Calendar<'app>(cx: Scope<'app, CalendarProps<'app>>) -> Element {
         button {
             class: "btn",
             onclick: move |_| {
-                use_subtree_depth_algorithm.set(!use_subtree_depth_algorithm.get());
+                stacking_strategy.set(stacking_strategy.get().next());
             },
             "Switch Stacking Algorithm"
         }
+        button {
+            class: "btn",
+            onclick: move |_| {
+                calendar_layering.set(calendar_layering.get().next());
+            },
+            "Layering: {calendar_layering}"
+        }
+        rsx!(legend)
         div {
+            id: "{CALENDAR_CONTAINER_ID}",
             class: "calendar-container",
             div {
-                class: "calendar flex noselect",
-                onmousemove: handle_ghost_block_drag,
-                flattened_blocks.iter().map(move |flattened_block|
-                    {
-                        let flattened_block = flattened_block.clone();
-                        let dragged_block_option = dragged_block.get();
-
-                        let opacity = match dragged_block_option.is_some()
-                            && (flattened_block.block.id.to_string() == dragged_block_option.as_ref().unwrap().block.id.to_string()) {
-                            true => 50,
-                            false => 100,
-                        };
-
-                        let (left, width) = match use_subtree_depth_algorithm.get() {
-                            true => get_subtree_depth_transforms(flattened_block.stack_position, flattened_block.block.subtree_depth),
-                            false => get_position_offsets(flattened_block.stack_position)
-                        };
-                        let top = format!("{}px", flattened_block.block.start_minute);
-                        let height = format!("{}px", flattened_block.block.end_minute - flattened_block.block.start_minute);
-
-                        let label = format!("{}, {}",
-                            flattened_block.block.label,
-                            get_time_from_minutes(flattened_block.block.start_minute));
-
-                        let id = flattened_block.block.id;
-                        let block_type = flattened_block.block.block_type;
-
-                        return rsx!(calendar_block::CalendarBlockListItem {
-                            key: "{id}",
-                            left: left,
-                            top: top,
-                            width: width,
-                            height: height,
+                class: "calendar-day",
+                time_axis::HourGutter { config: config }
+                div {
+                    id: "{CALENDAR_ID}",
+                    class: "calendar flex noselect",
+                    width: "{calendar_width}px",
+                    height: "{calendar_height}px",
+                    onpointermove: handle_pointer_move,
+                    onpointerup: handle_move_calendar_block,
+                    onpointercancel: handle_pointer_cancel,
+                    time_axis::Gridlines { config: config }
+                    block_layouts.iter().map(move |block_layout|
+                        {
+                            let block = block_layout.block.clone();
+                            let drag_state = drag_state.clone();
+                            let dragged_block_option = dragged_block.get();
+
+                            let opacity = match dragged_block_option.is_some()
+                                && (block.id.to_string() == dragged_block_option.as_ref().unwrap().id.to_string()) {
+                                true => 50,
+                                false => 100,
+                            };
+
+                            let left = format!("{}px", block_layout.left);
+                            let width = format!("{}px", block_layout.width);
+                            let top = format!("{}px", block_layout.top);
+                            let height = format!("{}px", block_layout.height);
+
+                            let label = format!("{}, {}",
+                                block.label,
+                                config.time_format.format_range(block.start_minute, block.end_minute));
+
+                            let id = block.id;
+                            let block_type = block.block_type;
+                            let is_conflicting = conflicting_block_ids.contains(&id);
+                            let color = calendar_colors.get(&block.calendar_id).copied();
+                            let fill = block.metadata.color.clone();
+
+                            return rsx!(calendar_block::CalendarBlockListItem {
+                                key: "{id}",
+                                left: left,
+                                top: top,
+                                width: width,
+                                height: height,
+                                label: "{label}",
+                                block_type: block_type,
+                                is_conflicting: is_conflicting,
+                                color: color,
+                                fill: fill,
+                                opacity: opacity,
+                                onpointerenter: move |evt: PointerEvent| {
+                                    if evt.pointer_type != "touch" {
+                                        hovered_block_id.set(Some(id));
+                                    }
+                                },
+                                onpointerleave: move |_| {
+                                    if *hovered_block_id.get() == Some(id) {
+                                        hovered_block_id.set(None);
+                                    }
+                                },
+                                onpointerdown: move |evt: PointerEvent| {
+                                    let client_y = evt.client_y as f64;
+
+                                    if evt.pointer_type != "touch" {
+                                        if evt.button == 0 {
+                                            drag_state.start(block.clone(), evt.pointer_id, client_y);
+                                        }
+                                        return;
+                                    }
+
+                                    pending_press.set(Some(PendingPress {
+                                        block: block.clone(),
+                                        pointer_id: evt.pointer_id,
+                                        client_y,
+                                        is_panning: false,
+                                    }));
+
+                                    let drag_state = drag_state.clone();
+                                    let pending_press = pending_press.clone();
+                                    cx.spawn(async move {
+                                        TimeoutFuture::new(LONG_PRESS_MS).await;
+                                        let press = pending_press.current();
+                                        if let Some(press) = press.as_ref() {
+                                            if press.pointer_id == evt.pointer_id && !press.is_panning {
+                                                drag_state.start(press.block.clone(), press.pointer_id, press.client_y);
+                                                pending_press.set(None);
+                                            }
+                                        }
+                                    });
+                                },
+                            });
+                        }
+                    )
+                    time_axis::NowIndicator { config: config }
+                    remote_ghost_blocks.iter().map(|(id, top, height, label, block_type)| rsx!(
+                        calendar_block::CalendarBlockListItem {
+                            key: "remote-{id}",
+                            class: "ghost remote",
+                            top: format!("{top}px"),
+                            left: format!("{}px", 0),
+                            height: format!("{height}px"),
+                            width: format!("{calendar_width}px"),
+                            opacity: 100,
                             label: "{label}",
-                            block_type: block_type,
-                            opacity: opacity,
-                            onmousedown: move |evt: MouseEvent| {
-                                dragged_block.set(Some(flattened_block.clone()));
-                                ghost_block_top.set(flattened_block.block.start_minute as f64);
-                                click_offset.set(evt.client_y  as f64 - flattened_block.block.start_minute as f64);
-                            },
-                            onmouseup: handle_move_calendar_block,
-                        });
-                    }
-                )
-                rsx!(ghost_block)
+                            block_type: *block_type,
+                        }
+                    ))
+                    rsx!(ghost_block)
+                    rsx!(block_details)
+                    rsx!(block_tooltip)
+                }
             }
         }
     });
@@ -10,42 +10,61 @@ pub struct CalendarBlockListItemProps<'block> {
     opacity: u8,
     label: &'block str,
     block_type: CalendarBlockType,
-    onmousedown: Option<EventHandler<'block, MouseEvent>>,
-    onmouseup: EventHandler<'block, MouseEvent>,
-    onmousemove: Option<EventHandler<'block, MouseEvent>>,
+    /// Color of the calendar the block belongs to, drawn as its left edge.
+    #[props(default, !optional)]
+    color: Option<&'block str>,
+    /// Overrides the block type's background.
+    #[props(default, !optional)]
+    fill: Option<String>,
+    #[props(default)]
+    is_conflicting: bool,
+    onpointerdown: Option<EventHandler<'block, PointerEvent>>,
+    onpointerenter: Option<EventHandler<'block, PointerEvent>>,
+    onpointerleave: Option<EventHandler<'block, PointerEvent>>,
 }
 
 #[allow(non_snake_case)]
 pub fn CalendarBlockListItem<'block>(
     cx: Scope<'block, CalendarBlockListItemProps<'block>>,
 ) -> Element {
-    let block_type_class = match cx.props.block_type {
-        CalendarBlockType::Wrapper => "wrapper",
-        CalendarBlockType::Busy => "busy",
-        CalendarBlockType::Available => "available",
-    };
+    let block_type_class = cx.props.block_type.info().css_class;
     let classes = match cx.props.class {
         Some(c) => c.to_string(),
         None => "".to_string(),
     };
+    let (calendar_class, color) = match cx.props.color {
+        Some(color) => ("calendar-colored", color),
+        None => ("", ""),
+    };
+    let fill = cx.props.fill.as_deref().unwrap_or("");
+    let conflict_class = match cx.props.is_conflicting {
+        true => "conflict",
+        false => "",
+    };
 
     return cx.render(rsx!(div {
-        class: "absolute calendar-block {block_type_class} {classes}",
-        title: "{cx.props.label}",
+        class: "absolute calendar-block {block_type_class} {conflict_class} {calendar_class} {classes}",
+        aria_label: "{cx.props.label}",
         top: "{cx.props.top}",
         left: "{cx.props.left}",
         height: "{cx.props.height}",
         width: "{cx.props.width}",
         opacity: "{cx.props.opacity}%",
-        onmousedown: move |evt| {
-            if let Some(handle_mouse_down) = &cx.props.onmousedown {
-                handle_mouse_down.call(evt);
+        border_left_color: "{color}",
+        background_color: "{fill}",
+        onpointerdown: move |evt| {
+            if let Some(handle_pointer_down) = &cx.props.onpointerdown {
+                handle_pointer_down.call(evt);
+            }
+        },
+        onpointerenter: move |evt| {
+            if let Some(handle_pointer_enter) = &cx.props.onpointerenter {
+                handle_pointer_enter.call(evt);
             }
         },
-        onmouseup: move |evt| cx.props.onmouseup.call(evt),
-        onmousemove: move |evt| {
-            if let Some(handle_mouse_move) = &cx.props.onmousemove {
-                handle_mouse_move.call(evt);
+        onpointerleave: move |evt| {
+            if let Some(handle_pointer_leave) = &cx.props.onpointerleave {
+                handle_pointer_leave.call(evt);
             }
         },
         "{cx.props.label}"
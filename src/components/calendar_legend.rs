@@ -0,0 +1,46 @@
+use crate::prelude::*;
+use std::collections::HashSet;
+
+#[derive(Props)]
+pub struct CalendarLegendProps<'legend> {
+    calendar_sources: Vec<CalendarSource>,
+    hidden_calendar_ids: &'legend UseState<HashSet<String>>,
+}
+
+/// One toggle per calendar, showing or hiding its blocks.
+#[allow(non_snake_case)]
+pub fn CalendarLegend<'legend>(cx: Scope<'legend, CalendarLegendProps<'legend>>) -> Element {
+    let hidden_calendar_ids = cx.props.hidden_calendar_ids;
+
+    return cx.render(rsx!(div {
+        class: "calendar-legend flex flex-row",
+        cx.props.calendar_sources.iter().map(|calendar_source| {
+            let id = calendar_source.id.clone();
+            let color = calendar_source.color;
+            let is_hidden = hidden_calendar_ids.get().contains(&id);
+            let hidden_class = match is_hidden {
+                true => "hidden",
+                false => "",
+            };
+
+            rsx!(button {
+                key: "{calendar_source.id}",
+                class: "btn calendar-legend-item {hidden_class}",
+                title: "Show or hide {calendar_source.id}",
+                onclick: move |_| {
+                    let mut calendar_ids = hidden_calendar_ids.get().clone();
+                    match is_hidden {
+                        true => calendar_ids.remove(&id),
+                        false => calendar_ids.insert(id.clone()),
+                    };
+                    hidden_calendar_ids.set(calendar_ids);
+                },
+                span {
+                    class: "calendar-swatch",
+                    background_color: "{color}",
+                }
+                "{calendar_source.id}"
+            })
+        })
+    }));
+}
@@ -1,3 +1,7 @@
+pub mod block_details;
 pub mod calendar;
 pub mod calendar_block;
+pub mod calendar_legend;
 pub mod empty_element;
+pub mod time_axis;
+pub mod tooltip;
@@ -0,0 +1,89 @@
+use crate::prelude::*;
+use gloo_timers::future::TimeoutFuture;
+
+const NOW_REFRESH_MS: u32 = 30_000;
+
+#[derive(Props, PartialEq)]
+pub struct TimeAxisProps {
+    config: CalendarConfig,
+}
+
+fn get_current_minute() -> u32 {
+    let now = js_sys::Date::new_0();
+    now.get_hours() * 60 + now.get_minutes()
+}
+
+#[allow(non_snake_case)]
+pub fn HourGutter(cx: Scope<TimeAxisProps>) -> Element {
+    let config = cx.props.config;
+    let gutter_height = config.height();
+
+    cx.render(rsx!(div {
+        class: "hour-gutter",
+        height: "{gutter_height}px",
+        (config.visible_start_minute() / 60..config.visible_end_minute() / 60).map(|hour| {
+            let top = config.minute_to_px((hour * 60) as f64);
+            let label = config.time_format.format_time(hour * 60);
+
+            rsx!(div {
+                key: "{hour}",
+                class: "absolute hour-label",
+                top: "{top}px",
+                "{label}"
+            })
+        })
+    }))
+}
+
+/// Lines on every snap interval, emphasized on the hour and half-hour.
+#[allow(non_snake_case)]
+pub fn Gridlines(cx: Scope<TimeAxisProps>) -> Element {
+    let config = cx.props.config;
+    let snap_minutes = config.snap_minutes.max(1) as usize;
+
+    cx.render(rsx!((config.visible_start_minute()
+        ..config.visible_end_minute())
+        .step_by(snap_minutes)
+        .map(|minute| {
+            let top = config.minute_to_px(minute as f64);
+            let class = match (minute % 60, minute % 30) {
+                (0, _) => "hour",
+                (_, 0) => "half-hour",
+                _ => "snap",
+            };
+
+            rsx!(div {
+                key: "{minute}",
+                class: "absolute gridline {class}",
+                top: "{top}px",
+            })
+        })))
+}
+
+#[allow(non_snake_case)]
+pub fn NowIndicator(cx: Scope<TimeAxisProps>) -> Element {
+    let config = cx.props.config;
+    let current_minute = use_state(&cx, get_current_minute);
+
+    use_future(&cx, (), |_| {
+        let current_minute = current_minute.clone();
+        async move {
+            loop {
+                TimeoutFuture::new(NOW_REFRESH_MS).await;
+                current_minute.set(get_current_minute());
+            }
+        }
+    });
+
+    let minute = *current_minute.get();
+    if !config.is_visible(minute, minute + 1) {
+        return None;
+    }
+
+    let top = config.minute_to_px(minute as f64);
+
+    cx.render(rsx!(div {
+        class: "absolute now-indicator",
+        top: "{top}px",
+    }))
+}
@@ -0,0 +1,41 @@
+use crate::prelude::*;
+
+/// Size of a tooltip in pixels, matching `.tooltip` in `app.css`, so it can be
+/// placed before it's drawn.
+pub const TOOLTIP_WIDTH: f64 = 220.;
+pub const TOOLTIP_HEIGHT: f64 = 92.;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TooltipVariant {
+    #[default]
+    Light,
+    Dark,
+}
+
+#[derive(Props)]
+pub struct TooltipProps<'tooltip> {
+    #[props(default)]
+    variant: TooltipVariant,
+    top: f64,
+    left: f64,
+    children: Element<'tooltip>,
+}
+
+/// A box drawn with the bundled tooltip border, at a position picked with `place_overlay`.
+#[allow(non_snake_case)]
+pub fn Tooltip<'tooltip>(cx: Scope<'tooltip, TooltipProps<'tooltip>>) -> Element {
+    let variant_class = match cx.props.variant {
+        TooltipVariant::Light => "light",
+        TooltipVariant::Dark => "dark",
+    };
+    let top = cx.props.top;
+    let left = cx.props.left;
+
+    return cx.render(rsx!(div {
+        class: "absolute tooltip {variant_class}",
+        role: "tooltip",
+        top: "{top}px",
+        left: "{left}px",
+        &cx.props.children
+    }));
+}
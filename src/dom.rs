@@ -0,0 +1,137 @@
+use crate::prelude::*;
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::JsCast;
+
+fn get_element(element_id: &str) -> Option<web_sys::Element> {
+    web_sys::window()?.document()?.get_element_by_id(element_id)
+}
+
+/// Routes all further events of `pointer_id` to the element, so a drag keeps
+/// receiving moves after the pointer leaves the dragged block.
+pub fn capture_pointer(element_id: &str, pointer_id: i32) {
+    if let Some(element) = get_element(element_id) {
+        let _ = element.set_pointer_capture(pointer_id);
+    }
+}
+
+pub fn release_pointer(element_id: &str, pointer_id: i32) {
+    if let Some(element) = get_element(element_id) {
+        if element.has_pointer_capture(pointer_id) {
+            let _ = element.release_pointer_capture(pointer_id);
+        }
+    }
+}
+
+/// Returns the (top, bottom) edges of the element in client coordinates.
+pub fn get_vertical_bounds(element_id: &str) -> Option<(f64, f64)> {
+    get_element(element_id).map(|element| {
+        let rect = element.get_bounding_client_rect();
+        (rect.top(), rect.bottom())
+    })
+}
+
+/// The element's box in client coordinates.
+pub fn get_bounds(element_id: &str) -> Option<Rect> {
+    get_element(element_id).map(|element| {
+        let rect = element.get_bounding_client_rect();
+        Rect {
+            left: rect.left(),
+            top: rect.top(),
+            width: rect.width(),
+            height: rect.height(),
+        }
+    })
+}
+
+/// Scrolls by `delta_y` rounded to whole pixels and returns the rounded amount,
+/// so callers can carry the rest over to their next step.
+pub fn scroll_by(element_id: &str, delta_y: f64) -> f64 {
+    let delta_y = delta_y.round();
+    if let Some(element) = get_element(element_id) {
+        element.set_scroll_top(element.scroll_top() + delta_y as i32);
+    }
+    delta_y
+}
+
+/// Whether a media query such as `(prefers-color-scheme: dark)` currently matches.
+pub fn matches_media(query: &str) -> bool {
+    web_sys::window()
+        .and_then(|window| window.match_media(query).ok()?)
+        .map(|media_query_list| media_query_list.matches())
+        .unwrap_or(false)
+}
+
+/// Calls `on_change` every time the media query starts or stops matching, for
+/// as long as the page is open.
+pub fn on_media_change(query: &str, on_change: impl FnMut() + 'static) {
+    let media_query_list =
+        match web_sys::window().and_then(|window| window.match_media(query).ok()?) {
+            Some(media_query_list) => media_query_list,
+            None => return,
+        };
+
+    let handle_change = Closure::wrap(Box::new(on_change) as Box<dyn FnMut()>);
+    media_query_list.set_onchange(Some(handle_change.as_ref().unchecked_ref()));
+    handle_change.forget();
+}
+
+/// Calls `on_scroll` every time the element scrolls, for as long as the page is
+/// open. Scroll events don't bubble, so Dioxus' own listeners never see them.
+pub fn on_scroll(element_id: &str, on_scroll: impl FnMut() + 'static) {
+    let element = match get_element(element_id)
+        .and_then(|element| element.dyn_into::<web_sys::HtmlElement>().ok())
+    {
+        Some(element) => element,
+        None => return,
+    };
+
+    let handle_scroll = Closure::wrap(Box::new(on_scroll) as Box<dyn FnMut()>);
+    element.set_onscroll(Some(handle_scroll.as_ref().unchecked_ref()));
+    handle_scroll.forget();
+}
+
+/// Sets each (name, value) as a custom property on the root element, overriding the stylesheet's.
+pub fn set_css_variables(variables: &[(String, String)]) {
+    let root = web_sys::window()
+        .and_then(|window| window.document())
+        .and_then(|document| document.document_element())
+        .and_then(|element| element.dyn_into::<web_sys::HtmlElement>().ok());
+
+    if let Some(root) = root {
+        let style = root.style();
+        variables.iter().for_each(|(name, value)| {
+            let _ = style.set_property(name, value);
+        });
+    }
+}
+
+pub fn get_locale() -> Option<String> {
+    web_sys::window()?.navigator().language()
+}
+
+fn get_local_storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok()?
+}
+
+pub fn get_local_storage_item(key: &str) -> Option<String> {
+    get_local_storage()?.get_item(key).ok()?
+}
+
+/// Best effort: storage can be full or disabled, in which case nothing is saved.
+pub fn set_local_storage_item(key: &str, value: &str) {
+    if let Some(storage) = get_local_storage() {
+        let _ = storage.set_item(key, value);
+    }
+}
+
+/// Value of a parameter in the page's query string, such as `?collab=ws://localhost:9001`.
+pub fn get_query_param(name: &str) -> Option<String> {
+    let search = web_sys::window()?.location().search().ok()?;
+    search
+        .trim_start_matches('?')
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| *key == name)
+        .and_then(|(_, value)| js_sys::decode_uri_component(value).ok())
+        .and_then(|value| value.as_string())
+}
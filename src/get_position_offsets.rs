@@ -1,30 +1,76 @@
 use crate::prelude::*;
 
-pub fn get_position_offsets(stack_position: usize) -> (String, String) {
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StackingStrategy {
+    #[default]
+    SubtreeDepth,
+    StackPosition,
+}
+
+impl StackingStrategy {
+    pub fn next(&self) -> Self {
+        match self {
+            StackingStrategy::SubtreeDepth => StackingStrategy::StackPosition,
+            StackingStrategy::StackPosition => StackingStrategy::SubtreeDepth,
+        }
+    }
+
+    /// Returns the (left, width) of the block in pixels.
+    pub fn get_horizontal_extent(
+        &self,
+        config: &CalendarConfig,
+        flattened_block: &FlattenedCalendarBlock,
+    ) -> (f64, f64) {
+        match self {
+            StackingStrategy::SubtreeDepth => get_subtree_depth_transforms(
+                config,
+                flattened_block.stack_position,
+                flattened_block.block.subtree_depth,
+            ),
+            StackingStrategy::StackPosition => {
+                get_position_offsets(config, flattened_block.stack_position)
+            }
+        }
+    }
+}
+
+impl Display for StackingStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let stacking_strategy = match self {
+            StackingStrategy::SubtreeDepth => "subtree-depth",
+            StackingStrategy::StackPosition => "stack-position",
+        };
+        write!(f, "{stacking_strategy}")
+    }
+}
+
+pub fn get_position_offsets(config: &CalendarConfig, stack_position: usize) -> (f64, f64) {
     let stack_position = stack_position as f64;
-    match stack_position < 1.0 {
-        true => (format!("{}", 0), format!("{MAX_COL_WIDTH}px")),
+    let column_width = config.column_width;
+    match stack_position <= 1.0 {
+        true => (0.0, column_width),
         false => {
-            let stack_separation = stack_position * BLOCK_STACK_PADDING;
+            let stack_separation = stack_position * config.stack_padding;
             let left_offset =
-                MAX_COL_WIDTH - MAX_COL_WIDTH / (stack_position - 1.0) + stack_separation;
-            let width = MAX_COL_WIDTH / (stack_position - 1.0) - 2.0 * stack_separation;
-            (format!("{left_offset}px"), format!("{width}px"))
+                column_width - column_width / (stack_position - 1.0) + stack_separation;
+            let width = column_width / (stack_position - 1.0) - 2.0 * stack_separation;
+            (left_offset, width)
         }
     }
 }
 
 pub fn get_subtree_depth_transforms(
+    config: &CalendarConfig,
     stack_position: usize,
     subtree_depth: usize,
-) -> (String, String) {
+) -> (f64, f64) {
     let stack_position = stack_position as f64;
     let subtree_depth = subtree_depth as f64;
 
     let width_divisor = stack_position + subtree_depth;
 
     match stack_position < 1.0 {
-        true => (0.to_string(), MAX_COL_WIDTH.to_string()),
+        true => (0.0, config.column_width),
         false => {
             let width = match subtree_depth > 0.0 {
                 true => 1.8 / width_divisor,
@@ -32,9 +78,28 @@ pub fn get_subtree_depth_transforms(
             };
 
             (
-                format!("calc(100% * {})", (stack_position - 1.0) / width_divisor),
-                format!("calc(100% * {width})"),
+                config.column_width * (stack_position - 1.0) / width_divisor,
+                config.column_width * width,
             )
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn get_position_offsets_gives_top_level_blocks_the_whole_column() {
+        let config = CalendarConfig {
+            column_width: 240.,
+            ..CalendarConfig::default()
+        };
+
+        // top-level blocks are at stack position 1, which used to divide by zero
+        assert_eq!(get_position_offsets(&config, 0), (0., 240.));
+        assert_eq!(get_position_offsets(&config, 1), (0., 240.));
+        assert_eq!(get_position_offsets(&config, 2), (20., 200.));
+        assert_eq!(get_position_offsets(&config, 3), (150., 60.));
+    }
+}
@@ -0,0 +1,1000 @@
+use crate::prelude::*;
+
+const MINUTES_IN_DAY: u32 = 1440;
+
+/// Keeps the block types iCalendar has no busy status for.
+const BLOCK_TYPE_PROPERTY: &str = "X-CAL-ENDER-TYPE";
+
+#[derive(Debug, Error, PartialEq)]
+pub enum IcsError {
+    #[error("line {0} is not a property: \"{1}\"")]
+    InvalidLine(usize, String),
+    #[error("invalid date-time \"{0}\"")]
+    InvalidDateTime(String),
+    #[error("invalid duration \"{0}\"")]
+    InvalidDuration(String),
+    #[error("event \"{0}\" has no DTSTART")]
+    MissingStart(String),
+    #[error("no event with UID \"{0}\"")]
+    MissingEvent(String),
+}
+
+/// A VEVENT mapped onto the day it starts on.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IcsEvent {
+    pub uid: Option<String>,
+    /// Set on an overridden occurrence of a recurring event, which shares the
+    /// UID of the event it overrides.
+    #[serde(default)]
+    pub recurrence_id: Option<String>,
+    pub date: String, // YYYY-MM-DD
+    pub block: CalendarBlock,
+}
+
+#[derive(Default)]
+struct EventBuilder {
+    uid: Option<String>,
+    recurrence_id: Option<String>,
+    summary: Option<String>,
+    start: Option<(String, Option<u32>)>,
+    end: Option<(String, Option<u32>)>,
+    duration: Option<u32>,
+    is_transparent: bool,
+    is_tentative: bool,
+    /// Outlook's X-MICROSOFT-CDO-BUSYSTATUS, the only place out-of-office shows up.
+    busy_status: Option<String>,
+    block_type: Option<CalendarBlockType>,
+    metadata: BlockMetadata,
+}
+
+impl EventBuilder {
+    fn build(self) -> Result<IcsEvent, IcsError> {
+        let label = self.summary.unwrap_or_default();
+        let (date, start_minute) = self
+            .start
+            .ok_or_else(|| IcsError::MissingStart(label.clone()))?;
+
+        // all-day events have no time and cover the whole day
+        let start_minute = start_minute.unwrap_or(0);
+        let end_minute = match (self.end, self.duration) {
+            (Some((end_date, Some(end_minute))), _) if end_date == date => end_minute,
+            (Some(_), _) => MINUTES_IN_DAY,
+            (None, Some(duration)) => start_minute.saturating_add(duration).min(MINUTES_IN_DAY),
+            (None, None) => MINUTES_IN_DAY,
+        };
+
+        // occurrences share their event's UID, so they need the RECURRENCE-ID for a distinct id
+        let id = match (&self.uid, &self.recurrence_id) {
+            (Some(uid), None) => Uuid::parse_str(uid)
+                .unwrap_or_else(|_| Uuid::new_v5(&Uuid::NAMESPACE_OID, uid.as_bytes())),
+            (Some(uid), Some(recurrence_id)) => Uuid::new_v5(
+                &Uuid::NAMESPACE_OID,
+                format!("{uid}/{recurrence_id}").as_bytes(),
+            ),
+            (None, _) => Uuid::new_v4(),
+        };
+
+        let block_type = match (
+            self.block_type,
+            self.busy_status.as_deref(),
+            self.is_tentative,
+        ) {
+            (Some(block_type), _, _) => block_type,
+            (None, Some("OOF"), _) => CalendarBlockType::OutOfOffice,
+            (None, Some("TENTATIVE"), _) | (None, None, true) => CalendarBlockType::Tentative,
+            (None, Some("FREE"), _) => CalendarBlockType::Available,
+            (None, Some(_), _) => CalendarBlockType::Busy,
+            (None, None, false) => match self.is_transparent {
+                true => CalendarBlockType::Available,
+                false => CalendarBlockType::Busy,
+            },
+        };
+
+        Ok(IcsEvent {
+            uid: self.uid,
+            recurrence_id: self.recurrence_id,
+            date,
+            block: CalendarBlock {
+                id,
+                start_minute,
+                end_minute: end_minute.max(start_minute),
+                block_type,
+                subtree_depth: 0,
+                label,
+                calendar_id: DEFAULT_CALENDAR_ID.to_string(),
+                metadata: self.metadata,
+            },
+        })
+    }
+}
+
+/// Parses the VEVENTs of an iCalendar document. Times are read as wall-clock
+/// times, ignoring TZID and UTC markers. Components nested in an event, like
+/// its alarms, are skipped.
+pub fn parse_ics(source: &str) -> Result<Vec<IcsEvent>, IcsError> {
+    let mut events = Vec::new();
+    let mut event: Option<EventBuilder> = None;
+    let mut nested_depth = 0;
+
+    for (line_idx, line) in unfold_lines(source).iter().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let (name, value) = split_property(line)
+            .ok_or_else(|| IcsError::InvalidLine(line_idx + 1, line.clone()))?;
+
+        match (name.as_str(), value, event.as_mut()) {
+            ("BEGIN", "VEVENT", None) => event = Some(EventBuilder::default()),
+            // components inside an event, like a VALARM, have properties of their own
+            ("BEGIN", _, Some(_)) => nested_depth += 1,
+            ("END", _, Some(_)) if nested_depth > 0 => nested_depth -= 1,
+            (_, _, Some(_)) if nested_depth > 0 => {}
+            ("END", "VEVENT", Some(_)) => {
+                if let Some(builder) = event.take() {
+                    events.push(builder.build()?);
+                }
+            }
+            ("UID", value, Some(builder)) => builder.uid = Some(value.to_string()),
+            ("RECURRENCE-ID", value, Some(builder)) => {
+                builder.recurrence_id = Some(value.to_string())
+            }
+            ("SUMMARY", value, Some(builder)) => builder.summary = Some(unescape_text(value)),
+            ("DTSTART", value, Some(builder)) => builder.start = Some(parse_date_time(value)?),
+            ("DTEND", value, Some(builder)) => builder.end = Some(parse_date_time(value)?),
+            ("DURATION", value, Some(builder)) => builder.duration = Some(parse_duration(value)?),
+            ("TRANSP", value, Some(builder)) => builder.is_transparent = value == "TRANSPARENT",
+            ("STATUS", value, Some(builder)) => builder.is_tentative = value == "TENTATIVE",
+            ("X-MICROSOFT-CDO-BUSYSTATUS", value, Some(builder)) => {
+                builder.busy_status = Some(value.to_string())
+            }
+            (BLOCK_TYPE_PROPERTY, value, Some(builder)) => {
+                builder.block_type = match value {
+                    "FOCUS" => Some(CalendarBlockType::Focus),
+                    "TRAVEL" => Some(CalendarBlockType::Travel),
+                    _ => None,
+                }
+            }
+            ("DESCRIPTION", value, Some(builder)) => {
+                builder.metadata.description = Some(unescape_text(value))
+            }
+            ("LOCATION", value, Some(builder)) => {
+                builder.metadata.location = Some(unescape_text(value))
+            }
+            ("URL", value, Some(builder)) => builder.metadata.url = Some(value.to_string()),
+            ("COLOR", value, Some(builder)) => builder.metadata.color = Some(value.to_string()),
+            ("ATTENDEE", value, Some(builder)) => builder.metadata.attendees.push(Attendee {
+                name: get_param(line, "CN"),
+                email: value
+                    .strip_prefix("mailto:")
+                    .or_else(|| value.strip_prefix("MAILTO:"))
+                    .unwrap_or(value)
+                    .to_string(),
+                rsvp: get_param(line, "PARTSTAT")
+                    .map(|partstat| RsvpStatus::from_partstat(&partstat))
+                    .unwrap_or_default(),
+            }),
+            _ => {}
+        }
+    }
+
+    Ok(events)
+}
+
+/// Joins folded continuation lines, which start with a space or tab.
+fn unfold_lines(source: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    source.lines().for_each(
+        |line| match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(continuation), Some(last)) => last.push_str(continuation),
+            _ => lines.push(line.to_string()),
+        },
+    );
+    lines
+}
+
+/// Splits `NAME;PARAM=...:VALUE` into the upper-cased name and the value.
+fn split_property(line: &str) -> Option<(String, &str)> {
+    let mut in_quotes = false;
+    let value_idx = line.char_indices().find_map(|(idx, c)| match c {
+        '"' => {
+            in_quotes = !in_quotes;
+            None
+        }
+        ':' if !in_quotes => Some(idx),
+        _ => None,
+    })?;
+
+    let name = line[..value_idx].split(';').next()?.to_uppercase();
+    Some((name, &line[value_idx + 1..]))
+}
+
+/// The value of a property parameter, like CN in `ATTENDEE;CN="Doe, Jane":mailto:jane@example.com`.
+fn get_param(line: &str, param: &str) -> Option<String> {
+    let (_, value) = split_property(line)?;
+    let params = &line[..line.len() - value.len() - 1];
+
+    let mut in_quotes = false;
+    params
+        .split(|c| {
+            if c == '"' {
+                in_quotes = !in_quotes;
+            }
+            c == ';' && !in_quotes
+        })
+        .skip(1)
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| key.eq_ignore_ascii_case(param))
+        .map(|(_, value)| value.trim_matches('"').to_string())
+}
+
+/// Parses `YYYYMMDD` or `YYYYMMDDTHHMMSS[Z]` into the date and minute of day.
+fn parse_date_time(value: &str) -> Result<(String, Option<u32>), IcsError> {
+    let invalid = || IcsError::InvalidDateTime(value.to_string());
+    let digits = |range: std::ops::Range<usize>| -> Result<u32, IcsError> {
+        value
+            .get(range)
+            .filter(|part| part.chars().all(|c| c.is_ascii_digit()))
+            .and_then(|part| part.parse().ok())
+            .ok_or_else(invalid)
+    };
+
+    let date = format!(
+        "{:04}-{:02}-{:02}",
+        digits(0..4)?,
+        digits(4..6)?,
+        digits(6..8)?
+    );
+    match value.get(8..9) {
+        None => Ok((date, None)),
+        Some("T") => match (digits(9..11)?, digits(11..13)?) {
+            (hour @ 0..=23, minute @ 0..=59) => Ok((date, Some(hour * 60 + minute))),
+            _ => Err(invalid()),
+        },
+        Some(_) => Err(invalid()),
+    }
+}
+
+/// Parses durations such as `PT1H30M` or `P1D` into minutes.
+fn parse_duration(value: &str) -> Result<u32, IcsError> {
+    let invalid = || IcsError::InvalidDuration(value.to_string());
+    let body = value
+        .trim_start_matches('+')
+        .strip_prefix('P')
+        .ok_or_else(invalid)?;
+
+    let mut minutes: u32 = 0;
+    let mut number = String::new();
+    for c in body.chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            'T' => {}
+            unit => {
+                let amount: u32 = number.parse().map_err(|_| invalid())?;
+                let unit_minutes = match unit {
+                    'W' => 7 * MINUTES_IN_DAY,
+                    'D' => MINUTES_IN_DAY,
+                    'H' => 60,
+                    'M' => 1,
+                    'S' => 0,
+                    _ => return Err(invalid()),
+                };
+                minutes = amount
+                    .checked_mul(unit_minutes)
+                    .and_then(|unit_total| minutes.checked_add(unit_total))
+                    .ok_or_else(invalid)?;
+                number.clear();
+            }
+        }
+    }
+
+    Ok(minutes)
+}
+
+fn unescape_text(value: &str) -> String {
+    let mut text = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('n') | Some('N') => text.push('\n'),
+                Some(escaped) => text.push(escaped),
+                None => {}
+            },
+            c => text.push(c),
+        }
+    }
+    text
+}
+
+const MAX_LINE_OCTETS: usize = 75;
+
+/// Writes the events as an iCalendar document. `dtstamp` is the UTC time the
+/// document is created at, as `YYYYMMDDTHHMMSSZ`.
+pub fn write_ics(events: &[IcsEvent], dtstamp: &str) -> Result<String, IcsError> {
+    let mut lines = vec![
+        String::from("BEGIN:VCALENDAR"),
+        String::from("VERSION:2.0"),
+        String::from("PRODID:-//cal-ender//EN"),
+    ];
+
+    for event in events {
+        let block = &event.block;
+        let uid = event.uid.clone().unwrap_or_else(|| block.id.to_string());
+        let transp = match block.block_type.busyness() {
+            Busyness::Free => "TRANSPARENT",
+            _ => "OPAQUE",
+        };
+        let busy_status = match block.block_type {
+            CalendarBlockType::Available => "FREE",
+            CalendarBlockType::Tentative => "TENTATIVE",
+            CalendarBlockType::OutOfOffice => "OOF",
+            _ => "BUSY",
+        };
+        let block_type = match block.block_type {
+            CalendarBlockType::Focus => Some("FOCUS"),
+            CalendarBlockType::Travel => Some("TRAVEL"),
+            _ => None,
+        };
+
+        let metadata = &block.metadata;
+        lines.extend([
+            String::from("BEGIN:VEVENT"),
+            format!("UID:{}", escape_text(&uid)),
+            format!("DTSTAMP:{dtstamp}"),
+        ]);
+        lines.extend(
+            event
+                .recurrence_id
+                .as_ref()
+                .map(|recurrence_id| format!("RECURRENCE-ID:{recurrence_id}")),
+        );
+        lines.extend([
+            format!(
+                "DTSTART:{}",
+                format_date_time(&event.date, block.start_minute)?
+            ),
+            format!("DTEND:{}", format_date_time(&event.date, block.end_minute)?),
+            format!("SUMMARY:{}", escape_text(&block.label)),
+            format!("TRANSP:{transp}"),
+            format!("X-MICROSOFT-CDO-BUSYSTATUS:{busy_status}"),
+        ]);
+        lines.extend(block_type.map(|block_type| format!("{BLOCK_TYPE_PROPERTY}:{block_type}")));
+        lines.extend(
+            [
+                (
+                    "DESCRIPTION",
+                    metadata.description.as_deref().map(escape_text),
+                ),
+                ("LOCATION", metadata.location.as_deref().map(escape_text)),
+                ("URL", metadata.url.clone()),
+                ("COLOR", metadata.color.clone()),
+            ]
+            .into_iter()
+            .filter_map(|(name, value)| Some(format!("{name}:{}", value?))),
+        );
+        lines.extend(metadata.attendees.iter().map(format_attendee));
+        lines.push(String::from("END:VEVENT"));
+    }
+    lines.push(String::from("END:VCALENDAR"));
+
+    Ok(lines.iter().map(|line| fold_line(line)).collect())
+}
+
+/// Moves the VEVENT with the given UID and RECURRENCE-ID (`None` for an event
+/// without one) to new wall-clock times, leaving every other line of the
+/// document untouched. TZID parameters and UTC markers of the original times
+/// are kept, and a DURATION is rewritten in place of DTEND.
+pub fn reschedule_ics(
+    source: &str,
+    uid: &str,
+    recurrence_id: Option<&str>,
+    date: &str,
+    start_minute: u32,
+    end_minute: u32,
+) -> Result<String, IcsError> {
+    let lines = unfold_lines(source);
+
+    // find the events' lines first, UID may come after DTSTART
+    let event_lines = get_event_lines(&lines)
+        .into_iter()
+        .find(|line_indices| {
+            let properties: Vec<(String, &str)> = line_indices
+                .iter()
+                .filter_map(|line_idx| split_property(&lines[*line_idx]))
+                .collect();
+            let get_value = |property: &str| {
+                properties
+                    .iter()
+                    .find(|(name, _)| name == property)
+                    .map(|(_, value)| *value)
+            };
+            get_value("UID") == Some(uid) && get_value("RECURRENCE-ID") == recurrence_id
+        })
+        .ok_or_else(|| IcsError::MissingEvent(uid.to_string()))?;
+
+    let rescheduled: Vec<String> = lines
+        .iter()
+        .enumerate()
+        .map(|(line_idx, line)| -> Result<String, IcsError> {
+            let property = match event_lines.contains(&line_idx) {
+                true => split_property(line),
+                false => None,
+            };
+            match property {
+                Some((name, value)) if name == "DTSTART" || name == "DTEND" => {
+                    let minute = match name == "DTSTART" {
+                        true => start_minute,
+                        false => end_minute,
+                    };
+                    let utc_marker = match value.ends_with('Z') {
+                        true => "Z",
+                        false => "",
+                    };
+                    let params = &line[..line.len() - value.len() - 1];
+                    let params = params.replace(";VALUE=DATE", "");
+                    Ok(format!(
+                        "{params}:{}{utc_marker}",
+                        format_date_time(date, minute)?
+                    ))
+                }
+                Some((name, _)) if name == "DURATION" => {
+                    Ok(format!("DURATION:PT{}M", end_minute - start_minute))
+                }
+                _ => Ok(line.clone()),
+            }
+        })
+        .collect::<Result<_, _>>()?;
+
+    Ok(rescheduled.iter().map(|line| fold_line(line)).collect())
+}
+
+/// The indices of each VEVENT's own property lines, leaving out the lines of
+/// components nested in it, like a VALARM.
+fn get_event_lines(lines: &[String]) -> Vec<Vec<usize>> {
+    let mut events = Vec::new();
+    let mut event: Option<Vec<usize>> = None;
+    let mut nested_depth = 0;
+
+    for (line_idx, line) in lines.iter().enumerate() {
+        match (split_property(line), event.as_mut()) {
+            (Some((name, "VEVENT")), None) if name == "BEGIN" => event = Some(Vec::new()),
+            (Some((name, _)), Some(_)) if name == "BEGIN" => nested_depth += 1,
+            (Some((name, _)), Some(_)) if name == "END" && nested_depth > 0 => nested_depth -= 1,
+            (Some((name, "VEVENT")), Some(_)) if name == "END" => events.extend(event.take()),
+            (Some(_), Some(line_indices)) if nested_depth == 0 => line_indices.push(line_idx),
+            _ => {}
+        }
+    }
+
+    events
+}
+
+fn format_attendee(attendee: &Attendee) -> String {
+    let name = match &attendee.name {
+        // quotes can't be escaped inside a parameter value
+        Some(name) => format!(";CN=\"{}\"", name.replace('"', "")),
+        None => String::new(),
+    };
+    format!(
+        "ATTENDEE{name};PARTSTAT={}:mailto:{}",
+        attendee.rsvp.to_partstat(),
+        attendee.email
+    )
+}
+
+/// `YYYY-MM-DD` and a minute of the day as `YYYYMMDDTHHMMSS`. Midnight at the
+/// end of the day is written as the start of the next, there is no `T240000`.
+fn format_date_time(date: &str, minute: u32) -> Result<String, IcsError> {
+    let date = shift_day(date, (minute / MINUTES_IN_DAY) as i64)
+        .ok_or_else(|| IcsError::InvalidDateTime(date.to_string()))?;
+    let minute = minute % MINUTES_IN_DAY;
+    Ok(format!("{date}T{:02}{:02}00", minute / 60, minute % 60))
+}
+
+/// Splits lines longer than 75 octets into continuation lines, ending each with CRLF.
+fn fold_line(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len() + 2);
+    let mut line_octets = 0;
+
+    line.chars().for_each(|c| {
+        if line_octets + c.len_utf8() > MAX_LINE_OCTETS {
+            folded.push_str("\r\n ");
+            line_octets = 1;
+        }
+        folded.push(c);
+        line_octets += c.len_utf8();
+    });
+    folded.push_str("\r\n");
+    folded
+}
+
+fn escape_text(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Days since 1970-01-01 for a proleptic Gregorian date, after Howard Hinnant's
+/// `days_from_civil`.
+pub(crate) fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = match month <= 2 {
+        true => year - 1,
+        false => year,
+    };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month_index = match month > 2 {
+        true => month - 3,
+        false => month + 9,
+    };
+    let day_of_year = (153 * month_index + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+/// Inverse of `days_from_civil`.
+pub(crate) fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = match month_index < 10 {
+        true => month_index + 3,
+        false => month_index - 9,
+    };
+    let year = year_of_era + era * 400;
+    match month <= 2 {
+        true => (year + 1, month, day),
+        false => (year, month, day),
+    }
+}
+
+/// The day `offset` days away from a `YYYY-MM-DD` date, as `YYYYMMDD`.
+pub(crate) fn shift_day(date: &str, offset: i64) -> Option<String> {
+    let mut parts = date.splitn(3, '-').map(|part| part.parse::<i64>().ok());
+    let (year, month, day) = match (parts.next(), parts.next(), parts.next()) {
+        (Some(Some(year)), Some(Some(month)), Some(Some(day))) => (year, month, day),
+        _ => return None,
+    };
+
+    let (year, month, day) = civil_from_days(days_from_civil(year, month, day) + offset);
+    Some(format!("{year:04}{month:02}{day:02}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CALENDAR: &str = "BEGIN:VCALENDAR\r
+VERSION:2.0\r
+BEGIN:VEVENT\r
+DTSTART;TZID=Europe/Berlin:20240501T090000\r
+DTEND;TZID=Europe/Berlin:20240501T100000\r
+SUMMARY:Planning\r
+UID:planning\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+UID:review\r
+DTSTART:20240501T140000Z\r
+DURATION:PT1H\r
+SUMMARY:Review\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+UID:offsite\r
+DTSTART;VALUE=DATE:20240502\r
+DTEND;VALUE=DATE:20240503\r
+SUMMARY:Offsite\r
+END:VEVENT\r
+END:VCALENDAR\r
+";
+
+    const RECURRING_CALENDAR: &str = "BEGIN:VCALENDAR\r
+VERSION:2.0\r
+BEGIN:VEVENT\r
+UID:standup\r
+DTSTART:20240501T093000\r
+DTEND:20240501T094500\r
+RRULE:FREQ=DAILY\r
+SUMMARY:Standup\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+UID:standup\r
+RECURRENCE-ID:20240502T093000\r
+DTSTART:20240502T100000\r
+DTEND:20240502T101500\r
+SUMMARY:Standup\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+UID:standup\r
+RECURRENCE-ID:20240503T093000\r
+DTSTART:20240503T110000\r
+DTEND:20240503T111500\r
+SUMMARY:Standup\r
+END:VEVENT\r
+END:VCALENDAR\r
+";
+
+    const ALARM_CALENDAR: &str = "BEGIN:VCALENDAR\r
+VERSION:2.0\r
+BEGIN:VEVENT\r
+UID:dentist\r
+DTSTART:20240501T150000\r
+DURATION:PT45M\r
+SUMMARY:Dentist\r
+DESCRIPTION:Bring the insurance card\r
+BEGIN:VALARM\r
+ACTION:DISPLAY\r
+TRIGGER:-PT30M\r
+DURATION:PT5M\r
+REPEAT:2\r
+DESCRIPTION:Leave for the dentist\r
+END:VALARM\r
+END:VEVENT\r
+END:VCALENDAR\r
+";
+
+    fn get_lines(source: &str) -> Vec<&str> {
+        source.lines().collect()
+    }
+
+    #[test]
+    fn reschedule_ics_keeps_tzid_and_touches_only_the_event() {
+        let rescheduled =
+            reschedule_ics(CALENDAR, "planning", None, "2024-05-01", 630, 690).unwrap();
+
+        let original = get_lines(CALENDAR);
+        let lines = get_lines(&rescheduled);
+        assert_eq!(lines.len(), original.len());
+        assert_eq!(lines[3], "DTSTART;TZID=Europe/Berlin:20240501T103000");
+        assert_eq!(lines[4], "DTEND;TZID=Europe/Berlin:20240501T113000");
+        let unchanged: Vec<usize> = (0..lines.len())
+            .filter(|idx| ![3, 4].contains(idx))
+            .collect();
+        unchanged
+            .iter()
+            .for_each(|idx| assert_eq!(lines[*idx], original[*idx]));
+    }
+
+    #[test]
+    fn reschedule_ics_keeps_the_utc_marker_and_rewrites_duration() {
+        let rescheduled = reschedule_ics(CALENDAR, "review", None, "2024-05-01", 900, 945).unwrap();
+
+        let lines = get_lines(&rescheduled);
+        assert_eq!(lines[10], "DTSTART:20240501T150000Z");
+        assert_eq!(lines[11], "DURATION:PT45M");
+    }
+
+    #[test]
+    fn reschedule_ics_turns_all_day_events_into_timed_ones() {
+        let rescheduled = reschedule_ics(CALENDAR, "offsite", None, "2024-05-02", 0, 1440).unwrap();
+
+        let lines = get_lines(&rescheduled);
+        assert_eq!(lines[16], "DTSTART:20240502T000000");
+        assert_eq!(lines[17], "DTEND:20240503T000000");
+
+        let events = parse_ics(&rescheduled).unwrap();
+        assert_eq!(
+            (events[2].block.start_minute, events[2].block.end_minute),
+            (0, 1440)
+        );
+    }
+
+    #[test]
+    fn reschedule_ics_fails_for_an_unknown_uid() {
+        assert_eq!(
+            reschedule_ics(CALENDAR, "missing", None, "2024-05-01", 0, 60),
+            Err(IcsError::MissingEvent(String::from("missing")))
+        );
+    }
+
+    #[test]
+    fn parse_ics_gives_overridden_occurrences_their_own_ids() {
+        let events = parse_ics(RECURRING_CALENDAR).unwrap();
+
+        let ids: std::collections::HashSet<Uuid> =
+            events.iter().map(|event| event.block.id).collect();
+        assert_eq!(ids.len(), 3);
+        assert_eq!(events[0].recurrence_id, None);
+        assert_eq!(events[1].recurrence_id.as_deref(), Some("20240502T093000"));
+        assert_eq!(
+            parse_ics(RECURRING_CALENDAR).unwrap()[1].block.id,
+            events[1].block.id
+        );
+    }
+
+    #[test]
+    fn write_ics_keeps_the_recurrence_id() {
+        let events = parse_ics(RECURRING_CALENDAR).unwrap();
+        let written = write_ics(&events, "20240501T000000Z").unwrap();
+
+        assert!(written.contains("RECURRENCE-ID:20240503T093000\r\n"));
+        assert_eq!(parse_ics(&written).unwrap(), events);
+    }
+
+    #[test]
+    fn reschedule_ics_moves_only_the_matching_occurrence() {
+        let rescheduled = reschedule_ics(
+            RECURRING_CALENDAR,
+            "standup",
+            Some("20240503T093000"),
+            "2024-05-03",
+            720,
+            735,
+        )
+        .unwrap();
+
+        let lines = get_lines(&rescheduled);
+        let original = get_lines(RECURRING_CALENDAR);
+        assert_eq!(lines[19], "DTSTART:20240503T120000");
+        assert_eq!(lines[20], "DTEND:20240503T121500");
+        let unchanged: Vec<usize> = (0..lines.len())
+            .filter(|idx| ![19, 20].contains(idx))
+            .collect();
+        unchanged
+            .iter()
+            .for_each(|idx| assert_eq!(lines[*idx], original[*idx]));
+    }
+
+    #[test]
+    fn reschedule_ics_fails_for_an_unknown_occurrence() {
+        assert_eq!(
+            reschedule_ics(
+                RECURRING_CALENDAR,
+                "standup",
+                Some("20240504T093000"),
+                "2024-05-04",
+                0,
+                60
+            ),
+            Err(IcsError::MissingEvent(String::from("standup")))
+        );
+    }
+
+    #[test]
+    fn parse_ics_ignores_the_properties_of_nested_alarms() {
+        let events = parse_ics(ALARM_CALENDAR).unwrap();
+
+        assert_eq!(events.len(), 1);
+        let block = &events[0].block;
+        assert_eq!((block.start_minute, block.end_minute), (900, 945));
+        assert_eq!(
+            block.metadata.description.as_deref(),
+            Some("Bring the insurance card")
+        );
+        assert_eq!(
+            parse_ics(&write_ics(&events, "20240501T000000Z").unwrap()).unwrap(),
+            events
+        );
+    }
+
+    #[test]
+    fn reschedule_ics_leaves_nested_alarms_alone() {
+        let rescheduled =
+            reschedule_ics(ALARM_CALENDAR, "dentist", None, "2024-05-01", 960, 1020).unwrap();
+
+        let lines = get_lines(&rescheduled);
+        assert_eq!(lines[4], "DTSTART:20240501T160000");
+        assert_eq!(lines[5], "DURATION:PT60M");
+        assert_eq!(lines[11], "DURATION:PT5M");
+        let events = parse_ics(&rescheduled).unwrap();
+        assert_eq!(
+            (events[0].block.start_minute, events[0].block.end_minute),
+            (960, 1020)
+        );
+    }
+
+    #[test]
+    fn write_ics_writes_midnight_as_the_next_day() {
+        let events = parse_ics(CALENDAR).unwrap();
+        let written = write_ics(&events[2..], "20240501T000000Z").unwrap();
+
+        assert!(written.contains("DTSTART:20240502T000000\r\n"));
+        assert!(written.contains("DTEND:20240503T000000\r\n"));
+        assert!(!written.contains("T2400"));
+    }
+
+    #[test]
+    fn write_ics_rejects_malformed_dates() {
+        let event = IcsEvent {
+            date: String::from("May 1st"),
+            ..parse_ics(CALENDAR).unwrap().remove(0)
+        };
+
+        assert_eq!(
+            write_ics(&[event], "20240501T000000Z"),
+            Err(IcsError::InvalidDateTime(String::from("May 1st")))
+        );
+    }
+
+    #[test]
+    fn shift_day_crosses_months_years_and_leap_days() {
+        assert_eq!(shift_day("2024-05-01", 0).as_deref(), Some("20240501"));
+        assert_eq!(shift_day("2024-05-01", -1).as_deref(), Some("20240430"));
+        assert_eq!(shift_day("2024-12-31", 1).as_deref(), Some("20250101"));
+        assert_eq!(shift_day("2025-01-01", -1).as_deref(), Some("20241231"));
+        assert_eq!(shift_day("2024-02-28", 1).as_deref(), Some("20240229"));
+        assert_eq!(shift_day("2023-02-28", 1).as_deref(), Some("20230301"));
+        assert_eq!(shift_day("1900-02-28", 1).as_deref(), Some("19000301"));
+        assert_eq!(shift_day("2000-02-28", 1).as_deref(), Some("20000229"));
+        assert_eq!(shift_day("2024-05-01", 365).as_deref(), Some("20250501"));
+    }
+
+    #[test]
+    fn shift_day_rejects_malformed_dates() {
+        assert_eq!(shift_day("20240501", 1), None);
+        assert_eq!(shift_day("2024-05", 1), None);
+        assert_eq!(shift_day("2024-May-01", 1), None);
+    }
+
+    #[test]
+    fn days_from_civil_counts_from_the_unix_epoch() {
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(days_from_civil(1969, 12, 31), -1);
+        assert_eq!(days_from_civil(2000, 3, 1), 11017);
+        assert_eq!(days_from_civil(2024, 5, 1), 19844);
+        assert_eq!(days_from_civil(1600, 1, 1), -135140);
+    }
+
+    #[test]
+    fn civil_from_days_inverts_days_from_civil() {
+        (-800_000..800_000).step_by(97).for_each(|days| {
+            let (year, month, day) = civil_from_days(days);
+            assert!((1..=12).contains(&month));
+            assert!((1..=31).contains(&day));
+            assert_eq!(days_from_civil(year, month, day), days);
+        });
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(19844), (2024, 5, 1));
+    }
+
+    #[test]
+    fn parse_duration_adds_up_units() {
+        assert_eq!(parse_duration("PT1H30M"), Ok(90));
+        assert_eq!(parse_duration("P1DT2H"), Ok(1560));
+        assert_eq!(parse_duration("P1W"), Ok(10080));
+        assert_eq!(parse_duration("PT45S"), Ok(0));
+    }
+
+    #[test]
+    fn parse_duration_rejects_overflowing_and_malformed_durations() {
+        let invalid = |value: &str| Err(IcsError::InvalidDuration(value.to_string()));
+        assert_eq!(parse_duration("P4294967295W"), invalid("P4294967295W"));
+        assert_eq!(parse_duration("P2982617D"), invalid("P2982617D"));
+        assert_eq!(parse_duration("P2982616DT256M"), invalid("P2982616DT256M"));
+        assert_eq!(parse_duration("P99999999999D"), invalid("P99999999999D"));
+        assert_eq!(parse_duration("1H"), invalid("1H"));
+        assert_eq!(parse_duration("PT1X"), invalid("PT1X"));
+    }
+
+    #[test]
+    fn parse_date_time_rejects_out_of_range_times() {
+        assert_eq!(
+            parse_date_time("20240501T235900"),
+            Ok((String::from("2024-05-01"), Some(1439)))
+        );
+        ["20240501T240000", "20240501T096000", "20240501T9900Z"]
+            .iter()
+            .for_each(|value| {
+                assert_eq!(
+                    parse_date_time(value),
+                    Err(IcsError::InvalidDateTime(value.to_string()))
+                )
+            });
+    }
+
+    #[test]
+    fn long_durations_end_at_midnight() {
+        let source =
+            "BEGIN:VEVENT\r\nDTSTART:20240501T230000\r\nDURATION:P2982615D\r\nEND:VEVENT\r\n";
+        let events = parse_ics(source).unwrap();
+        assert_eq!(
+            (events[0].block.start_minute, events[0].block.end_minute),
+            (1380, 1440)
+        );
+    }
+
+    #[test]
+    fn write_ics_keeps_every_block_type() {
+        let event = parse_ics(CALENDAR).unwrap().remove(0);
+        let events: Vec<IcsEvent> = CalendarBlockType::ALL
+            .iter()
+            .map(|block_type| IcsEvent {
+                uid: Some(block_type.to_string()),
+                block: CalendarBlock {
+                    block_type: *block_type,
+                    ..event.block.clone()
+                },
+                ..event.clone()
+            })
+            .collect();
+
+        let written = write_ics(&events, "20240501T000000Z").unwrap();
+
+        assert!(written.contains("X-CAL-ENDER-TYPE:FOCUS\r\n"));
+        assert!(written.contains("X-CAL-ENDER-TYPE:TRAVEL\r\n"));
+        let block_types: Vec<CalendarBlockType> = parse_ics(&written)
+            .unwrap()
+            .into_iter()
+            .map(|event| event.block.block_type)
+            .collect();
+        assert_eq!(block_types, CalendarBlockType::ALL);
+    }
+
+    #[test]
+    fn parse_ics_falls_back_to_the_busy_status_for_unknown_types() {
+        let source = "BEGIN:VEVENT\r\nDTSTART:20240501T090000\r\nX-CAL-ENDER-TYPE:NAP\r\nX-MICROSOFT-CDO-BUSYSTATUS:OOF\r\nEND:VEVENT\r\n";
+
+        assert_eq!(
+            parse_ics(source).unwrap()[0].block.block_type,
+            CalendarBlockType::OutOfOffice
+        );
+    }
+
+    #[test]
+    fn attendees_survive_a_round_trip() {
+        let event = parse_ics(CALENDAR).unwrap().remove(0);
+        let attendees = vec![
+            Attendee {
+                name: Some(String::from("Doe; Jane: Ops")),
+                email: String::from("jane@example.com"),
+                rsvp: RsvpStatus::Accepted,
+            },
+            Attendee {
+                name: Some(String::from(
+                    "Ferris the crab, keeper of the borrow checker: ownership; lifetimes",
+                )),
+                email: String::from("ferris@example.com"),
+                rsvp: RsvpStatus::Tentative,
+            },
+            Attendee {
+                name: None,
+                email: String::from("room@example.com"),
+                rsvp: RsvpStatus::NeedsAction,
+            },
+        ];
+        let event = IcsEvent {
+            block: CalendarBlock {
+                metadata: BlockMetadata {
+                    attendees: attendees.clone(),
+                    ..BlockMetadata::default()
+                },
+                ..event.block
+            },
+            ..event
+        };
+
+        let written = write_ics(&[event], "20240501T000000Z").unwrap();
+
+        assert!(written.contains(
+            "ATTENDEE;CN=\"Doe; Jane: Ops\";PARTSTAT=ACCEPTED:mailto:jane@example.com\r\n"
+        ));
+        assert_eq!(
+            parse_ics(&written).unwrap()[0].block.metadata.attendees,
+            attendees
+        );
+    }
+
+    #[test]
+    fn parse_ics_reads_quoted_attendee_names() {
+        let source = "BEGIN:VEVENT\r\nDTSTART:20240501T090000\r\nATTENDEE;ROLE=REQ-PARTICIPANT;CN=\"Doe, Jane; Ops\":MAILTO:jane@example.com\r\nATTENDEE;PARTSTAT=DECLINED;CN=\"a:b\":mailto:ab@example.com\r\nEND:VEVENT\r\n";
+
+        let attendees = parse_ics(source)
+            .unwrap()
+            .remove(0)
+            .block
+            .metadata
+            .attendees;
+
+        assert_eq!(attendees[0].name.as_deref(), Some("Doe, Jane; Ops"));
+        assert_eq!(attendees[0].email, "jane@example.com");
+        assert_eq!(attendees[1].name.as_deref(), Some("a:b"));
+        assert_eq!(attendees[1].rsvp, RsvpStatus::Declined);
+    }
+}
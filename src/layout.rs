@@ -0,0 +1,266 @@
+use crate::prelude::*;
+
+/// A block positioned on the time grid, in pixels relative to the calendar column.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BlockLayout {
+    pub block: CalendarBlock,
+    pub stack_position: usize,
+    pub top: f64,
+    pub height: f64,
+    pub left: f64,
+    pub width: f64,
+}
+
+/// Horizontal mapping `x -> offset + scale * x` a block applies to its subtree,
+/// so children of a block squeezed into a lane stay inside that lane.
+#[derive(Debug, Clone, Copy)]
+struct LaneTransform {
+    offset: f64,
+    scale: f64,
+}
+
+impl LaneTransform {
+    const IDENTITY: LaneTransform = LaneTransform {
+        offset: 0.,
+        scale: 1.,
+    };
+
+    fn apply(&self, x: f64) -> f64 {
+        self.offset + self.scale * x
+    }
+}
+
+/// Positions every visible block, keeping the traversal order so later blocks draw on top.
+/// Partially overlapping siblings split the space right of their left edge into lanes.
+pub fn layout_blocks(
+    flattened_blocks: &[FlattenedCalendarBlock],
+    stacking_strategy: StackingStrategy,
+    config: &CalendarConfig,
+) -> Vec<BlockLayout> {
+    // transforms are computed for hidden blocks too, their children may still be visible
+    let mut transforms: HashMap<Uuid, LaneTransform> = HashMap::new();
+
+    flattened_blocks
+        .iter()
+        .map(|flattened_block| {
+            let block = &flattened_block.block;
+            let (raw_left, raw_width) =
+                stacking_strategy.get_horizontal_extent(config, flattened_block);
+
+            let parent_transform = flattened_block
+                .parent_id
+                .and_then(|parent_id| transforms.get(&parent_id).copied())
+                .unwrap_or(LaneTransform::IDENTITY);
+
+            let column_count = flattened_block.column_count.max(1) as f64;
+            let lane_width = (config.column_width - raw_left) / column_count;
+            let lane_left = raw_left + flattened_block.column as f64 * lane_width;
+
+            transforms.insert(
+                block.id,
+                LaneTransform {
+                    offset: parent_transform.apply(lane_left - raw_left / column_count),
+                    scale: parent_transform.scale / column_count,
+                },
+            );
+
+            BlockLayout {
+                block: block.clone(),
+                stack_position: flattened_block.stack_position,
+                top: config.minute_to_px(block.start_minute as f64),
+                height: config.duration_to_px(block.end_minute - block.start_minute),
+                left: parent_transform.apply(lane_left),
+                width: parent_transform.scale * raw_width / column_count,
+            }
+        })
+        .filter(|block_layout| {
+            config.is_visible(
+                block_layout.block.start_minute,
+                block_layout.block.end_minute,
+            )
+        })
+        .collect()
+}
+
+/// Lays out each set of blocks in its own equal share of the column, left to right.
+pub fn layout_side_by_side(
+    flattened_block_sets: &[Vec<FlattenedCalendarBlock>],
+    stacking_strategy: StackingStrategy,
+    config: &CalendarConfig,
+) -> Vec<BlockLayout> {
+    let set_config = CalendarConfig {
+        column_width: config.column_width / flattened_block_sets.len().max(1) as f64,
+        ..*config
+    };
+
+    flattened_block_sets
+        .iter()
+        .enumerate()
+        .flat_map(|(idx, flattened_blocks)| {
+            let offset = idx as f64 * set_config.column_width;
+            layout_blocks(flattened_blocks, stacking_strategy, &set_config)
+                .into_iter()
+                .map(move |block_layout| BlockLayout {
+                    left: block_layout.left + offset,
+                    ..block_layout
+                })
+        })
+        .collect()
+}
+
+/// A rectangle in pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Rect {
+    pub left: f64,
+    pub top: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl Rect {
+    pub fn right(&self) -> f64 {
+        self.left + self.width
+    }
+
+    pub fn bottom(&self) -> f64 {
+        self.top + self.height
+    }
+
+    /// The part of the rectangle inside `other`, empty when they don't meet.
+    pub fn intersect(&self, other: &Rect) -> Rect {
+        let left = self.left.max(other.left);
+        let top = self.top.max(other.top);
+        Rect {
+            left,
+            top,
+            width: (self.right().min(other.right()) - left).max(0.),
+            height: (self.bottom().min(other.bottom()) - top).max(0.),
+        }
+    }
+}
+
+/// Top left corner for an overlay of `width` x `height` next to `anchor`, kept
+/// inside `bounds`. It goes below the anchor, above it when there's no room
+/// below, and overlaps the anchor only when neither fits.
+pub fn place_overlay(
+    anchor: &Rect,
+    width: f64,
+    height: f64,
+    bounds: &Rect,
+    gap: f64,
+) -> (f64, f64) {
+    let below = anchor.bottom() + gap;
+    let above = anchor.top - gap - height;
+    let top = match (below + height <= bounds.bottom(), above >= bounds.top) {
+        (true, _) => below,
+        (false, true) => above,
+        (false, false) => (anchor.top + gap)
+            .min(bounds.bottom() - height)
+            .max(bounds.top),
+    };
+    let left = (anchor.left + gap)
+        .min(bounds.right() - width)
+        .max(bounds.left);
+
+    (left, top)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BOUNDS: Rect = Rect {
+        left: 0.,
+        top: 0.,
+        width: 500.,
+        height: 600.,
+    };
+
+    fn get_anchor(left: f64, top: f64, height: f64) -> Rect {
+        Rect {
+            left,
+            top,
+            width: 200.,
+            height,
+        }
+    }
+
+    #[test]
+    fn intersect_keeps_the_overlapping_part() {
+        let rect = Rect {
+            left: -50.,
+            top: 100.,
+            width: 200.,
+            height: 600.,
+        };
+
+        assert_eq!(
+            rect.intersect(&BOUNDS),
+            Rect {
+                left: 0.,
+                top: 100.,
+                width: 150.,
+                height: 500.,
+            }
+        );
+        assert_eq!(rect.intersect(&BOUNDS), BOUNDS.intersect(&rect));
+        assert_eq!(BOUNDS.intersect(&BOUNDS), BOUNDS);
+    }
+
+    #[test]
+    fn intersect_is_empty_when_rects_dont_meet() {
+        let rect = Rect {
+            left: 600.,
+            top: 700.,
+            width: 10.,
+            height: 10.,
+        };
+
+        let intersection = rect.intersect(&BOUNDS);
+        assert_eq!((intersection.width, intersection.height), (0., 0.));
+    }
+
+    #[test]
+    fn place_overlay_goes_below_the_anchor() {
+        let anchor = get_anchor(100., 100., 60.);
+
+        assert_eq!(place_overlay(&anchor, 220., 92., &BOUNDS, 6.), (106., 166.));
+    }
+
+    #[test]
+    fn place_overlay_goes_above_without_room_below() {
+        let anchor = get_anchor(100., 480., 60.);
+
+        assert_eq!(place_overlay(&anchor, 220., 92., &BOUNDS, 6.), (106., 382.));
+    }
+
+    #[test]
+    fn place_overlay_overlaps_the_anchor_without_room_around_it() {
+        let anchor = get_anchor(100., 50., 500.);
+
+        assert_eq!(place_overlay(&anchor, 220., 92., &BOUNDS, 6.), (106., 56.));
+
+        // an anchor reaching past the bounds keeps the overlay inside them
+        let anchor = get_anchor(100., -100., 800.);
+        assert_eq!(place_overlay(&anchor, 220., 92., &BOUNDS, 6.), (106., 0.));
+    }
+
+    #[test]
+    fn place_overlay_stays_inside_the_bounds() {
+        let anchor = get_anchor(400., 100., 60.);
+        assert_eq!(place_overlay(&anchor, 220., 92., &BOUNDS, 6.), (280., 166.));
+
+        let scrolled_bounds = Rect {
+            top: 200.,
+            ..BOUNDS
+        };
+        let anchor = get_anchor(-100., 150., 60.);
+        assert_eq!(
+            place_overlay(&anchor, 220., 92., &scrolled_bounds, 6.),
+            (0., 216.)
+        );
+
+        // wider than the bounds, the left edge stays visible
+        assert_eq!(place_overlay(&anchor, 600., 92., &BOUNDS, 6.), (0., 216.));
+    }
+}
@@ -0,0 +1,66 @@
+pub mod algo;
+#[cfg(feature = "caldav")]
+pub mod caldav;
+pub mod calendar_config;
+pub mod calendars;
+#[cfg(feature = "collab")]
+pub mod collab;
+#[cfg(feature = "collab")]
+pub mod collab_socket;
+pub mod components;
+pub mod dom;
+pub mod get_position_offsets;
+pub mod ics;
+pub mod layout;
+pub mod render;
+pub mod storage;
+pub mod store;
+pub mod theme;
+pub mod time_format;
+
+pub mod prelude {
+    pub use std::cmp::Ordering;
+    pub use std::collections::HashMap;
+    pub use std::collections::VecDeque;
+    pub use std::fmt::Display;
+
+    pub use cfg_block::cfg_block;
+    pub use core::fmt;
+    pub use dioxus::events::PointerEvent;
+    pub use dioxus::prelude::*;
+    pub use log::{error, info, Level};
+    pub use petgraph::dot::Dot;
+    pub use petgraph::graph::{Graph, NodeIndex};
+    pub use petgraph::visit::EdgeRef;
+    pub use serde::{Deserialize, Serialize};
+    pub use thiserror::Error;
+    pub use uuid::Uuid;
+
+    pub use crate::algo::block_metadata::*;
+    pub use crate::algo::calendar_block::*;
+    pub use crate::algo::calendar_tree::*;
+    pub use crate::algo::conflicts::*;
+    pub use crate::algo::drop_policy::*;
+    pub use crate::algo::editing::*;
+    pub use crate::algo::free_slots::*;
+    #[cfg(feature = "caldav")]
+    pub use crate::caldav::*;
+    pub use crate::calendar_config::*;
+    pub use crate::calendars::*;
+    #[cfg(feature = "collab")]
+    pub use crate::collab::*;
+    pub use crate::components::{
+        block_details, calendar, calendar_block, calendar_legend, empty_element, time_axis, tooltip,
+    };
+    pub use crate::get_position_offsets::*;
+    pub use crate::ics::*;
+    pub use crate::layout::*;
+    pub use crate::render::svg::*;
+    pub use crate::render::text::*;
+    pub use crate::storage::*;
+    pub use crate::store::file::*;
+    pub use crate::store::memory::*;
+    pub use crate::store::*;
+    pub use crate::theme::*;
+    pub use crate::time_format::*;
+}
@@ -1,36 +1,5 @@
-mod algo;
-mod components;
-mod get_position_offsets;
-
-mod prelude {
-    pub use std::cmp::Ordering;
-    pub use std::collections::HashMap;
-    pub use std::collections::VecDeque;
-    pub use std::fmt::Display;
-
-    pub use cfg_block::cfg_block;
-    pub use core::fmt;
-    pub use dioxus::events::MouseEvent;
-    pub use dioxus::prelude::*;
-    pub use log::{info, Level};
-    pub use petgraph::dot::Dot;
-    pub use petgraph::graph::{Graph, NodeIndex};
-    pub use petgraph::visit::EdgeRef;
-    pub use serde::Deserialize;
-    pub use thiserror::Error;
-    pub use uuid::Uuid;
-
-    pub use crate::algo::calendar_block::*;
-    pub use crate::algo::calendar_tree::*;
-    pub use crate::components::{calendar, calendar_block, empty_element};
-    pub use crate::get_position_offsets::*;
-
-    pub const MAX_COL_WIDTH: f64 = 500.0;
-    pub const BLOCK_STACK_PADDING: f64 = 10.0;
-    pub const BLOCK_TOP_OFFSET: u32 = 200;
-}
-
-use prelude::*;
+use cal_ender::dom;
+use cal_ender::prelude::*;
 
 cfg_block! {
     if #[cfg(feature = "console_log")] {
@@ -42,6 +11,129 @@ cfg_block! {
     }
 }
 
+cfg_block! {
+    if #[cfg(feature = "collab")] {
+        use cal_ender::collab_socket::CollabSocket;
+
+        /// Edits shared with everyone else on the page opened with
+        /// `?collab=<server url>`.
+        #[derive(Clone, Copy)]
+        struct Collab<'a> {
+            doc: &'a UseRef<CollabDoc>,
+            socket: &'a UseRef<Option<CollabSocket>>,
+        }
+
+        impl Collab<'_> {
+            fn send_drag(&self, drag: Option<DragPosition>) {
+                if let Some(socket) = self.socket.read().as_ref() {
+                    let replica = self.doc.read().replica();
+                    socket.send(&CollabMessage::Drag { replica, drag });
+                }
+            }
+        }
+
+        fn use_collab<'a>(
+            cx: &'a ScopeState,
+            calendar_blocks: &UseState<Vec<CalendarBlock>>,
+            remote_drags: &UseState<HashMap<Uuid, DragPosition>>,
+        ) -> Collab<'a> {
+            let doc = use_ref(cx, || CollabDoc::new(Uuid::new_v4()));
+            let socket = use_ref(cx, || {
+                let url = dom::get_query_param("collab")?;
+                let calendar_blocks = calendar_blocks.clone();
+                let doc = doc.clone();
+                let remote_drags = remote_drags.clone();
+                CollabSocket::connect(&url, move |socket, message| {
+                    receive_collab_message(socket, message, &calendar_blocks, &doc, &remote_drags)
+                })
+            });
+
+            // however the blocks were edited, others get the same edits
+            use_effect(cx, (calendar_blocks.get(),), {
+                let doc = doc.clone();
+                let socket = socket.clone();
+                |(calendar_blocks,)| async move {
+                    if let Some(socket) = socket.read().as_ref() {
+                        send_local_edits(socket, &mut doc.write_silent(), &calendar_blocks);
+                    }
+                }
+            });
+
+            Collab { doc, socket }
+        }
+
+        /// Nothing is shared before the server's operations arrive, the blocks
+        /// are then either seeded from this page or replaced by the shared ones.
+        fn send_local_edits(
+            socket: &CollabSocket,
+            collab_doc: &mut CollabDoc,
+            calendar_blocks: &[CalendarBlock],
+        ) {
+            if collab_doc.is_synced() {
+                collab_doc
+                    .local_edits(calendar_blocks)
+                    .into_iter()
+                    .for_each(|op| socket.send(&CollabMessage::Op { op }));
+            }
+        }
+
+        fn receive_collab_message(
+            socket: &CollabSocket,
+            message: CollabMessage,
+            calendar_blocks: &UseState<Vec<CalendarBlock>>,
+            collab_doc: &UseRef<CollabDoc>,
+            remote_drags: &UseState<HashMap<Uuid, DragPosition>>,
+        ) {
+            let mut collab_doc = collab_doc.write_silent();
+            // edits not yet sent would be overwritten by the shared blocks
+            send_local_edits(socket, &mut collab_doc, &calendar_blocks.current());
+
+            match message {
+                // the relay asks one client to share its blocks, everyone else takes the shared ones
+                CollabMessage::Sync { ops, seed: true } => {
+                    collab_doc.sync(ops);
+                    send_local_edits(socket, &mut collab_doc, &calendar_blocks.current());
+                }
+                CollabMessage::Sync { ops, .. } => {
+                    collab_doc.sync(ops);
+                    calendar_blocks.set(collab_doc.blocks());
+                }
+                CollabMessage::Op { op } => {
+                    if collab_doc.apply(op) {
+                        calendar_blocks.set(collab_doc.blocks());
+                    }
+                }
+                CollabMessage::Drag { replica, drag } => {
+                    let mut drags = (*remote_drags.current()).clone();
+                    match drag {
+                        Some(drag) => drags.insert(replica, drag),
+                        None => drags.remove(&replica),
+                    };
+                    remote_drags.set(drags);
+                }
+            }
+        }
+    } else {
+        #[derive(Clone, Copy)]
+        struct Collab;
+
+        impl Collab {
+            fn send_drag(&self, _drag: Option<DragPosition>) {}
+        }
+
+        fn use_collab(
+            _cx: &ScopeState,
+            _calendar_blocks: &UseState<Vec<CalendarBlock>>,
+            _remote_drags: &UseState<HashMap<Uuid, DragPosition>>,
+        ) -> Collab {
+            Collab
+        }
+    }
+}
+
+const PREFERS_DARK_QUERY: &str = "(prefers-color-scheme: dark)";
+const PREFERS_MORE_CONTRAST_QUERY: &str = "(prefers-contrast: more)";
+
 fn main() {
     init_log();
     dioxus::web::launch(app);
@@ -49,89 +141,228 @@ fn main() {
 
 fn app(cx: Scope) -> Element {
     let calendar_blocks = use_state(&cx, || {
-        let mut calendar_blocks = vec![
-            CalendarBlock {
-                id: Uuid::new_v4(),
-                start_minute: 530 - BLOCK_TOP_OFFSET,
-                end_minute: 830 - BLOCK_TOP_OFFSET,
-                block_type: CalendarBlockType::Available,
-                subtree_depth: 0,
-                label: String::from("Available"),
-            },
-            CalendarBlock {
-                id: Uuid::new_v4(),
-                start_minute: 550 - BLOCK_TOP_OFFSET,
-                end_minute: 590 - BLOCK_TOP_OFFSET,
-                block_type: CalendarBlockType::Busy,
-                subtree_depth: 0,
-                label: String::from("Shower"),
-            },
-            CalendarBlock {
-                id: Uuid::new_v4(),
-                start_minute: 550 - BLOCK_TOP_OFFSET,
-                end_minute: 580 - BLOCK_TOP_OFFSET,
-                block_type: CalendarBlockType::Busy,
-                subtree_depth: 0,
-                label: String::from("Shower Thoughts"),
-            },
-            CalendarBlock {
-                id: Uuid::new_v4(),
-                start_minute: 605 - BLOCK_TOP_OFFSET,
-                end_minute: 665 - BLOCK_TOP_OFFSET,
-                block_type: CalendarBlockType::Busy,
-                subtree_depth: 0,
-                label: String::from("Coffee"),
-            },
-            CalendarBlock {
-                id: Uuid::new_v4(),
-                start_minute: 605 - BLOCK_TOP_OFFSET,
-                end_minute: 630 - BLOCK_TOP_OFFSET,
-                block_type: CalendarBlockType::Busy,
-                subtree_depth: 0,
-                label: String::from("Brew"),
-            },
-            CalendarBlock {
-                id: Uuid::new_v4(),
-                start_minute: 635 - BLOCK_TOP_OFFSET,
-                end_minute: 710 - BLOCK_TOP_OFFSET,
-                block_type: CalendarBlockType::Busy,
-                subtree_depth: 0,
-                label: String::from("Contemplation"),
-            },
-            CalendarBlock {
-                id: Uuid::new_v4(),
-                start_minute: 650 - BLOCK_TOP_OFFSET,
-                end_minute: 830 - BLOCK_TOP_OFFSET,
-                block_type: CalendarBlockType::Busy,
-                subtree_depth: 0,
-                label: String::from("Code"),
-            },
-        ];
-
-        calendar_blocks.sort_by(|a, b| {
-            if a.start_minute < b.start_minute
-                || a.start_minute == b.start_minute && a.end_minute >= b.end_minute
-            {
-                Ordering::Less
-            } else {
-                Ordering::Greater
+        match dom::get_local_storage_item(STORAGE_KEY).map(|json| deserialize_blocks(&json)) {
+            Some(Ok(mut calendar_blocks)) => {
+                calendar_blocks.sort_by_key(CalendarBlock::sort_key);
+                calendar_blocks
+            }
+            Some(Err(e)) => {
+                info!("Ignoring stored calendar: {e}");
+                get_demo_blocks()
+            }
+            None => get_demo_blocks(),
+        }
+    });
+
+    use_effect(
+        &cx,
+        (calendar_blocks.get(),),
+        |(calendar_blocks,)| async move {
+            dom::set_local_storage_item(STORAGE_KEY, &serialize_blocks(&calendar_blocks));
+        },
+    );
+
+    let remote_drags = use_state(&cx, HashMap::<Uuid, DragPosition>::new);
+    let collab = use_collab(&cx, calendar_blocks, remote_drags);
+
+    let drop_policy = use_state(&cx, || DropPolicy::ForbidBusyOverlap);
+    let calendar_config = use_state(&cx, CalendarConfig::default);
+    let time_format = use_state(&cx, || {
+        dom::get_locale()
+            .map(|locale| TimeFormat::for_locale(&locale))
+            .unwrap_or_default()
+    });
+
+    let theme = use_state(&cx, || {
+        dom::get_local_storage_item(THEME_STORAGE_KEY)
+            .and_then(|json| serde_json::from_str::<Theme>(&json).ok())
+            .unwrap_or_default()
+    });
+    let custom_palette = use_state(&cx, || {
+        match dom::get_local_storage_item(PALETTE_STORAGE_KEY)
+            .map(|json| serde_json::from_str::<Palette>(&json))
+        {
+            Some(Ok(palette)) => Some(palette),
+            Some(Err(e)) => {
+                info!("Ignoring stored palette: {e}");
+                None
             }
-        });
+            None => None,
+        }
+    });
+    let system_color_scheme = use_state(&cx, get_system_color_scheme);
+    // listening once, for as long as the page is open
+    use_ref(&cx, || {
+        [PREFERS_DARK_QUERY, PREFERS_MORE_CONTRAST_QUERY]
+            .iter()
+            .for_each(|query| {
+                let system_color_scheme = system_color_scheme.clone();
+                dom::on_media_change(query, move || {
+                    system_color_scheme.set(get_system_color_scheme())
+                });
+            });
+    });
+    let palette = theme.get_palette(*system_color_scheme.get(), custom_palette.get().as_ref());
+    let tooltip_variant = match palette.is_dark {
+        true => tooltip::TooltipVariant::Dark,
+        false => tooltip::TooltipVariant::Light,
+    };
 
-        calendar_blocks
+    use_effect(&cx, (theme.get(),), |(theme,)| async move {
+        if let Ok(json) = serde_json::to_string(&theme) {
+            dom::set_local_storage_item(THEME_STORAGE_KEY, &json);
+        }
+    });
+    use_effect(&cx, (&palette,), |(palette,)| async move {
+        dom::set_css_variables(&palette.to_css_variables());
     });
 
     cx.render(rsx! {
         div {
             class: "App",
+            button {
+                class: "btn",
+                onclick: move |_| drop_policy.set(drop_policy.get().next()),
+                "Drop Policy: {drop_policy}"
+            }
+            button {
+                class: "btn",
+                onclick: move |_| {
+                    let next_config = match *calendar_config.get() {
+                        config if config == CalendarConfig::compact() => CalendarConfig::detailed(),
+                        config if config == CalendarConfig::detailed() => CalendarConfig::default(),
+                        _ => CalendarConfig::compact(),
+                    };
+                    calendar_config.set(next_config);
+                },
+                "Switch View"
+            }
+            button {
+                class: "btn",
+                onclick: move |_| time_format.set(time_format.get().next()),
+                "Switch Clock"
+            }
+            button {
+                class: "btn",
+                onclick: move |_| theme.set(theme.get().next(custom_palette.is_some())),
+                "Theme: {theme}"
+            }
             rsx!(
                 div {
                     class: "flex flex-row",
                     rsx!(calendar::Calendar {
                         calendar_blocks: calendar_blocks,
+                        drop_policy: *drop_policy.get(),
+                        config: CalendarConfig {
+                            time_format: *time_format.get(),
+                            ..*calendar_config.get()
+                        },
+                        remote_drags: remote_drags.values().copied().collect(),
+                        tooltip_variant: tooltip_variant,
+                        ondrag: move |drag: Option<DragPosition>| collab.send_drag(drag),
                     })
                 })
         }
 
     })
 }
+
+fn get_system_color_scheme() -> SystemColorScheme {
+    SystemColorScheme {
+        prefers_dark: dom::matches_media(PREFERS_DARK_QUERY),
+        prefers_more_contrast: dom::matches_media(PREFERS_MORE_CONTRAST_QUERY),
+    }
+}
+
+fn get_demo_blocks() -> Vec<CalendarBlock> {
+    let mut calendar_blocks = vec![
+        CalendarBlock {
+            id: Uuid::new_v4(),
+            start_minute: 330,
+            end_minute: 630,
+            block_type: CalendarBlockType::Available,
+            subtree_depth: 0,
+            label: String::from("Available"),
+            calendar_id: String::from("personal"),
+            metadata: BlockMetadata::default(),
+        },
+        CalendarBlock {
+            id: Uuid::new_v4(),
+            start_minute: 350,
+            end_minute: 390,
+            block_type: CalendarBlockType::Busy,
+            subtree_depth: 0,
+            label: String::from("Shower"),
+            calendar_id: String::from("personal"),
+            metadata: BlockMetadata::default(),
+        },
+        CalendarBlock {
+            id: Uuid::new_v4(),
+            start_minute: 350,
+            end_minute: 380,
+            block_type: CalendarBlockType::Busy,
+            subtree_depth: 0,
+            label: String::from("Shower Thoughts"),
+            calendar_id: String::from("personal"),
+            metadata: BlockMetadata::default(),
+        },
+        CalendarBlock {
+            id: Uuid::new_v4(),
+            start_minute: 405,
+            end_minute: 465,
+            block_type: CalendarBlockType::Busy,
+            subtree_depth: 0,
+            label: String::from("Coffee"),
+            calendar_id: String::from("personal"),
+            metadata: BlockMetadata {
+                location: Some(String::from("Kitchen")),
+                ..BlockMetadata::default()
+            },
+        },
+        CalendarBlock {
+            id: Uuid::new_v4(),
+            start_minute: 405,
+            end_minute: 430,
+            block_type: CalendarBlockType::Busy,
+            subtree_depth: 0,
+            label: String::from("Brew"),
+            calendar_id: String::from("personal"),
+            metadata: BlockMetadata::default(),
+        },
+        CalendarBlock {
+            id: Uuid::new_v4(),
+            start_minute: 435,
+            end_minute: 510,
+            block_type: CalendarBlockType::Busy,
+            subtree_depth: 0,
+            label: String::from("Contemplation"),
+            calendar_id: String::from("work"),
+            metadata: BlockMetadata::default(),
+        },
+        CalendarBlock {
+            id: Uuid::new_v4(),
+            start_minute: 450,
+            end_minute: 630,
+            block_type: CalendarBlockType::Focus,
+            subtree_depth: 0,
+            label: String::from("Code"),
+            calendar_id: String::from("work"),
+            metadata: BlockMetadata {
+                description: Some(String::from("Pair on the stacking layout.")),
+                attendees: vec![Attendee {
+                    name: Some(String::from("Ferris")),
+                    email: String::from("ferris@example.com"),
+                    rsvp: RsvpStatus::Accepted,
+                }],
+                url: Some(String::from(
+                    "https://tauseefk.github.io/posts/calendar-tetris-pt-1",
+                )),
+                ..BlockMetadata::default()
+            },
+        },
+    ];
+
+    calendar_blocks.sort_by_key(CalendarBlock::sort_key);
+
+    calendar_blocks
+}
@@ -0,0 +1,208 @@
+use crate::prelude::*;
+
+const GUTTER_WIDTH: f64 = 52.;
+const FONT_FAMILY: &str = "Verdana, Geneva, Tahoma, sans-serif";
+
+/// Lays out `blocks` with the given strategy and draws them as a standalone SVG
+/// document in the palette's colors.
+pub fn render_svg(
+    blocks: &[CalendarBlock],
+    stacking_strategy: StackingStrategy,
+    config: &CalendarConfig,
+    palette: &Palette,
+) -> Result<String, CalendarTreeError> {
+    let flattened_blocks = CalendarBlockTree::from_blocks(blocks)?.traverse();
+    let layouts = layout_blocks(&flattened_blocks, stacking_strategy, config);
+    Ok(render_svg_layouts(&layouts, config, palette))
+}
+
+pub fn render_svg_layouts(
+    layouts: &[BlockLayout],
+    config: &CalendarConfig,
+    palette: &Palette,
+) -> String {
+    let width = GUTTER_WIDTH + config.column_width;
+    let height = config.height();
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" viewBox=\"0 0 {width} {height}\" font-family=\"{FONT_FAMILY}\" font-size=\"12\">\n"
+    );
+
+    svg.push_str(&format!(
+        "  <rect x=\"{GUTTER_WIDTH}\" y=\"0\" width=\"{}\" height=\"{height}\" fill=\"{}\"/>\n",
+        config.column_width,
+        escape_xml(&palette.surface)
+    ));
+    push_time_axis(&mut svg, config, palette);
+
+    layouts
+        .iter()
+        .enumerate()
+        .for_each(|(idx, layout)| push_block(&mut svg, idx, layout, config, palette));
+
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn push_time_axis(svg: &mut String, config: &CalendarConfig, palette: &Palette) {
+    let right = GUTTER_WIDTH + config.column_width;
+    let gridline_color = escape_xml(&palette.gridline);
+    let label_color = escape_xml(&palette.muted_text);
+
+    (config.visible_start_minute()..config.visible_end_minute())
+        .step_by(30)
+        .for_each(|minute| {
+            let y = config.minute_to_px(minute as f64);
+            match minute % 60 {
+                0 => {
+                    svg.push_str(&format!(
+                        "  <line x1=\"{GUTTER_WIDTH}\" y1=\"{y}\" x2=\"{right}\" y2=\"{y}\" stroke=\"{gridline_color}\"/>\n"
+                    ));
+                    svg.push_str(&format!(
+                        "  <text x=\"{}\" y=\"{y}\" font-size=\"10\" text-anchor=\"end\" dominant-baseline=\"middle\" fill=\"{label_color}\">{}</text>\n",
+                        GUTTER_WIDTH - 8.,
+                        config.time_format.format_time(minute)
+                    ));
+                }
+                _ => svg.push_str(&format!(
+                    "  <line x1=\"{GUTTER_WIDTH}\" y1=\"{y}\" x2=\"{right}\" y2=\"{y}\" stroke=\"{gridline_color}\" stroke-dasharray=\"4 4\"/>\n"
+                )),
+            }
+        });
+}
+
+fn push_block(
+    svg: &mut String,
+    idx: usize,
+    layout: &BlockLayout,
+    config: &CalendarConfig,
+    palette: &Palette,
+) {
+    let block_colors = palette.get_block_colors(layout.block.block_type);
+    let fill_color = escape_xml(
+        layout
+            .block
+            .metadata
+            .color
+            .as_deref()
+            .unwrap_or(&block_colors.fill),
+    );
+    let border_color = escape_xml(&block_colors.border);
+    let text_color = escape_xml(&block_colors.text);
+    let x = GUTTER_WIDTH + layout.left;
+    let label = format!(
+        "{}, {}",
+        layout.block.label,
+        config
+            .time_format
+            .format_range(layout.block.start_minute, layout.block.end_minute)
+    );
+
+    svg.push_str(&format!(
+        "  <clipPath id=\"block-{idx}\"><rect x=\"{x}\" y=\"{}\" width=\"{}\" height=\"{}\"/></clipPath>\n",
+        layout.top, layout.width, layout.height
+    ));
+    svg.push_str(&format!(
+        "  <rect x=\"{x}\" y=\"{}\" width=\"{}\" height=\"{}\" rx=\"4\" fill=\"{fill_color}\" stroke=\"{border_color}\"/>\n",
+        layout.top, layout.width, layout.height
+    ));
+    svg.push_str(&format!(
+        "  <text x=\"{}\" y=\"{}\" fill=\"{text_color}\" clip-path=\"url(#block-{idx})\">{}</text>\n",
+        x + 6.,
+        layout.top + 16.,
+        escape_xml(&label)
+    ));
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_block(
+        label: &str,
+        start_minute: u32,
+        end_minute: u32,
+        block_type: CalendarBlockType,
+    ) -> CalendarBlock {
+        CalendarBlock {
+            id: Uuid::new_v4(),
+            start_minute,
+            end_minute,
+            block_type,
+            subtree_depth: 0,
+            label: label.to_string(),
+            calendar_id: DEFAULT_CALENDAR_ID.to_string(),
+            metadata: BlockMetadata::default(),
+        }
+    }
+
+    #[test]
+    fn render_svg_layouts_draws_the_axis_and_blocks() {
+        let config = CalendarConfig {
+            start_hour: 9,
+            end_hour: 11,
+            column_width: 240.,
+            ..CalendarConfig::default()
+        };
+        let blocks = vec![
+            get_block("Planning", 540, 630, CalendarBlockType::Busy),
+            get_block("Standup", 555, 585, CalendarBlockType::Focus),
+            get_block("Q&A", 570, 600, CalendarBlockType::Tentative),
+        ];
+        let flattened_blocks = CalendarBlockTree::from_blocks(&blocks).unwrap().traverse();
+        let layouts = layout_blocks(&flattened_blocks, StackingStrategy::StackPosition, &config);
+
+        assert_eq!(
+            render_svg_layouts(&layouts, &config, &Palette::light()),
+            r##"<svg xmlns="http://www.w3.org/2000/svg" width="292" height="120" viewBox="0 0 292 120" font-family="Verdana, Geneva, Tahoma, sans-serif" font-size="12">
+  <rect x="52" y="0" width="240" height="120" fill="#FCF9F7"/>
+  <line x1="52" y1="0" x2="292" y2="0" stroke="#DADBF1"/>
+  <text x="44" y="0" font-size="10" text-anchor="end" dominant-baseline="middle" fill="#494949">09:00</text>
+  <line x1="52" y1="30" x2="292" y2="30" stroke="#DADBF1" stroke-dasharray="4 4"/>
+  <line x1="52" y1="60" x2="292" y2="60" stroke="#DADBF1"/>
+  <text x="44" y="60" font-size="10" text-anchor="end" dominant-baseline="middle" fill="#494949">10:00</text>
+  <line x1="52" y1="90" x2="292" y2="90" stroke="#DADBF1" stroke-dasharray="4 4"/>
+  <clipPath id="block-0"><rect x="52" y="0" width="240" height="90"/></clipPath>
+  <rect x="52" y="0" width="240" height="90" rx="4" fill="#FFFFFF" stroke="#494949"/>
+  <text x="58" y="16" fill="#000000" clip-path="url(#block-0)">Planning, 09:00–10:30</text>
+  <clipPath id="block-1"><rect x="72" y="15" width="100" height="30"/></clipPath>
+  <rect x="72" y="15" width="100" height="30" rx="4" fill="#E4E8FB" stroke="#4C5FD5"/>
+  <text x="78" y="31" fill="#000000" clip-path="url(#block-1)">Standup, 09:15–09:45</text>
+  <clipPath id="block-2"><rect x="182" y="30" width="100" height="30"/></clipPath>
+  <rect x="182" y="30" width="100" height="30" rx="4" fill="#FFFFFF" stroke="#494949"/>
+  <text x="188" y="46" fill="#494949" clip-path="url(#block-2)">Q&amp;A, 09:30–10:00</text>
+</svg>
+"##
+        );
+    }
+
+    #[test]
+    fn render_svg_layouts_prefers_the_block_color() {
+        let config = CalendarConfig {
+            start_hour: 9,
+            end_hour: 10,
+            ..CalendarConfig::default()
+        };
+        let block = CalendarBlock {
+            metadata: BlockMetadata {
+                color: Some(String::from("#FF0000\"")),
+                ..BlockMetadata::default()
+            },
+            ..get_block("Custom", 540, 600, CalendarBlockType::Busy)
+        };
+        let layouts = layout_blocks(
+            &CalendarBlockTree::from_blocks(&[block]).unwrap().traverse(),
+            StackingStrategy::StackPosition,
+            &config,
+        );
+
+        let svg = render_svg_layouts(&layouts, &config, &Palette::light());
+        assert!(svg.contains("fill=\"#FF0000&quot;\" stroke=\"#494949\""));
+    }
+}
@@ -0,0 +1,201 @@
+use crate::prelude::*;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextRenderOptions {
+    /// Width of the calendar column in characters, excluding the time axis.
+    pub columns: usize,
+    /// Minutes covered by a single row; defaults to the snap interval when `None`.
+    pub minutes_per_row: Option<u32>,
+}
+
+impl Default for TextRenderOptions {
+    fn default() -> Self {
+        Self {
+            columns: 60,
+            minutes_per_row: None,
+        }
+    }
+}
+
+/// Draws laid out blocks as a fixed-width grid, one row per time slice:
+///
+/// ```text
+/// 05:30 | +Available------------------------------------------------+
+/// 05:45 | |                                                         |
+/// 06:00 | | +Shower------------------+                              |
+/// ```
+///
+/// Blocks are drawn in order, so nested blocks cover their parents like in the DOM.
+pub fn render_text(
+    layouts: &[BlockLayout],
+    config: &CalendarConfig,
+    options: &TextRenderOptions,
+) -> String {
+    let minutes_per_row = options
+        .minutes_per_row
+        .unwrap_or(config.snap_minutes)
+        .max(1);
+    let visible_start = config.visible_start_minute();
+    let visible_end = config.visible_end_minute();
+    let row_count = (visible_end - visible_start).div_ceil(minutes_per_row);
+
+    let mut grid = vec![vec![' '; options.columns]; row_count as usize];
+
+    layouts
+        .iter()
+        .for_each(|layout| draw_block(&mut grid, layout, config, minutes_per_row));
+
+    grid.iter()
+        .enumerate()
+        .map(|(row, cells)| {
+            let time = config
+                .time_format
+                .format_time(visible_start + row as u32 * minutes_per_row);
+            let cells: String = cells.iter().collect();
+            format!("{time:>8} | {cells}").trim_end().to_string()
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn draw_block(
+    grid: &mut [Vec<char>],
+    layout: &BlockLayout,
+    config: &CalendarConfig,
+    minutes_per_row: u32,
+) {
+    let columns = grid.first().map(|row| row.len()).unwrap_or(0);
+    if columns < 2 {
+        return;
+    }
+
+    let to_column = |px: f64| {
+        let column = (px / config.column_width * columns as f64).round();
+        column.clamp(0., columns as f64) as usize
+    };
+    let first_column = to_column(layout.left).min(columns - 2);
+    let last_column = to_column(layout.left + layout.width)
+        .saturating_sub(1)
+        .max(first_column + 1);
+
+    let visible_start = config.visible_start_minute();
+    let first_row =
+        (layout.block.start_minute.max(visible_start) - visible_start) / minutes_per_row;
+    let end_row = layout
+        .block
+        .end_minute
+        .saturating_sub(visible_start)
+        .div_ceil(minutes_per_row);
+    let end_row = (end_row as usize).min(grid.len());
+    let first_row = first_row as usize;
+
+    for (row, cells) in grid.iter_mut().enumerate().take(end_row).skip(first_row) {
+        let is_edge = row == first_row || row == end_row - 1;
+        for (column, cell) in cells
+            .iter_mut()
+            .enumerate()
+            .take(last_column + 1)
+            .skip(first_column)
+        {
+            let is_side = column == first_column || column == last_column;
+            *cell = match (is_edge, is_side) {
+                (true, true) => '+',
+                (true, false) => '-',
+                (false, true) => '|',
+                (false, false) => ' ',
+            };
+        }
+    }
+
+    if first_row < end_row {
+        layout
+            .block
+            .label
+            .chars()
+            .take(last_column - first_column - 1)
+            .enumerate()
+            .for_each(|(offset, c)| grid[first_row][first_column + 1 + offset] = c);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_block(label: &str, start_minute: u32, end_minute: u32) -> CalendarBlock {
+        CalendarBlock {
+            id: Uuid::new_v4(),
+            start_minute,
+            end_minute,
+            block_type: CalendarBlockType::Busy,
+            subtree_depth: 0,
+            label: label.to_string(),
+            calendar_id: DEFAULT_CALENDAR_ID.to_string(),
+            metadata: BlockMetadata::default(),
+        }
+    }
+
+    fn render(stacking_strategy: StackingStrategy, options: &TextRenderOptions) -> String {
+        let config = CalendarConfig {
+            start_hour: 9,
+            end_hour: 11,
+            column_width: 240.,
+            ..CalendarConfig::default()
+        };
+        let blocks = vec![
+            get_block("Planning", 540, 630),
+            get_block("Standup", 555, 585),
+            get_block("Q&A", 570, 600),
+        ];
+        let flattened_blocks = CalendarBlockTree::from_blocks(&blocks).unwrap().traverse();
+        let layouts = layout_blocks(&flattened_blocks, stacking_strategy, &config);
+        render_text(&layouts, &config, options)
+    }
+
+    #[test]
+    fn render_text_draws_stacked_blocks() {
+        let options = TextRenderOptions {
+            columns: 24,
+            minutes_per_row: None,
+        };
+
+        assert_eq!(
+            render(StackingStrategy::StackPosition, &options),
+            "   09:00 | +Planning--------------+
+   09:15 | | +Standup-+           |
+   09:30 | | +--------+ +Q&A-----+|
+   09:45 | |            +--------+|
+   10:00 | |                      |
+   10:15 | +----------------------+
+   10:30 |
+   10:45 |"
+        );
+        assert_eq!(
+            render(StackingStrategy::SubtreeDepth, &options),
+            "   09:00 | +Planning------------+
+   09:15 | |           +Stan+   |
+   09:30 | |           +----++Q&A-+
+   09:45 | |                 +----+
+   10:00 | |                    |
+   10:15 | +--------------------+
+   10:30 |
+   10:45 |"
+        );
+    }
+
+    #[test]
+    fn render_text_draws_later_blocks_over_earlier_ones_in_long_rows() {
+        let options = TextRenderOptions {
+            columns: 12,
+            minutes_per_row: Some(30),
+        };
+
+        assert_eq!(
+            render(StackingStrategy::StackPosition, &options),
+            "   09:00 | ++Sta+ing--+
+   09:30 | |+---+ +Q&A+
+   10:00 | +----------+
+   10:30 |"
+        );
+    }
+}
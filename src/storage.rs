@@ -0,0 +1,131 @@
+use crate::prelude::*;
+use serde_json::Value;
+
+/// Key the calendar state is saved under in the browser's localStorage.
+pub const STORAGE_KEY: &str = "cal-ender.calendar";
+
+/// Schema version written by `serialize_blocks`. Bump it together with a new
+/// entry in `MIGRATIONS` whenever the stored shape changes.
+pub const SCHEMA_VERSION: u64 = 1;
+
+/// `MIGRATIONS[n - 1]` upgrades a version `n` document to version `n + 1`.
+/// Version 1 is the first stored shape, nothing was saved before it.
+const MIGRATIONS: [fn(Value) -> Value; 0] = [];
+
+#[derive(Debug, Error)]
+pub enum StorageError {
+    #[error("stored calendar is not valid JSON: {0}")]
+    InvalidJson(#[from] serde_json::Error),
+    #[error("stored calendar has no schema version")]
+    MissingVersion,
+    #[error("stored calendar has schema version {0}, only 1 to {SCHEMA_VERSION} are known")]
+    UnsupportedVersion(u64),
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredCalendar {
+    version: u64,
+    blocks: Vec<CalendarBlock>,
+}
+
+fn get_version(document: &Value) -> Result<u64, StorageError> {
+    document
+        .get("version")
+        .and_then(Value::as_u64)
+        .ok_or(StorageError::MissingVersion)
+}
+
+pub fn serialize_blocks(blocks: &[CalendarBlock]) -> String {
+    let stored_calendar = StoredCalendar {
+        version: SCHEMA_VERSION,
+        blocks: blocks.to_vec(),
+    };
+    serde_json::to_string(&stored_calendar).expect("calendar blocks always serialize")
+}
+
+/// Reads blocks saved by any schema version, migrating them to the current one.
+pub fn deserialize_blocks(json: &str) -> Result<Vec<CalendarBlock>, StorageError> {
+    let mut document: Value = serde_json::from_str(json)?;
+    let mut version = get_version(&document)?;
+
+    if version == 0 || version > SCHEMA_VERSION {
+        return Err(StorageError::UnsupportedVersion(version));
+    }
+    while version < SCHEMA_VERSION {
+        document = MIGRATIONS[version as usize - 1](document);
+        version = get_version(&document)?;
+    }
+
+    let stored_calendar: StoredCalendar = serde_json::from_value(document)?;
+    Ok(stored_calendar.blocks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Saved before blocks had a calendar or metadata.
+    const V1_BLOCKS_ONLY: &str = r#"{"version":1,"blocks":[
+        {"id":"0b9c5a1e-4b4a-4c38-9b8e-6c1f1d1c2a01","start_minute":330,"end_minute":630,"block_type":"Available","label":"Available"},
+        {"id":"0b9c5a1e-4b4a-4c38-9b8e-6c1f1d1c2a02","start_minute":350,"end_minute":390,"block_type":"Busy","label":"Shower"}
+    ]}"#;
+
+    /// Saved once blocks were tagged with a calendar.
+    const V1_WITH_CALENDARS: &str = r#"{"version":1,"blocks":[
+        {"id":"0b9c5a1e-4b4a-4c38-9b8e-6c1f1d1c2a02","start_minute":350,"end_minute":390,"block_type":"Busy","label":"Shower","calendar_id":"personal"}
+    ]}"#;
+
+    /// Saved once blocks carried metadata and the newer block types.
+    const V1_WITH_METADATA: &str = r#"{"version":1,"blocks":[
+        {"id":"0b9c5a1e-4b4a-4c38-9b8e-6c1f1d1c2a03","start_minute":450,"end_minute":630,"block_type":"Focus","label":"Code","calendar_id":"work","metadata":{"description":"Pair on the stacking layout.","attendees":[{"name":"Ferris","email":"ferris@example.com","rsvp":"accepted"}]}}
+    ]}"#;
+
+    #[test]
+    fn deserialize_blocks_reads_blocks_saved_before_calendars() {
+        let blocks = deserialize_blocks(V1_BLOCKS_ONLY).unwrap();
+
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].block_type, CalendarBlockType::Available);
+        assert_eq!(blocks[1].label, "Shower");
+        assert!(blocks
+            .iter()
+            .all(|block| block.calendar_id == DEFAULT_CALENDAR_ID
+                && block.metadata == BlockMetadata::default()));
+    }
+
+    #[test]
+    fn deserialize_blocks_reads_blocks_saved_with_calendars() {
+        let blocks = deserialize_blocks(V1_WITH_CALENDARS).unwrap();
+
+        assert_eq!(blocks[0].calendar_id, "personal");
+        assert_eq!(blocks[0].metadata, BlockMetadata::default());
+    }
+
+    #[test]
+    fn deserialize_blocks_reads_blocks_saved_with_metadata() {
+        let blocks = deserialize_blocks(V1_WITH_METADATA).unwrap();
+
+        assert_eq!(blocks[0].block_type, CalendarBlockType::Focus);
+        assert_eq!(blocks[0].metadata.attendees[0].email, "ferris@example.com");
+        assert_eq!(
+            deserialize_blocks(&serialize_blocks(&blocks)).unwrap(),
+            blocks
+        );
+    }
+
+    #[test]
+    fn deserialize_blocks_rejects_unknown_versions() {
+        assert!(matches!(
+            deserialize_blocks("[]"),
+            Err(StorageError::MissingVersion)
+        ));
+        assert!(matches!(
+            deserialize_blocks(r#"{"version":0,"blocks":[]}"#),
+            Err(StorageError::UnsupportedVersion(0))
+        ));
+        assert!(matches!(
+            deserialize_blocks(r#"{"version":2,"blocks":[]}"#),
+            Err(StorageError::UnsupportedVersion(2))
+        ));
+    }
+}
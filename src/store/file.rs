@@ -0,0 +1,214 @@
+use crate::prelude::*;
+use std::path::{Path, PathBuf};
+
+/// Keeps blocks in a JSON file, rewriting it after every change.
+/// Meant for the native CLI, where one process owns the file at a time.
+#[derive(Debug)]
+pub struct FileStore {
+    path: PathBuf,
+    memory: InMemoryStore,
+}
+
+impl FileStore {
+    /// Opens the store at `path`, starting empty if the file doesn't exist yet.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, StoreError> {
+        let path = path.as_ref().to_path_buf();
+        let memory = match std::fs::read_to_string(&path) {
+            Ok(json) => serde_json::from_str(&json)
+                .map_err(|e| StoreError::Backend(format!("{}: {e}", path.display())))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => InMemoryStore::new(),
+            Err(e) => return Err(StoreError::Backend(format!("{}: {e}", path.display()))),
+        };
+
+        Ok(Self { path, memory })
+    }
+
+    /// Applies a change to a copy of the blocks and only keeps it once saved.
+    fn apply<T>(
+        &mut self,
+        change: impl FnOnce(&mut InMemoryStore) -> Result<T, StoreError>,
+    ) -> Result<T, StoreError> {
+        let mut memory = self.memory.clone();
+        let result = change(&mut memory)?;
+        self.save(&memory)?;
+        self.memory = memory;
+        Ok(result)
+    }
+
+    /// Writes to a sibling file first, so a failed write never truncates the store.
+    fn save(&self, memory: &InMemoryStore) -> Result<(), StoreError> {
+        let to_backend_error =
+            |e: std::io::Error| StoreError::Backend(format!("{}: {e}", self.path.display()));
+        let json =
+            serde_json::to_string_pretty(memory).map_err(|e| StoreError::Backend(e.to_string()))?;
+        let temp_path = self.path.with_extension("tmp");
+
+        std::fs::write(&temp_path, json).map_err(to_backend_error)?;
+        std::fs::rename(&temp_path, &self.path).map_err(to_backend_error)
+    }
+}
+
+impl CalendarStore for FileStore {
+    fn list(&self, day: &Day) -> Result<Vec<StoredBlock>, StoreError> {
+        self.memory.list(day)
+    }
+
+    fn get(&self, id: Uuid) -> Result<StoredBlock, StoreError> {
+        self.memory.get(id)
+    }
+
+    fn insert(&mut self, day: &Day, block: CalendarBlock) -> Result<StoredBlock, StoreError> {
+        self.apply(|memory| memory.insert(day, block))
+    }
+
+    fn insert_all(
+        &mut self,
+        day: &Day,
+        blocks: Vec<CalendarBlock>,
+    ) -> Result<Vec<StoredBlock>, StoreError> {
+        self.apply(|memory| memory.insert_all(day, blocks))
+    }
+
+    fn update(
+        &mut self,
+        block: CalendarBlock,
+        expected_version: u64,
+    ) -> Result<StoredBlock, StoreError> {
+        self.apply(|memory| memory.update(block, expected_version))
+    }
+
+    fn delete(&mut self, id: Uuid, expected_version: u64) -> Result<(), StoreError> {
+        self.apply(|memory| memory.delete(id, expected_version))
+    }
+
+    fn replace_day(
+        &mut self,
+        day: &Day,
+        blocks: Vec<CalendarBlock>,
+    ) -> Result<Vec<StoredBlock>, StoreError> {
+        self.apply(|memory| memory.replace_day(day, blocks))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A directory of its own under the system temp dir, removed when dropped.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new() -> Self {
+            let path = std::env::temp_dir().join(format!("cal-ender-{}", Uuid::new_v4()));
+            std::fs::create_dir(&path).unwrap();
+            TempDir(path)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn get_block(label: &str, start_minute: u32, end_minute: u32) -> CalendarBlock {
+        CalendarBlock {
+            id: Uuid::new_v4(),
+            start_minute,
+            end_minute,
+            block_type: CalendarBlockType::Busy,
+            subtree_depth: 0,
+            label: label.to_string(),
+            calendar_id: DEFAULT_CALENDAR_ID.to_string(),
+            metadata: BlockMetadata::default(),
+        }
+    }
+
+    #[test]
+    fn changes_are_saved_and_loaded_back() {
+        let dir = TempDir::new();
+        let path = dir.0.join("calendar.json");
+        let day = Day(String::from("2024-05-01"));
+
+        let mut store = FileStore::open(&path).unwrap();
+        assert!(store.list(&day).unwrap().is_empty());
+        let standup = store.insert(&day, get_block("Standup", 540, 555)).unwrap();
+        let review = store.insert(&day, get_block("Review", 840, 900)).unwrap();
+        let moved = CalendarBlock {
+            start_minute: 600,
+            end_minute: 615,
+            ..standup.block.clone()
+        };
+        store.update(moved, standup.version).unwrap();
+        store.delete(review.block.id, review.version).unwrap();
+
+        // the temp file is renamed over the store, so only the store is left
+        let files: Vec<PathBuf> = std::fs::read_dir(&dir.0)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        assert_eq!(files, std::slice::from_ref(&path));
+        let reopened = FileStore::open(&path).unwrap();
+        assert_eq!(reopened.list(&day).unwrap(), store.list(&day).unwrap());
+        let record = reopened.get(standup.block.id).unwrap();
+        assert_eq!((record.version, record.block.start_minute), (2, 600));
+    }
+
+    #[test]
+    fn a_failed_save_changes_neither_the_file_nor_the_store() {
+        let dir = TempDir::new();
+        let path = dir.0.join("calendar.json");
+        let day = Day(String::from("2024-05-01"));
+        let mut store = FileStore::open(&path).unwrap();
+        store.insert(&day, get_block("Standup", 540, 555)).unwrap();
+        let saved = std::fs::read_to_string(&path).unwrap();
+        // a directory in the temp file's place makes writing it fail
+        std::fs::create_dir(path.with_extension("tmp")).unwrap();
+
+        let result = store.insert(&day, get_block("Review", 840, 900));
+
+        assert!(matches!(result, Err(StoreError::Backend(_))));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), saved);
+        assert_eq!(store.list(&day).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn stale_updates_and_deletes_are_conflicts_and_leave_the_file_alone() {
+        let dir = TempDir::new();
+        let path = dir.0.join("calendar.json");
+        let day = Day(String::from("2024-05-01"));
+        let mut store = FileStore::open(&path).unwrap();
+        let record = store.insert(&day, get_block("Standup", 540, 555)).unwrap();
+        store.update(record.block.clone(), record.version).unwrap();
+        let saved = std::fs::read_to_string(&path).unwrap();
+
+        let updated = store.update(record.block.clone(), record.version);
+        let deleted = store.delete(record.block.id, record.version);
+
+        [updated.map(|_| ()), deleted]
+            .into_iter()
+            .for_each(|result| {
+                assert!(matches!(
+                    result,
+                    Err(StoreError::VersionConflict {
+                        expected: 1,
+                        actual: 2,
+                        ..
+                    })
+                ))
+            });
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), saved);
+    }
+
+    #[test]
+    fn open_fails_on_a_file_that_isnt_a_store() {
+        let dir = TempDir::new();
+        let path = dir.0.join("calendar.json");
+        std::fs::write(&path, "not json").unwrap();
+
+        assert!(matches!(
+            FileStore::open(&path),
+            Err(StoreError::Backend(message)) if message.starts_with(&path.display().to_string())
+        ));
+    }
+}
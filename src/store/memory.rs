@@ -0,0 +1,305 @@
+use crate::prelude::*;
+use std::collections::HashSet;
+
+/// Keeps blocks in a map, for tests and for stores that persist a snapshot.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct InMemoryStore {
+    records: HashMap<Uuid, StoredBlock>,
+}
+
+impl InMemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn get_checked(&self, id: Uuid, expected_version: u64) -> Result<&StoredBlock, StoreError> {
+        let record = self.records.get(&id).ok_or(StoreError::NotFound(id))?;
+        match record.version == expected_version {
+            true => Ok(record),
+            false => Err(StoreError::VersionConflict {
+                id,
+                expected: expected_version,
+                actual: record.version,
+            }),
+        }
+    }
+}
+
+impl CalendarStore for InMemoryStore {
+    fn list(&self, day: &Day) -> Result<Vec<StoredBlock>, StoreError> {
+        let mut records: Vec<StoredBlock> = self
+            .records
+            .values()
+            .filter(|record| &record.day == day)
+            .cloned()
+            .collect();
+        records.sort_by_key(|record| record.block.sort_key());
+        Ok(records)
+    }
+
+    fn get(&self, id: Uuid) -> Result<StoredBlock, StoreError> {
+        self.records
+            .get(&id)
+            .cloned()
+            .ok_or(StoreError::NotFound(id))
+    }
+
+    fn insert(&mut self, day: &Day, block: CalendarBlock) -> Result<StoredBlock, StoreError> {
+        if self.records.contains_key(&block.id) {
+            return Err(StoreError::AlreadyExists(block.id));
+        }
+
+        let record = StoredBlock {
+            day: day.clone(),
+            version: 1,
+            block,
+        };
+        self.records.insert(record.block.id, record.clone());
+        Ok(record)
+    }
+
+    fn insert_all(
+        &mut self,
+        day: &Day,
+        blocks: Vec<CalendarBlock>,
+    ) -> Result<Vec<StoredBlock>, StoreError> {
+        let mut ids = HashSet::new();
+        for block in &blocks {
+            if !ids.insert(block.id) {
+                return Err(StoreError::DuplicateId(block.id));
+            }
+            if self.records.contains_key(&block.id) {
+                return Err(StoreError::AlreadyExists(block.id));
+            }
+        }
+
+        blocks
+            .into_iter()
+            .map(|block| self.insert(day, block))
+            .collect()
+    }
+
+    fn update(
+        &mut self,
+        block: CalendarBlock,
+        expected_version: u64,
+    ) -> Result<StoredBlock, StoreError> {
+        let day = self.get_checked(block.id, expected_version)?.day.clone();
+        let record = StoredBlock {
+            day,
+            version: expected_version + 1,
+            block,
+        };
+        self.records.insert(record.block.id, record.clone());
+        Ok(record)
+    }
+
+    fn delete(&mut self, id: Uuid, expected_version: u64) -> Result<(), StoreError> {
+        self.get_checked(id, expected_version)?;
+        self.records.remove(&id);
+        Ok(())
+    }
+
+    fn replace_day(
+        &mut self,
+        day: &Day,
+        blocks: Vec<CalendarBlock>,
+    ) -> Result<Vec<StoredBlock>, StoreError> {
+        let mut ids = HashSet::new();
+        for block in &blocks {
+            if !ids.insert(block.id) {
+                return Err(StoreError::DuplicateId(block.id));
+            }
+            match self.records.get(&block.id) {
+                Some(record) if &record.day != day => {
+                    return Err(StoreError::AlreadyExists(block.id))
+                }
+                _ => {}
+            }
+        }
+
+        let previous_versions: HashMap<Uuid, u64> = self
+            .records
+            .values()
+            .filter(|record| &record.day == day)
+            .map(|record| (record.block.id, record.version))
+            .collect();
+        self.records.retain(|_, record| &record.day != day);
+
+        blocks.into_iter().for_each(|block| {
+            let record = StoredBlock {
+                day: day.clone(),
+                version: previous_versions
+                    .get(&block.id)
+                    .map_or(1, |version| version + 1),
+                block,
+            };
+            self.records.insert(record.block.id, record);
+        });
+        self.list(day)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_block(label: &str, start_minute: u32, end_minute: u32) -> CalendarBlock {
+        CalendarBlock {
+            id: Uuid::new_v4(),
+            start_minute,
+            end_minute,
+            block_type: CalendarBlockType::Busy,
+            subtree_depth: 0,
+            label: label.to_string(),
+            calendar_id: DEFAULT_CALENDAR_ID.to_string(),
+            metadata: BlockMetadata::default(),
+        }
+    }
+
+    fn get_labels(store: &InMemoryStore, day: &Day) -> Vec<String> {
+        store
+            .list(day)
+            .unwrap()
+            .into_iter()
+            .map(|record| record.block.label)
+            .collect()
+    }
+
+    #[test]
+    fn replace_day_swaps_the_days_blocks_and_keeps_counting_versions() {
+        let (monday, tuesday) = (Day(String::from("monday")), Day(String::from("tuesday")));
+        let mut store = InMemoryStore::new();
+        let kept = store.insert(&monday, get_block("Kept", 60, 120)).unwrap();
+        store
+            .insert(&monday, get_block("Dropped", 120, 180))
+            .unwrap();
+        store
+            .insert(&tuesday, get_block("Other day", 60, 120))
+            .unwrap();
+
+        let records = store
+            .replace_day(
+                &monday,
+                vec![
+                    CalendarBlock {
+                        start_minute: 90,
+                        ..kept.block.clone()
+                    },
+                    get_block("Added", 0, 30),
+                ],
+            )
+            .unwrap();
+
+        assert_eq!(get_labels(&store, &monday), ["Added", "Kept"]);
+        assert_eq!(get_labels(&store, &tuesday), ["Other day"]);
+        let versions: Vec<u64> = records.iter().map(|record| record.version).collect();
+        assert_eq!(versions, [1, 2]);
+    }
+
+    #[test]
+    fn replace_day_changes_nothing_for_duplicate_ids() {
+        let monday = Day(String::from("monday"));
+        let mut store = InMemoryStore::new();
+        store
+            .insert(&monday, get_block("Existing", 60, 120))
+            .unwrap();
+        let duplicate = get_block("Duplicate", 0, 30);
+
+        let result = store.replace_day(&monday, vec![duplicate.clone(), duplicate.clone()]);
+
+        assert!(matches!(result, Err(StoreError::DuplicateId(id)) if id == duplicate.id));
+        assert_eq!(get_labels(&store, &monday), ["Existing"]);
+    }
+
+    #[test]
+    fn replace_day_changes_nothing_for_blocks_of_another_day() {
+        let (monday, tuesday) = (Day(String::from("monday")), Day(String::from("tuesday")));
+        let mut store = InMemoryStore::new();
+        store
+            .insert(&monday, get_block("Existing", 60, 120))
+            .unwrap();
+        let other = store
+            .insert(&tuesday, get_block("Other day", 60, 120))
+            .unwrap();
+
+        let result = store.replace_day(&monday, vec![get_block("New", 0, 30), other.block.clone()]);
+
+        assert!(matches!(result, Err(StoreError::AlreadyExists(id)) if id == other.block.id));
+        assert_eq!(get_labels(&store, &monday), ["Existing"]);
+        assert_eq!(get_labels(&store, &tuesday), ["Other day"]);
+    }
+
+    #[test]
+    fn insert_all_inserts_nothing_if_any_block_is_stored() {
+        let day = Day(String::from("2024-05-01"));
+        let standup = get_block("Standup", 540, 555);
+        let mut store = InMemoryStore::new();
+        store.insert(&day, standup.clone()).unwrap();
+
+        let result = store.insert_all(&day, vec![get_block("Review", 840, 900), standup.clone()]);
+
+        assert!(matches!(result, Err(StoreError::AlreadyExists(id)) if id == standup.id));
+        assert_eq!(get_labels(&store, &day), vec!["Standup"]);
+    }
+
+    #[test]
+    fn insert_all_inserts_nothing_for_duplicate_ids() {
+        let day = Day(String::from("2024-05-01"));
+        let review = get_block("Review", 840, 900);
+        let mut store = InMemoryStore::new();
+
+        let result = store.insert_all(&day, vec![review.clone(), review.clone()]);
+
+        assert!(matches!(result, Err(StoreError::DuplicateId(id)) if id == review.id));
+        assert!(get_labels(&store, &day).is_empty());
+
+        let records = store
+            .insert_all(&day, vec![review, get_block("Lunch", 720, 780)])
+            .unwrap();
+        assert!(records.iter().all(|record| record.version == 1));
+        assert_eq!(get_labels(&store, &day), vec!["Lunch", "Review"]);
+    }
+
+    #[test]
+    fn update_with_a_stale_version_is_a_conflict() {
+        let day = Day(String::from("2024-05-01"));
+        let mut store = InMemoryStore::new();
+        let record = store.insert(&day, get_block("Standup", 540, 555)).unwrap();
+        let moved = CalendarBlock {
+            start_minute: 600,
+            end_minute: 615,
+            ..record.block.clone()
+        };
+        store.update(moved.clone(), record.version).unwrap();
+
+        let result = store.update(moved, record.version);
+
+        assert!(matches!(
+            result,
+            Err(StoreError::VersionConflict { id, expected: 1, actual: 2 }) if id == record.block.id
+        ));
+        assert_eq!(store.get(record.block.id).unwrap().version, 2);
+    }
+
+    #[test]
+    fn delete_with_a_stale_version_is_a_conflict() {
+        let day = Day(String::from("2024-05-01"));
+        let mut store = InMemoryStore::new();
+        let record = store.insert(&day, get_block("Standup", 540, 555)).unwrap();
+        store.update(record.block.clone(), record.version).unwrap();
+
+        let result = store.delete(record.block.id, record.version);
+
+        assert!(matches!(
+            result,
+            Err(StoreError::VersionConflict { id, expected: 1, actual: 2 }) if id == record.block.id
+        ));
+        assert_eq!(get_labels(&store, &day), vec!["Standup"]);
+        store.delete(record.block.id, 2).unwrap();
+        assert!(matches!(
+            store.delete(record.block.id, 2),
+            Err(StoreError::NotFound(id)) if id == record.block.id
+        ));
+    }
+}
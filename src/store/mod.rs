@@ -0,0 +1,126 @@
+use crate::ics::shift_day;
+use crate::prelude::*;
+
+pub mod file;
+pub mod memory;
+
+/// A calendar day, as `YYYY-MM-DD`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Day(pub String);
+
+impl Day {
+    /// Checks that the day is an existing date written as `YYYY-MM-DD`.
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        let is_well_formed = self.0.len() == 10
+            && self.0.char_indices().all(|(idx, c)| match idx {
+                4 | 7 => c == '-',
+                _ => c.is_ascii_digit(),
+            });
+        // a date that doesn't exist, like 2023-02-29, comes back as another one
+        match is_well_formed && shift_day(&self.0, 0) == Some(self.0.replace('-', "")) {
+            true => Ok(()),
+            false => Err(ValidationError::InvalidDay(self.0.clone())),
+        }
+    }
+}
+
+impl Display for Day {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// A block as kept by a store. `version` starts at 1 and increases on every
+/// update, so writers can detect that someone else changed the block first.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StoredBlock {
+    pub day: Day,
+    pub version: u64,
+    pub block: CalendarBlock,
+}
+
+#[derive(Debug, Error)]
+pub enum StoreError {
+    #[error("block {0} not found")]
+    NotFound(Uuid),
+    #[error("block {0} already exists")]
+    AlreadyExists(Uuid),
+    #[error("block {0} is listed more than once")]
+    DuplicateId(Uuid),
+    #[error("block {id} is at version {actual}, expected version {expected}")]
+    VersionConflict {
+        id: Uuid,
+        expected: u64,
+        actual: u64,
+    },
+    #[error("{0}")]
+    Backend(String),
+}
+
+pub trait CalendarStore {
+    /// Blocks of the day in canonical block order.
+    fn list(&self, day: &Day) -> Result<Vec<StoredBlock>, StoreError>;
+
+    fn get(&self, id: Uuid) -> Result<StoredBlock, StoreError>;
+
+    fn insert(&mut self, day: &Day, block: CalendarBlock) -> Result<StoredBlock, StoreError>;
+
+    /// Inserts every block or none of them, failing if any block is listed
+    /// twice or already stored.
+    fn insert_all(
+        &mut self,
+        day: &Day,
+        blocks: Vec<CalendarBlock>,
+    ) -> Result<Vec<StoredBlock>, StoreError>;
+
+    /// Replaces the block with the same id, failing with `VersionConflict`
+    /// unless it is still at `expected_version`.
+    fn update(
+        &mut self,
+        block: CalendarBlock,
+        expected_version: u64,
+    ) -> Result<StoredBlock, StoreError>;
+
+    fn delete(&mut self, id: Uuid, expected_version: u64) -> Result<(), StoreError>;
+
+    /// Replaces all blocks of the day at once, or changes nothing if any block
+    /// is listed twice or already belongs to another day. Blocks that were on
+    /// the day keep counting versions, new ones start at 1.
+    fn replace_day(
+        &mut self,
+        day: &Day,
+        blocks: Vec<CalendarBlock>,
+    ) -> Result<Vec<StoredBlock>, StoreError>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn day_validate_accepts_existing_dates_only() {
+        ["2024-05-01", "2024-02-29", "2000-02-29", "1999-12-31"]
+            .iter()
+            .for_each(|day| assert_eq!(Day(day.to_string()).validate(), Ok(()), "{day}"));
+
+        [
+            "2023-02-29",
+            "2024-04-31",
+            "2024-13-01",
+            "2024-00-10",
+            "2024-5-1",
+            "20240501",
+            "2024-05-01T09",
+            "+024-05-01",
+            "",
+        ]
+        .iter()
+        .for_each(|day| {
+            assert_eq!(
+                Day(day.to_string()).validate(),
+                Err(ValidationError::InvalidDay(day.to_string())),
+                "{day}"
+            )
+        });
+    }
+}
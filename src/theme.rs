@@ -0,0 +1,269 @@
+use crate::prelude::*;
+
+/// Key the selected theme is saved under in the browser's localStorage.
+pub const THEME_STORAGE_KEY: &str = "cal-ender.theme";
+/// Key of a custom palette, a JSON `Palette` whose missing fields are taken from the light one.
+pub const PALETTE_STORAGE_KEY: &str = "cal-ender.palette";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlockColors {
+    pub fill: String,
+    pub border: String,
+    pub text: String,
+}
+
+impl BlockColors {
+    fn new(fill: &str, border: &str, text: &str) -> Self {
+        Self {
+            fill: fill.to_string(),
+            border: border.to_string(),
+            text: text.to_string(),
+        }
+    }
+}
+
+/// Every color the calendar is drawn with. Applied to the page as CSS variables,
+/// see `to_css_variables`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Palette {
+    pub is_dark: bool,
+    pub background: String,
+    /// Behind the calendar column and the buttons.
+    pub surface: String,
+    /// Behind popovers and tooltips.
+    pub popover: String,
+    pub text: String,
+    pub muted_text: String,
+    pub gridline: String,
+    pub accent: String,
+    pub conflict: String,
+    pub rejected: String,
+    pub busy: BlockColors,
+    pub available: BlockColors,
+    pub tentative: BlockColors,
+    pub out_of_office: BlockColors,
+    pub focus: BlockColors,
+    pub travel: BlockColors,
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self::light()
+    }
+}
+
+impl Palette {
+    pub fn light() -> Self {
+        Self {
+            is_dark: false,
+            background: String::from("#FFFFFF"),
+            surface: String::from("#FCF9F7"),
+            popover: String::from("#FFFFFF"),
+            text: String::from("#000000"),
+            muted_text: String::from("#494949"),
+            gridline: String::from("#DADBF1"),
+            accent: String::from("#4C5FD5"),
+            conflict: String::from("#D5544C"),
+            rejected: String::from("#D5544C"),
+            busy: BlockColors::new("#FFFFFF", "#494949", "#000000"),
+            available: BlockColors::new("#FEDF03", "#000000", "#000000"),
+            tentative: BlockColors::new("#FFFFFF", "#494949", "#494949"),
+            out_of_office: BlockColors::new("#DADBF1", "#494949", "#000000"),
+            focus: BlockColors::new("#E4E8FB", "#4C5FD5", "#000000"),
+            travel: BlockColors::new("#E3F3EA", "#2E9E6A", "#000000"),
+        }
+    }
+
+    pub fn dark() -> Self {
+        Self {
+            is_dark: true,
+            background: String::from("#121214"),
+            surface: String::from("#1C1C21"),
+            popover: String::from("#26262D"),
+            text: String::from("#ECECF1"),
+            muted_text: String::from("#A4A4B0"),
+            gridline: String::from("#2E2E38"),
+            accent: String::from("#8A98F0"),
+            conflict: String::from("#F07A72"),
+            rejected: String::from("#F07A72"),
+            busy: BlockColors::new("#2A2A31", "#A4A4B0", "#ECECF1"),
+            available: BlockColors::new("#4D4500", "#FEDF03", "#FFF6B3"),
+            tentative: BlockColors::new("#1C1C21", "#A4A4B0", "#A4A4B0"),
+            out_of_office: BlockColors::new("#34343F", "#A4A4B0", "#ECECF1"),
+            focus: BlockColors::new("#262C4F", "#8A98F0", "#ECECF1"),
+            travel: BlockColors::new("#1D3A2B", "#5CC795", "#ECECF1"),
+        }
+    }
+
+    /// Black and pure colors, for the strongest contrast between blocks and text.
+    pub fn high_contrast() -> Self {
+        Self {
+            is_dark: true,
+            background: String::from("#000000"),
+            surface: String::from("#000000"),
+            popover: String::from("#000000"),
+            text: String::from("#FFFFFF"),
+            muted_text: String::from("#FFFFFF"),
+            gridline: String::from("#808080"),
+            accent: String::from("#FFFF00"),
+            conflict: String::from("#FF4040"),
+            rejected: String::from("#FF4040"),
+            busy: BlockColors::new("#000000", "#FFFFFF", "#FFFFFF"),
+            available: BlockColors::new("#FFFF00", "#FFFF00", "#000000"),
+            tentative: BlockColors::new("#000000", "#FFFFFF", "#FFFFFF"),
+            out_of_office: BlockColors::new("#000000", "#00FFFF", "#00FFFF"),
+            focus: BlockColors::new("#000000", "#FF00FF", "#FFFFFF"),
+            travel: BlockColors::new("#000000", "#00FF00", "#FFFFFF"),
+        }
+    }
+
+    pub fn get_block_colors(&self, block_type: CalendarBlockType) -> &BlockColors {
+        match block_type {
+            CalendarBlockType::Busy => &self.busy,
+            CalendarBlockType::Available => &self.available,
+            CalendarBlockType::Tentative => &self.tentative,
+            CalendarBlockType::OutOfOffice => &self.out_of_office,
+            CalendarBlockType::Focus => &self.focus,
+            CalendarBlockType::Travel => &self.travel,
+        }
+    }
+
+    /// (name, value) of the variables `app.css` reads its colors from. Block
+    /// colors are named after the type's class, like `--color-busy-fill`.
+    pub fn to_css_variables(&self) -> Vec<(String, String)> {
+        let mut variables: Vec<(String, String)> = [
+            ("background", &self.background),
+            ("surface", &self.surface),
+            ("popover", &self.popover),
+            ("text", &self.text),
+            ("muted-text", &self.muted_text),
+            ("gridline", &self.gridline),
+            ("accent", &self.accent),
+            ("conflict", &self.conflict),
+            ("rejected", &self.rejected),
+        ]
+        .into_iter()
+        .map(|(name, value)| (format!("--color-{name}"), value.clone()))
+        .collect();
+
+        CalendarBlockType::ALL.iter().for_each(|block_type| {
+            let css_class = block_type.info().css_class;
+            let block_colors = self.get_block_colors(*block_type);
+            variables.extend([
+                (
+                    format!("--color-{css_class}-fill"),
+                    block_colors.fill.clone(),
+                ),
+                (
+                    format!("--color-{css_class}-border"),
+                    block_colors.border.clone(),
+                ),
+                (
+                    format!("--color-{css_class}-text"),
+                    block_colors.text.clone(),
+                ),
+            ]);
+        });
+
+        variables
+    }
+}
+
+/// What the operating system asks for, from the `prefers-*` media queries.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SystemColorScheme {
+    pub prefers_dark: bool,
+    pub prefers_more_contrast: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Theme {
+    /// Follows the system's color scheme and contrast settings.
+    #[default]
+    System,
+    Light,
+    Dark,
+    HighContrast,
+    /// The palette saved under `PALETTE_STORAGE_KEY`.
+    Custom,
+}
+
+impl Theme {
+    /// The next theme to switch to, skipping Custom when there's no custom palette.
+    pub fn next(&self, has_custom_palette: bool) -> Self {
+        match (self, has_custom_palette) {
+            (Theme::System, _) => Theme::Light,
+            (Theme::Light, _) => Theme::Dark,
+            (Theme::Dark, _) => Theme::HighContrast,
+            (Theme::HighContrast, true) => Theme::Custom,
+            (Theme::HighContrast, false) | (Theme::Custom, _) => Theme::System,
+        }
+    }
+
+    pub fn get_palette(
+        &self,
+        system_color_scheme: SystemColorScheme,
+        custom_palette: Option<&Palette>,
+    ) -> Palette {
+        match self {
+            Theme::System => match system_color_scheme {
+                SystemColorScheme {
+                    prefers_more_contrast: true,
+                    ..
+                } => Palette::high_contrast(),
+                SystemColorScheme {
+                    prefers_dark: true, ..
+                } => Palette::dark(),
+                _ => Palette::light(),
+            },
+            Theme::Light => Palette::light(),
+            Theme::Dark => Palette::dark(),
+            Theme::HighContrast => Palette::high_contrast(),
+            Theme::Custom => custom_palette.cloned().unwrap_or_default(),
+        }
+    }
+}
+
+impl Display for Theme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let theme = match self {
+            Theme::System => "System",
+            Theme::Light => "Light",
+            Theme::Dark => "Dark",
+            Theme::HighContrast => "High Contrast",
+            Theme::Custom => "Custom",
+        };
+        write!(f, "{theme}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const APP_CSS: &str = include_str!("../assets/app.css");
+
+    fn get_declarations(palette: &Palette) -> Vec<String> {
+        palette
+            .to_css_variables()
+            .into_iter()
+            .map(|(name, value)| format!("{name}: {value};"))
+            .collect()
+    }
+
+    #[test]
+    fn app_css_defaults_match_the_light_and_dark_palettes() {
+        let (light_defaults, dark_defaults) = APP_CSS
+            .split_once("@media (prefers-color-scheme: dark)")
+            .unwrap();
+
+        get_declarations(&Palette::light())
+            .iter()
+            .for_each(|declaration| assert!(light_defaults.contains(declaration), "{declaration}"));
+        get_declarations(&Palette::dark())
+            .iter()
+            .for_each(|declaration| assert!(dark_defaults.contains(declaration), "{declaration}"));
+    }
+}
@@ -0,0 +1,192 @@
+/// Languages that conventionally use a 12-hour clock in every region.
+const TWELVE_HOUR_LANGUAGES: [&str; 1] = ["ar"];
+
+/// Language-region tags that conventionally use a 12-hour clock. Browsers
+/// commonly report US English as a bare "en".
+const TWELVE_HOUR_LOCALES: [&str; 8] = [
+    "en", "en-US", "en-CA", "en-AU", "en-NZ", "en-IN", "en-PH", "es-MX",
+];
+
+/// Splits a tag such as "en_us" or "zh-Hant-TW" into its lowercase language
+/// and region subtags, skipping the script and anything after the region.
+fn split_locale(locale: &str) -> (String, Option<String>) {
+    let mut subtags = locale
+        .split(['-', '_'])
+        .map(|subtag| subtag.to_ascii_lowercase());
+    let language = subtags.next().unwrap_or_default();
+    let region = subtags
+        .take_while(|subtag| subtag.len() > 1)
+        .find(|subtag| {
+            subtag.len() == 2 || (subtag.len() == 3 && subtag.chars().all(|c| c.is_ascii_digit()))
+        });
+    (language, region)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TimeFormat {
+    #[default]
+    TwentyFourHour,
+    TwelveHour,
+}
+
+impl TimeFormat {
+    pub fn for_locale(locale: &str) -> Self {
+        let (language, region) = split_locale(locale);
+        let tag = match region {
+            Some(region) => format!("{language}-{region}"),
+            None => language.clone(),
+        };
+
+        match TWELVE_HOUR_LANGUAGES.contains(&language.as_str())
+            || TWELVE_HOUR_LOCALES
+                .iter()
+                .any(|twelve_hour_tag| twelve_hour_tag.eq_ignore_ascii_case(&tag))
+        {
+            true => TimeFormat::TwelveHour,
+            false => TimeFormat::TwentyFourHour,
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            TimeFormat::TwentyFourHour => TimeFormat::TwelveHour,
+            TimeFormat::TwelveHour => TimeFormat::TwentyFourHour,
+        }
+    }
+
+    /// Formats minutes since midnight, e.g. 590 as "09:50" or "9:50 AM".
+    pub fn format_time(&self, minutes: u32) -> String {
+        let hour = minutes / 60 % 24;
+        let minute = minutes % 60;
+
+        match self {
+            TimeFormat::TwentyFourHour => format!("{hour:02}:{minute:02}"),
+            TimeFormat::TwelveHour => {
+                let meridiem = match hour < 12 {
+                    true => "AM",
+                    false => "PM",
+                };
+                let hour = match hour % 12 {
+                    0 => 12,
+                    hour => hour,
+                };
+                format!("{hour}:{minute:02} {meridiem}")
+            }
+        }
+    }
+
+    pub fn format_range(&self, start_minute: u32, end_minute: u32) -> String {
+        format!(
+            "{}–{}",
+            self.format_time(start_minute),
+            self.format_time(end_minute)
+        )
+    }
+}
+
+/// Formats a duration in minutes, e.g. 75 as "1h 15m".
+pub fn format_duration(minutes: u32) -> String {
+    match (minutes / 60, minutes % 60) {
+        (0, minutes) => format!("{minutes}m"),
+        (hours, 0) => format!("{hours}h"),
+        (hours, minutes) => format!("{hours}h {minutes}m"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_time_writes_midnight_and_noon() {
+        assert_eq!(TimeFormat::TwentyFourHour.format_time(0), "00:00");
+        assert_eq!(TimeFormat::TwentyFourHour.format_time(720), "12:00");
+        assert_eq!(TimeFormat::TwelveHour.format_time(0), "12:00 AM");
+        assert_eq!(TimeFormat::TwelveHour.format_time(720), "12:00 PM");
+    }
+
+    #[test]
+    fn format_time_pads_minutes_but_not_twelve_hour_hours() {
+        assert_eq!(TimeFormat::TwentyFourHour.format_time(545), "09:05");
+        assert_eq!(TimeFormat::TwelveHour.format_time(545), "9:05 AM");
+        assert_eq!(TimeFormat::TwentyFourHour.format_time(1439), "23:59");
+        assert_eq!(TimeFormat::TwelveHour.format_time(1439), "11:59 PM");
+    }
+
+    #[test]
+    fn format_time_writes_the_end_of_the_day_as_midnight() {
+        assert_eq!(TimeFormat::TwentyFourHour.format_time(1440), "00:00");
+        assert_eq!(TimeFormat::TwelveHour.format_time(1440), "12:00 AM");
+        assert_eq!(
+            TimeFormat::TwentyFourHour.format_range(1380, 1440),
+            "23:00–00:00"
+        );
+    }
+
+    #[test]
+    fn format_range_crosses_noon() {
+        assert_eq!(
+            TimeFormat::TwentyFourHour.format_range(690, 780),
+            "11:30–13:00"
+        );
+        assert_eq!(
+            TimeFormat::TwelveHour.format_range(690, 780),
+            "11:30 AM–1:00 PM"
+        );
+    }
+
+    #[test]
+    fn format_duration_leaves_out_zero_parts() {
+        assert_eq!(format_duration(0), "0m");
+        assert_eq!(format_duration(59), "59m");
+        assert_eq!(format_duration(60), "1h");
+        assert_eq!(format_duration(75), "1h 15m");
+        assert_eq!(format_duration(599), "9h 59m");
+        assert_eq!(format_duration(1440), "24h");
+    }
+
+    #[test]
+    fn next_toggles_between_the_formats() {
+        assert_eq!(TimeFormat::TwentyFourHour.next(), TimeFormat::TwelveHour);
+        assert_eq!(TimeFormat::TwelveHour.next(), TimeFormat::TwentyFourHour);
+    }
+
+    #[test]
+    fn for_locale_matches_full_tags() {
+        assert_eq!(TimeFormat::for_locale("en-US"), TimeFormat::TwelveHour);
+        assert_eq!(TimeFormat::for_locale("es-MX"), TimeFormat::TwelveHour);
+        assert_eq!(TimeFormat::for_locale("en-GB"), TimeFormat::TwentyFourHour);
+        assert_eq!(TimeFormat::for_locale("es-ES"), TimeFormat::TwentyFourHour);
+    }
+
+    #[test]
+    fn for_locale_matches_a_bare_language() {
+        assert_eq!(TimeFormat::for_locale("en"), TimeFormat::TwelveHour);
+        assert_eq!(TimeFormat::for_locale("ar"), TimeFormat::TwelveHour);
+        assert_eq!(TimeFormat::for_locale("es"), TimeFormat::TwentyFourHour);
+        assert_eq!(TimeFormat::for_locale("fr"), TimeFormat::TwentyFourHour);
+    }
+
+    #[test]
+    fn for_locale_matches_every_region_of_a_twelve_hour_language() {
+        assert_eq!(TimeFormat::for_locale("ar-EG"), TimeFormat::TwelveHour);
+        assert_eq!(TimeFormat::for_locale("ar-SA"), TimeFormat::TwelveHour);
+    }
+
+    #[test]
+    fn for_locale_ignores_case_separators_scripts_and_extensions() {
+        assert_eq!(TimeFormat::for_locale("en_us"), TimeFormat::TwelveHour);
+        assert_eq!(TimeFormat::for_locale("EN-CA"), TimeFormat::TwelveHour);
+        assert_eq!(TimeFormat::for_locale("en-Latn-US"), TimeFormat::TwelveHour);
+        assert_eq!(
+            TimeFormat::for_locale("en-GB-u-ca-gregory"),
+            TimeFormat::TwentyFourHour
+        );
+    }
+
+    #[test]
+    fn for_locale_does_not_match_languages_sharing_a_prefix() {
+        assert_eq!(TimeFormat::for_locale("arn-CL"), TimeFormat::TwentyFourHour);
+        assert_eq!(TimeFormat::for_locale(""), TimeFormat::TwentyFourHour);
+    }
+}
@@ -0,0 +1,322 @@
+//! Syncs against a stub CalDAV server that keeps events in memory and honours
+//! `If-Match` and `If-None-Match` like Radicale does.
+
+use cal_ender::prelude::*;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+
+/// Event resources by path, with the ETag of their current version.
+#[derive(Default)]
+struct Resources {
+    events: HashMap<String, (String, String)>,
+    revision: u32,
+}
+
+impl Resources {
+    fn put(&mut self, path: &str, calendar_data: &str) -> String {
+        self.revision += 1;
+        let etag = format!("\"{}\"", self.revision);
+        self.events
+            .insert(path.to_string(), (etag.clone(), calendar_data.to_string()));
+        etag
+    }
+}
+
+struct StubServer {
+    calendar_url: String,
+    resources: Arc<Mutex<Resources>>,
+}
+
+impl StubServer {
+    fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let calendar_url = format!("http://{}/user/calendar/", listener.local_addr().unwrap());
+        let resources = Arc::new(Mutex::new(Resources::default()));
+
+        let shared_resources = resources.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                handle_connection(stream, &shared_resources);
+            }
+        });
+
+        Self {
+            calendar_url,
+            resources,
+        }
+    }
+
+    /// Stands in for another client editing the event on the server.
+    fn put(&self, name: &str, calendar_data: &str) -> String {
+        self.resources
+            .lock()
+            .unwrap()
+            .put(&format!("/user/calendar/{name}"), calendar_data)
+    }
+
+    fn get(&self, name: &str) -> Option<(String, String)> {
+        self.resources
+            .lock()
+            .unwrap()
+            .events
+            .get(&format!("/user/calendar/{name}"))
+            .cloned()
+    }
+}
+
+fn handle_connection(stream: TcpStream, resources: &Mutex<Resources>) {
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).unwrap();
+    let mut parts = request_line.split_whitespace();
+    let (method, path) = (parts.next().unwrap(), parts.next().unwrap());
+
+    let mut headers = HashMap::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        match line.trim_end().split_once(':') {
+            Some((name, value)) => headers.insert(name.to_lowercase(), value.trim().to_string()),
+            None => break,
+        };
+    }
+    let content_length = headers
+        .get("content-length")
+        .map(|length| length.parse().unwrap())
+        .unwrap_or(0);
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).unwrap();
+    let body = String::from_utf8(body).unwrap();
+
+    let mut resources = resources.lock().unwrap();
+    let current_etag = resources.events.get(path).map(|(etag, _)| etag.clone());
+    let (status, etag, response_body) = match method {
+        "REPORT" => (207, None, get_multistatus(&resources)),
+        "PUT" => match (
+            headers.get("if-match"),
+            headers.get("if-none-match"),
+            current_etag,
+        ) {
+            (Some(expected), _, Some(current)) if *expected != current => {
+                (412, None, String::new())
+            }
+            (Some(_), _, None) | (_, Some(_), Some(_)) => (412, None, String::new()),
+            _ => (204, Some(resources.put(path, &body)), String::new()),
+        },
+        _ => (405, None, String::new()),
+    };
+
+    let etag_header = etag
+        .map(|etag| format!("ETag: {etag}\r\n"))
+        .unwrap_or_default();
+    let mut stream = stream;
+    write!(
+        stream,
+        "HTTP/1.1 {status} Stub\r\nContent-Length: {}\r\n{etag_header}Connection: close\r\n\r\n{response_body}",
+        response_body.len()
+    )
+    .unwrap();
+}
+
+fn get_multistatus(resources: &Resources) -> String {
+    let responses: String = resources
+        .events
+        .iter()
+        .map(|(path, (etag, calendar_data))| {
+            format!(
+                "<D:response><D:href>{path}</D:href><D:propstat><D:prop><D:getetag>{}</D:getetag><C:calendar-data>{}</C:calendar-data></D:prop></D:propstat></D:response>",
+                etag.replace('"', "&quot;"),
+                calendar_data.replace('&', "&amp;").replace('<', "&lt;")
+            )
+        })
+        .collect();
+    format!("<?xml version=\"1.0\"?><D:multistatus xmlns:D=\"DAV:\" xmlns:C=\"urn:ietf:params:xml:ns:caldav\">{responses}</D:multistatus>")
+}
+
+fn get_event(uid: &str, start: &str, end: &str, summary: &str) -> String {
+    format!(
+        "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nBEGIN:VEVENT\r\nUID:{uid}\r\nDTSTART:{start}\r\nDTEND:{end}\r\nSUMMARY:{summary}\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n"
+    )
+}
+
+fn get_day() -> (Day, Day) {
+    (
+        Day(String::from("2024-05-01")),
+        Day(String::from("2024-05-02")),
+    )
+}
+
+/// Pulls a server holding a standup at 09:00 and a review at 14:00.
+fn pull_two_events() -> (StubServer, CalDavClient, InMemoryStore, SyncState) {
+    let server = StubServer::start();
+    server.put(
+        "standup.ics",
+        &get_event("standup", "20240501T090000", "20240501T091500", "Standup"),
+    );
+    server.put(
+        "review.ics",
+        &get_event("review", "20240501T140000", "20240501T150000", "Review"),
+    );
+
+    let client = CalDavClient::new(&server.calendar_url);
+    let mut store = InMemoryStore::new();
+    let mut sync_state = SyncState::new();
+    let (start, end) = get_day();
+    client
+        .pull(&mut store, &mut sync_state, &start, &end)
+        .unwrap();
+
+    (server, client, store, sync_state)
+}
+
+fn find_block(store: &InMemoryStore, label: &str) -> StoredBlock {
+    let (start, _) = get_day();
+    store
+        .list(&start)
+        .unwrap()
+        .into_iter()
+        .find(|record| record.block.label == label)
+        .unwrap()
+}
+
+fn move_block(store: &mut InMemoryStore, label: &str, start_minute: u32, end_minute: u32) {
+    let record = find_block(store, label);
+    store
+        .update(
+            CalendarBlock {
+                start_minute,
+                end_minute,
+                ..record.block
+            },
+            record.version,
+        )
+        .unwrap();
+}
+
+#[test]
+fn pull_copies_events_into_the_store_and_records_them() {
+    let (_server, _client, store, sync_state) = pull_two_events();
+
+    let standup = find_block(&store, "Standup");
+    assert_eq!(
+        (standup.block.start_minute, standup.block.end_minute),
+        (540, 555)
+    );
+    let recorded = sync_state.get(standup.block.id).unwrap();
+    assert_eq!(recorded.etag.as_deref(), Some("\"1\""));
+    assert!(recorded.href.ends_with("/user/calendar/standup.ics"));
+}
+
+#[test]
+fn push_moves_sends_only_moved_blocks() {
+    let (server, client, mut store, mut sync_state) = pull_two_events();
+    move_block(&mut store, "Standup", 570, 585);
+
+    let results = client.push_moves(&store, &mut sync_state);
+
+    assert_eq!(results.len(), 1);
+    let pushed = results.into_iter().next().unwrap().unwrap();
+    assert_eq!(pushed.etag.as_deref(), Some("\"3\""));
+    let (etag, calendar_data) = server.get("standup.ics").unwrap();
+    assert_eq!(etag, "\"3\"");
+    assert!(calendar_data.contains("DTSTART:20240501T093000"));
+    assert_eq!(server.get("review.ics").unwrap().0, "\"2\"");
+
+    // the push is recorded, so there is nothing left to send
+    assert!(client.push_moves(&store, &mut sync_state).is_empty());
+}
+
+#[test]
+fn push_moves_fails_for_events_changed_on_the_server_since_the_pull() {
+    let (server, client, mut store, mut sync_state) = pull_two_events();
+    let edited = get_event("standup", "20240501T100000", "20240501T101500", "Standup");
+    server.put("standup.ics", &edited);
+    move_block(&mut store, "Standup", 570, 585);
+
+    let results = client.push_moves(&store, &mut sync_state);
+
+    assert!(matches!(
+        results.as_slice(),
+        [Err(CalDavError::Conflict(href))] if href.ends_with("standup.ics")
+    ));
+    assert_eq!(server.get("standup.ics").unwrap().1, edited);
+}
+
+#[test]
+fn pull_after_a_conflict_takes_the_server_version() {
+    let (server, client, mut store, mut sync_state) = pull_two_events();
+    server.put(
+        "standup.ics",
+        &get_event("standup", "20240501T100000", "20240501T101500", "Standup"),
+    );
+    move_block(&mut store, "Standup", 570, 585);
+    client.push_moves(&store, &mut sync_state);
+
+    let (start, end) = get_day();
+    client
+        .pull(&mut store, &mut sync_state, &start, &end)
+        .unwrap();
+
+    let standup = find_block(&store, "Standup");
+    assert_eq!(
+        (standup.block.start_minute, standup.block.end_minute),
+        (600, 615)
+    );
+    assert!(client.push_moves(&store, &mut sync_state).is_empty());
+}
+
+#[test]
+fn pull_moves_blocks_of_events_moved_to_another_day() {
+    let (server, client, mut store, mut sync_state) = pull_two_events();
+    server.put(
+        "standup.ics",
+        &get_event("standup", "20240502T100000", "20240502T101500", "Standup"),
+    );
+
+    let (start, next_day) = get_day();
+    let end = Day(String::from("2024-05-03"));
+    client
+        .pull(&mut store, &mut sync_state, &start, &end)
+        .unwrap();
+
+    let labels: Vec<String> = store
+        .list(&start)
+        .unwrap()
+        .into_iter()
+        .map(|record| record.block.label)
+        .collect();
+    assert_eq!(labels, ["Review"]);
+    let moved = store.list(&next_day).unwrap();
+    assert_eq!(moved.len(), 1);
+    assert_eq!(moved[0].block.label, "Standup");
+    assert_eq!(
+        (moved[0].block.start_minute, moved[0].block.end_minute),
+        (600, 615)
+    );
+    assert!(client.push_moves(&store, &mut sync_state).is_empty());
+}
+
+#[test]
+fn create_event_fails_if_the_event_already_exists() {
+    let server = StubServer::start();
+    let client = CalDavClient::new(&server.calendar_url);
+    let (day, _) = get_day();
+    let block = CalendarBlock {
+        id: Uuid::new_v4(),
+        start_minute: 1380,
+        end_minute: 1440,
+        block_type: CalendarBlockType::Busy,
+        subtree_depth: 0,
+        label: String::from("Late"),
+        calendar_id: DEFAULT_CALENDAR_ID.to_string(),
+        metadata: BlockMetadata::default(),
+    };
+
+    let created = client.create_event(&day, &block).unwrap();
+    assert!(created.calendar_data.contains("DTEND:20240502T000000"));
+    assert!(matches!(
+        client.create_event(&day, &block),
+        Err(CalDavError::Conflict(_))
+    ));
+}